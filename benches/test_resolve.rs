@@ -3,7 +3,10 @@ use std::env;
 use boom_config::Config;
 use boom_core::{
     SourceIdentifier,
-    boom::{parse_bangs::parse_bang_file, resolver::resolve},
+    boom::{
+        parse_bangs::parse_bang_file,
+        resolver::{resolve, resolve_into},
+    },
     cache::{init_list, insert_bang},
 };
 
@@ -39,3 +42,13 @@ fn bench_resolve(query: &str) {
     #[allow(unused_must_use)]
     resolve(query, &Config::default(), &SourceIdentifier::default());
 }
+
+#[divan::bench(args = STRINGS, sample_count = 10_000)]
+fn bench_resolve_into(bencher: divan::Bencher, query: &str) {
+    let config = Config::default();
+    let mut url = String::with_capacity(1024);
+    bencher.bench_local(|| {
+        url.clear();
+        resolve_into(query, &config, &mut url);
+    });
+}
@@ -1,4 +1,8 @@
-use boom_core::boom::parse_templates::parse_template_indexes;
+use boom_core::{boom::parse_templates::parse_template_indexes, template::Template};
+use divan::{Bencher, black_box};
+
+const QUERY: &str = "this is a test";
+const ENCODED_QUERY: &str = "this%20is%20a%20test";
 
 static STRINGS: [&str; 1589] = [
     "http://www.01net.com/recherche/recherche.php?searchstring={{{s}}}&chaine=home",
@@ -1601,3 +1605,42 @@ fn bench_templates(template: &str) {
     #[allow(unused_must_use)]
     parse_template_indexes(template);
 }
+
+#[divan::bench(args = STRINGS)]
+fn bench_compile_template(template: &str) {
+    #[allow(unused_must_use)]
+    Template::compile(template);
+}
+
+/// Renders every template by parsing it first, as was done for every request before templates
+/// were compiled at load time.
+#[divan::bench]
+fn bench_render_parsed(bencher: Bencher) {
+    bencher.bench(|| {
+        for template in STRINGS {
+            let matches = parse_template_indexes(template).unwrap_or_default();
+            let mut url = String::with_capacity(template.len() + ENCODED_QUERY.len());
+            let mut literal_start = 0;
+            for m in matches.into_iter().filter(|m| !m.is_empty()) {
+                url.push_str(&template[literal_start..m.start]);
+                url.push_str(ENCODED_QUERY);
+                literal_start = m.end;
+            }
+            url.push_str(&template[literal_start..]);
+            black_box(url);
+        }
+    });
+}
+
+#[divan::bench]
+fn bench_render_compiled(bencher: Bencher) {
+    let compiled: Vec<Template> = STRINGS
+        .iter()
+        .map(|template| Template::compile(*template).unwrap())
+        .collect();
+    bencher.bench(|| {
+        for template in &compiled {
+            black_box(template.render(QUERY));
+        }
+    });
+}
@@ -1,9 +1,10 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     env,
     fmt::Display,
     net::{IpAddr, Ipv4Addr},
     path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use merge::Merge;
@@ -23,6 +24,9 @@ pub struct Config {
     pub config_source: PathBuf,
     pub server: ServerConfig,
     pub bangs: BangConfig,
+    /// Values substituted for names within queries, by name.
+    pub snippets: HashMap<String, SnippetConfig>,
+    pub plugins: PluginConfig,
 }
 
 /// Uses [`env::var`] to find the best place to store/find the config.
@@ -59,6 +63,12 @@ pub struct ServerConfig {
     pub port: u16,
     pub wait_for_internet: bool,
     pub search_suggestions: String,
+    /// Only paths beneath this prefix are resolved as go-links, such as `/go/jira/PROJ-1` for
+    /// `"go"`. Otherwise, every path which is not a built-in route is.
+    pub path_prefix: Option<String>,
+    /// The hostname which `/proxy.pac` sends to `boom`, so that `http://go/jira/PROJ-1` resolves
+    /// like `!jira PROJ-1`.
+    pub pseudo_host: String,
 }
 
 impl Default for ServerConfig {
@@ -68,6 +78,8 @@ impl Default for ServerConfig {
             port: 3000,
             wait_for_internet: false,
             search_suggestions: "https://search.brave.com/api/suggest?q={searchTerms}".to_string(),
+            path_prefix: None,
+            pseudo_host: "go".to_string(),
         }
     }
 }
@@ -77,6 +89,8 @@ pub struct BangConfig {
     pub default_search_template: String,
     pub sources: Vec<BangSourceConfig>,
     pub custom: HashMap<String, BangCustomConfig>,
+    /// Charsets for bangs imported from sources, by trigger (see [`BangCustomConfig::encoding`]).
+    pub encodings: HashMap<String, String>,
 }
 
 impl Default for BangConfig {
@@ -85,6 +99,7 @@ impl Default for BangConfig {
             default_search_template: "https://google.com/search?q={{{s}}}".to_string(),
             sources: vec![BangSourceConfig::default()],
             custom: HashMap::new(),
+            encodings: HashMap::new(),
         }
     }
 }
@@ -121,10 +136,249 @@ impl Display for BangSourceConfig {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct BangCustomConfig {
     pub short_name: String,
     pub template: String,
+    /// Encode every placeholder within `template` as part of this component, rather than the
+    /// component it sits within.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escape: Option<UrlComponent>,
+    /// The charset the site expects its queries in, such as `"shift_jis"`. Queries are
+    /// transcoded into it before being percent-encoded. Defaults to UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// Named values which `template` references as `{{{name}}}`, in the order they are asked for.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<BangParamConfig>,
+    /// How `template` is requested. Sites which only accept form submissions need `"post"`.
+    #[serde(default, skip_serializing_if = "HttpMethod::is_get")]
+    pub method: HttpMethod,
+    /// The form fields submitted alongside a `"post"` request, by name. Values may contain the
+    /// same placeholders as `template`, which are inserted without being percent-encoded.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+    /// Treats `template` as an endpoint which is asked for the url to redirect to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<WebhookConfig>,
+    /// Templates of other instances of the same site, which are chosen between alongside
+    /// `template`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirrors: Option<MirrorConfig>,
+    /// When the bang expires, in seconds since the Unix epoch. Expired bangs are no longer loaded,
+    /// and are pruned from the config whenever it is next written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+impl BangCustomConfig {
+    /// Whether the bang has expired by `now`, in seconds since the Unix epoch.
+    #[must_use]
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// The current time, in seconds since the Unix epoch, as `expires_at` is given in.
+///
+/// # Panics
+/// If the system clock is set before the Unix epoch.
+#[must_use]
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+/// The mirrors of a bang, such as the instances of a privacy front-end.
+///
+/// Mirrors which fail their health checks are skipped for as long as they keep failing.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MirrorConfig {
+    /// Templates which may contain the same placeholders as the bang's own `template`.
+    pub templates: Vec<String>,
+    #[serde(default)]
+    pub strategy: MirrorStrategy,
+}
+
+/// How a bang chooses between its template and those of its mirrors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MirrorStrategy {
+    /// Each healthy template in turn.
+    #[default]
+    RoundRobin,
+    /// Any healthy template.
+    Random,
+    /// The first healthy template, starting with the bang's own.
+    FirstHealthy,
+}
+
+/// How a bang whose url is given by an endpoint (its `template`) is resolved.
+///
+/// The endpoint is sent the query, along with details of the request, as JSON within a POST
+/// request. It should respond with JSON such as `{ "url": "https://..." }`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WebhookConfig {
+    /// How long to wait for the endpoint, in milliseconds.
+    #[serde(default = "WebhookConfig::default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// How long the url given for a query is reused for, in seconds. Not cached if 0.
+    #[serde(default)]
+    pub cache_ttl: u64,
+    /// The template used whenever the endpoint fails to give a url. Defaults to the
+    /// `default_search_template`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<String>,
+}
+
+impl WebhookConfig {
+    const fn default_timeout_ms() -> u64 {
+        1000
+    }
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: Self::default_timeout_ms(),
+            cache_ttl: 0,
+            fallback: None,
+        }
+    }
+}
+
+/// How the url of a bang is requested.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpMethod {
+    /// The user is redirected to the url.
+    #[default]
+    Get,
+    /// The user submits a form to the url, which `boom` submits on their behalf.
+    Post,
+}
+
+impl HttpMethod {
+    #[must_use]
+    pub const fn is_get(&self) -> bool {
+        matches!(self, Self::Get)
+    }
+}
+
+/// A parameter of a custom bang, given within queries as `name=value`, such as
+/// `!jira project=BOOM login`.
+///
+/// Whenever a required parameter is missing, `boom` asks for it with a form.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BangParamConfig {
+    pub name: String,
+    /// Shown beside the parameter within the form. Defaults to `name`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub label: String,
+    /// Used whenever the query does not give a value. Parameters without one are required.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    /// The only values the parameter may take, if any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+}
+
+impl BangParamConfig {
+    #[must_use]
+    pub fn label(&self) -> &str {
+        if self.label.is_empty() {
+            &self.name
+        } else {
+            &self.label
+        }
+    }
+
+    /// Whether `value` may be given for the parameter.
+    #[must_use]
+    pub fn accepts(&self, value: &str) -> bool {
+        self.choices.is_empty() || self.choices.iter().any(|choice| choice == value)
+    }
+}
+
+/// The part of a url which a search query is placed within, deciding how it is percent-encoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UrlComponent {
+    /// Before any `?` or `#`. Slashes are kept, so that `!gh owner/repo` works.
+    #[default]
+    Path,
+    /// After a `?`. Everything but unreserved characters is escaped.
+    Query,
+    /// After a `#`. Slashes and question marks are kept.
+    Fragment,
+}
+
+/// A value substituted for its name within queries, before any bang is resolved, such that
+/// `@org/boom !gh` can mean `tobybridle/boom !gh`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum SnippetConfig {
+    /// Expanded within every query.
+    Value(String),
+    /// Expanded only within queries using one of `bangs`, given by their trigger.
+    Scoped { value: String, bangs: Vec<String> },
+}
+
+impl SnippetConfig {
+    #[must_use]
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Value(value) | Self::Scoped { value, .. } => value,
+        }
+    }
+
+    /// Whether the snippet is expanded within a query using the bang `trigger`, if any.
+    #[must_use]
+    pub fn applies_to(&self, trigger: Option<&str>) -> bool {
+        match self {
+            Self::Value(_) => true,
+            Self::Scoped { bangs, .. } => trigger.is_some_and(|trigger| {
+                bangs
+                    .iter()
+                    .any(|bang| bang.strip_prefix('!').unwrap_or(bang) == trigger)
+            }),
+        }
+    }
+}
+
+/// WebAssembly plugins, which are given queries before the `default_search_template` (only
+/// loaded with the `plugins` feature).
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PluginConfig {
+    /// Where the plugins (`*.wasm`) are loaded from, relative to the directory of the config.
+    pub dir: PathBuf,
+    /// How much fuel (roughly, how many instructions) a plugin may use for each query.
+    pub fuel: u64,
+    /// How much memory a plugin may use, in bytes.
+    pub max_memory: usize,
+}
+
+impl PluginConfig {
+    /// The directory plugins are loaded from, given the config was read from `config_source`.
+    #[must_use]
+    pub fn dir(&self, config_source: &Path) -> PathBuf {
+        config_source
+            .parent()
+            .map_or_else(|| self.dir.clone(), |parent| parent.join(&self.dir))
+    }
+}
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("plugins"),
+            fuel: 10_000_000,
+            max_memory: 16 * 1024 * 1024,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Merge, Deserialize, Serialize)]
@@ -137,6 +391,12 @@ pub struct ConfigBuilder {
     server: Option<ServerConfigBuilder>,
     #[merge(strategy = merge::option::overwrite_none)]
     bangs: Option<BangConfigBuilder>,
+    #[merge(strategy = merge::hashmap::overwrite)]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    snippets: HashMap<String, SnippetConfig>,
+    #[merge(strategy = merge::option::overwrite_none)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    plugins: Option<PluginConfig>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Default, Merge, Deserialize, Serialize)]
@@ -149,6 +409,10 @@ pub struct ServerConfigBuilder {
     pub wait_for_internet: Option<bool>,
     #[merge(strategy = merge::option::overwrite_none)]
     pub search_suggestions: Option<String>,
+    #[merge(strategy = merge::option::overwrite_none)]
+    pub path_prefix: Option<String>,
+    #[merge(strategy = merge::option::overwrite_none)]
+    pub pseudo_host: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Default, Merge, Deserialize, Serialize)]
@@ -161,6 +425,9 @@ pub struct BangConfigBuilder {
     pub sources: Option<Vec<BangSourceConfigBuilder>>,
     #[merge(strategy = merge::hashmap::overwrite)]
     pub custom: HashMap<String, BangCustomConfig>,
+    #[merge(strategy = merge::hashmap::overwrite)]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub encodings: HashMap<String, String>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Default, Merge, Deserialize, Serialize)]
@@ -247,13 +514,36 @@ impl ConfigBuilder {
             config_source: self.config_source,
             server: self.server.unwrap_or_default().into(),
             bangs: self.bangs.unwrap_or_default().into(),
+            snippets: self.snippets,
+            plugins: self.plugins.unwrap_or_default(),
+        }
+    }
+
+    /// Removes every custom bang which has expired by `now` (see [`BangCustomConfig::expires_at`]),
+    /// returning their triggers.
+    pub fn prune_expired(&mut self, now: u64) -> Vec<String> {
+        let Some(bangs) = self.bangs.as_mut() else {
+            return vec![];
+        };
+        let expired: Vec<String> = bangs
+            .custom
+            .iter()
+            .filter(|(_, custom)| custom.is_expired(now))
+            .map(|(trigger, _)| trigger.clone())
+            .collect();
+        for trigger in &expired {
+            bangs.custom.remove(trigger);
         }
+        expired
     }
 
+    /// Writes the config back to its source, without any custom bangs which have expired.
+    ///
     /// # Panics
     /// - `self` can't be serialized back into TOML format
     /// - [`self::config_source`] is not writeable
-    pub fn serialize(self) {
+    pub fn serialize(mut self) {
+        self.prune_expired(unix_timestamp());
         let ser = toml::ser::to_string_pretty(&self).expect("Config should be serializable");
         std::fs::write(&self.config_source, ser)
             .unwrap_or_else(|_| panic!("{} should be writeable", self.config_source.display()));
@@ -267,6 +557,8 @@ impl From<ServerConfig> for ServerConfigBuilder {
             port: Some(config.port),
             wait_for_internet: Some(config.wait_for_internet),
             search_suggestions: Some(config.search_suggestions),
+            path_prefix: config.path_prefix,
+            pseudo_host: Some(config.pseudo_host),
         }
     }
 }
@@ -283,6 +575,8 @@ impl From<ServerConfigBuilder> for ServerConfig {
             search_suggestions: builder
                 .search_suggestions
                 .unwrap_or(default.search_suggestions),
+            path_prefix: builder.path_prefix.or(default.path_prefix),
+            pseudo_host: builder.pseudo_host.unwrap_or(default.pseudo_host),
         }
     }
 }
@@ -293,6 +587,7 @@ impl From<BangConfig> for BangConfigBuilder {
             default_search_template: Some(config.default_search_template),
             sources: Some(config.sources.into_iter().map(Into::into).collect()),
             custom: config.custom,
+            encodings: config.encodings,
         }
     }
 }
@@ -308,6 +603,7 @@ impl From<BangConfigBuilder> for BangConfig {
                 sources.into_iter().map(Into::into).collect()
             }),
             custom: builder.custom,
+            encodings: builder.encodings,
         }
     }
 }
@@ -339,6 +635,8 @@ impl From<Config> for ConfigBuilder {
             config_source: config.config_source,
             server: Some(config.server.into()),
             bangs: Some(config.bangs.into()),
+            snippets: config.snippets,
+            plugins: (config.plugins != PluginConfig::default()).then_some(config.plugins),
         }
     }
 }
@@ -44,6 +44,7 @@ mod test {
             Some(&BangCustomConfig {
                 short_name: "boomdev".to_string(),
                 template: "https://github.com/tobybridle/boom".to_string(),
+                ..Default::default()
             })
         );
         assert_eq!(
@@ -51,7 +52,257 @@ mod test {
             Some(&BangCustomConfig {
                 short_name: "amazingdev".to_string(),
                 template: "https://github.com/tobybridle/{{{s}}}".to_string(),
+                ..Default::default()
             })
         )
     }
+
+    #[test]
+    fn test_config_parse_escape() {
+        let parsed_config = toml::from_str::<ConfigBuilder>(
+            r#"
+            [bangs.custom.wiki]
+            short_name = "Wiki"
+            template = "https://wiki.example.com/{{{s}}}"
+            escape = "query"
+            "#,
+        )
+        .expect("Config should be properly formatted.")
+        .build();
+        assert_eq!(
+            parsed_config.bangs.custom.get("wiki").unwrap().escape,
+            Some(crate::UrlComponent::Query)
+        );
+    }
+
+    #[test]
+    fn test_config_parse_encoding() {
+        let parsed_config = toml::from_str::<ConfigBuilder>(
+            r#"
+            [bangs.custom.kakaku]
+            short_name = "Kakaku"
+            template = "https://kakaku.com/search_results/{{{s}}}"
+            encoding = "shift_jis"
+
+            [bangs.encodings]
+            yandex = "windows-1251"
+            "#,
+        )
+        .expect("Config should be properly formatted.")
+        .build();
+        assert_eq!(
+            parsed_config.bangs.custom["kakaku"].encoding.as_deref(),
+            Some("shift_jis")
+        );
+        assert_eq!(
+            parsed_config
+                .bangs
+                .encodings
+                .get("yandex")
+                .map(String::as_str),
+            Some("windows-1251")
+        );
+    }
+
+    #[test]
+    fn test_config_parse_snippets() {
+        let parsed_config = toml::from_str::<ConfigBuilder>(
+            r#"
+            [snippets]
+            "@org" = "tobybridle"
+            "@repo" = { value = "tobybridle/boom", bangs = ["gh", "!ghi"] }
+            "#,
+        )
+        .expect("Config should be properly formatted.")
+        .build();
+
+        let org = &parsed_config.snippets["@org"];
+        assert_eq!(org.value(), "tobybridle");
+        assert!(org.applies_to(None));
+
+        let repo = &parsed_config.snippets["@repo"];
+        assert_eq!(repo.value(), "tobybridle/boom");
+        assert!(repo.applies_to(Some("gh")));
+        assert!(repo.applies_to(Some("ghi")));
+        assert!(!repo.applies_to(Some("yt")));
+        assert!(!repo.applies_to(None));
+    }
+
+    #[test]
+    fn test_config_parse_params() {
+        let parsed_config = toml::from_str::<ConfigBuilder>(
+            r#"
+            [bangs.custom.jira]
+            short_name = "Jira"
+            template = "https://jira.example.com/issues/?jql=project={{{project}}}%20AND%20type={{{type}}}&text={{{s}}}"
+
+            [[bangs.custom.jira.params]]
+            name = "project"
+            label = "Project"
+
+            [[bangs.custom.jira.params]]
+            name = "type"
+            default = "bug"
+            choices = ["bug", "story", "epic"]
+            "#,
+        )
+        .expect("Config should be properly formatted.")
+        .build();
+
+        let params = &parsed_config.bangs.custom["jira"].params;
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0].label(), "Project");
+        assert!(params[0].default.is_none());
+        assert!(params[0].accepts("ANYTHING"));
+        assert_eq!(params[1].label(), "type");
+        assert_eq!(params[1].default.as_deref(), Some("bug"));
+        assert!(params[1].accepts("story"));
+        assert!(!params[1].accepts("task"));
+    }
+
+    #[test]
+    fn test_config_parse_post() {
+        let parsed_config = toml::from_str::<ConfigBuilder>(
+            r#"
+            [bangs.custom.intranet]
+            short_name = "Intranet"
+            template = "https://intranet.example.com/search"
+            method = "post"
+            fields = { query = "{{{s}}}", lang = "{{{lang}}}" }
+            "#,
+        )
+        .expect("Config should be properly formatted.")
+        .build();
+
+        let intranet = &parsed_config.bangs.custom["intranet"];
+        assert_eq!(intranet.method, crate::HttpMethod::Post);
+        assert_eq!(
+            intranet.fields.get("query").map(String::as_str),
+            Some("{{{s}}}")
+        );
+        assert_eq!(intranet.fields.len(), 2);
+    }
+
+    #[test]
+    fn test_config_parse_webhook() {
+        let parsed_config = toml::from_str::<ConfigBuilder>(
+            r#"
+            [bangs.custom.build]
+            short_name = "Latest build"
+            template = "https://ci.example.com/api/latest?branch={{{s}}}"
+            webhook = { cache_ttl = 60, fallback = "https://ci.example.com/builds?q={{{s}}}" }
+
+            [bangs.custom.oncall]
+            short_name = "On-call"
+            template = "https://oncall.example.com/api/dashboard"
+            webhook = {}
+            "#,
+        )
+        .expect("Config should be properly formatted.")
+        .build();
+
+        let build = parsed_config.bangs.custom["build"]
+            .webhook
+            .as_ref()
+            .unwrap();
+        assert_eq!(build.timeout_ms, 1000);
+        assert_eq!(build.cache_ttl, 60);
+        assert_eq!(
+            build.fallback.as_deref(),
+            Some("https://ci.example.com/builds?q={{{s}}}")
+        );
+        assert_eq!(
+            parsed_config.bangs.custom["oncall"].webhook,
+            Some(crate::WebhookConfig::default())
+        );
+    }
+
+    #[test]
+    fn test_config_parse_mirrors() {
+        let parsed_config = toml::from_str::<ConfigBuilder>(
+            r#"
+            [bangs.custom.yt]
+            short_name = "Invidious"
+            template = "https://yewtu.be/search?q={{{s}}}"
+            mirrors = { templates = ["https://inv.nadeko.net/search?q={{{s}}}"], strategy = "first-healthy" }
+
+            [bangs.custom.sx]
+            short_name = "SearXNG"
+            template = "https://searx.be/search?q={{{s}}}"
+            mirrors = { templates = ["https://search.sapti.me/search?q={{{s}}}"] }
+            "#,
+        )
+        .expect("Config should be properly formatted.")
+        .build();
+
+        let yt = parsed_config.bangs.custom["yt"].mirrors.as_ref().unwrap();
+        assert_eq!(yt.templates.len(), 1);
+        assert_eq!(yt.strategy, crate::MirrorStrategy::FirstHealthy);
+        assert_eq!(
+            parsed_config.bangs.custom["sx"]
+                .mirrors
+                .as_ref()
+                .unwrap()
+                .strategy,
+            crate::MirrorStrategy::RoundRobin
+        );
+    }
+
+    #[test]
+    fn test_config_prune_expired() {
+        let mut builder = toml::from_str::<ConfigBuilder>(
+            r#"
+            [bangs.custom.inc]
+            short_name = "Incident"
+            template = "https://docs.example.com/incidents/42?q={{{s}}}"
+            expires_at = 1700000000
+
+            [bangs.custom.rel]
+            short_name = "Release"
+            template = "https://docs.example.com/releases/7?q={{{s}}}"
+            expires_at = 1800000000
+
+            [bangs.custom.gh]
+            short_name = "GitHub"
+            template = "https://github.com/{{{s}}}"
+            "#,
+        )
+        .expect("Config should be properly formatted.");
+
+        assert_eq!(
+            builder.prune_expired(1_750_000_000),
+            vec!["inc".to_string()]
+        );
+        let parsed_config = builder.build();
+        assert!(!parsed_config.bangs.custom.contains_key("inc"));
+        assert_eq!(
+            parsed_config.bangs.custom["rel"].expires_at,
+            Some(1_800_000_000)
+        );
+        assert!(!parsed_config.bangs.custom["gh"].is_expired(u64::MAX));
+    }
+
+    #[test]
+    fn test_config_parse_plugins() {
+        let parsed_config = toml::from_str::<ConfigBuilder>(
+            r#"
+            [plugins]
+            fuel = 1000
+            "#,
+        )
+        .expect("Config should be properly formatted.")
+        .build();
+
+        assert_eq!(parsed_config.plugins.fuel, 1000);
+        assert_eq!(
+            parsed_config
+                .plugins
+                .dir(&PathBuf::from("/home/boom/.config/boom/config.toml")),
+            PathBuf::from("/home/boom/.config/boom/plugins")
+        );
+        assert_eq!(
+            toml::from_str::<ConfigBuilder>("").unwrap().build().plugins,
+            crate::PluginConfig::default()
+        );
+    }
 }
@@ -1,12 +1,35 @@
 pub mod add_external_sources;
+pub mod bang_cache;
+pub mod form;
 pub mod grab_remote_bangs;
+pub mod macros;
+pub mod mirrors;
+pub mod normalize;
+pub mod params;
 pub mod parse_bangs;
 pub mod parse_templates;
+pub mod pipeline;
+#[cfg(feature = "plugins")]
+pub mod plugins;
 pub mod resolver;
+pub mod snippets;
+pub mod trigger_index;
 pub mod update_bangs_from_config;
+pub mod webhook;
 
 use std::{cmp::max, ops::Range};
 
+/// Whether the SIMD parsers can be used on this CPU, which is detected at runtime.
+#[inline]
+#[must_use]
+pub fn simd_available() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    return std::arch::is_x86_feature_detected!("avx2");
+
+    #[cfg(not(target_arch = "x86_64"))]
+    false
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
 pub struct Match {
     /// Inclusive start index of a match
@@ -1,4 +1,6 @@
 use std::{
+    collections::HashSet,
+    error::Error,
     process::exit,
     sync::{Arc, RwLock},
 };
@@ -8,8 +10,11 @@ use tokio::task::JoinSet;
 use tracing::{error, info, warn};
 
 use crate::{
-    Redirect,
-    boom::{grab_remote_bangs::download_remote, parse_bangs::parse_bang_file},
+    BangOrigin, Redirect,
+    boom::{
+        bang_cache::{find_bangs, load_bangs},
+        grab_remote_bangs::download_remote,
+    },
 };
 use expanduser::expanduser;
 
@@ -19,30 +24,66 @@ use expanduser::expanduser;
 ///
 /// > **NOTE**: This function may error, without causing a [`panic!`] or exiting the process.
 /// > Error/warning logs will be produced, though the program will continue as usual, if the source
-/// > was not required.
+/// > was not required. The process exits should a required source fail, unlike with
+/// > [`try_add_external_sources`].
 pub async fn add_external_sources(
     sources: Arc<&[BangSourceConfig]>,
     bangs: Arc<RwLock<Vec<Redirect>>>,
     use_cache: bool,
 ) {
+    if let Err(e) = try_add_external_sources(sources, bangs, use_cache).await {
+        error!("{e}");
+        exit(1);
+    }
+}
+
+/// Like [`add_external_sources`], but without exiting the process should a required source fail.
+///
+/// # Errors
+/// If a required source could not be fetched or read, in which case nothing is added to `bangs`.
+pub async fn try_add_external_sources(
+    sources: Arc<&[BangSourceConfig]>,
+    bangs: Arc<RwLock<Vec<Redirect>>>,
+    use_cache: bool,
+) -> Result<(), Box<dyn Error>> {
+    add_sources(&sources, bangs, use_cache, None).await
+}
+
+/// Like [`try_add_external_sources`], but only adds the bangs whose trigger is within `triggers`,
+/// looking them up through the trigger index of each source's cache (see [`find_bangs`]).
+///
+/// # Errors
+/// If a required source could not be fetched or read, in which case nothing is added to `bangs`.
+pub async fn try_add_external_triggers(
+    sources: Arc<&[BangSourceConfig]>,
+    bangs: Arc<RwLock<Vec<Redirect>>>,
+    use_cache: bool,
+    triggers: Arc<HashSet<String>>,
+) -> Result<(), Box<dyn Error>> {
+    add_sources(&sources, bangs, use_cache, Some(triggers)).await
+}
+
+async fn add_sources(
+    sources: &[BangSourceConfig],
+    bangs: Arc<RwLock<Vec<Redirect>>>,
+    use_cache: bool,
+    triggers: Option<Arc<HashSet<String>>>,
+) -> Result<(), Box<dyn Error>> {
     let mut set = JoinSet::new();
 
     for source in sources.iter().cloned() {
+        let triggers = triggers.clone();
         set.spawn(async move {
             if !use_cache && let Some(remote) = &source.remote {
                 match download_remote(remote, &source.filepath).await {
                     Ok(()) => info!("Fetched bangs from {source}"),
                     Err(e) => {
                         if source.required {
-                            error!(
-                                "Could not fetch bangs from remote source {source}. Error: {e:?}"
-                            );
-                            exit(1);
-                        } else {
-                            warn!(
+                            return Err(format!(
                                 "Could not fetch bangs from remote source {source}. Error: {e:?}"
-                            );
+                            ));
                         }
+                        warn!("Could not fetch bangs from remote source {source}. Error: {e:?}");
                     }
                 }
             }
@@ -58,32 +99,45 @@ pub async fn add_external_sources(
                     },
                 );
 
-            match parse_bang_file(&filepath) {
-                Ok(bangs) => {
+            let loaded = match &triggers {
+                Some(triggers) => find_bangs(&filepath, triggers),
+                None => load_bangs(&filepath),
+            };
+            match loaded {
+                Ok(mut bangs) => {
                     info!("Loaded {} bangs from source {}", bangs.len(), source);
-                    bangs
+                    let origin = Arc::new(BangOrigin::Source {
+                        filepath,
+                        remote: source.remote.clone(),
+                    });
+                    for bang in &mut bangs {
+                        bang.origin = Arc::clone(&origin);
+                    }
+                    Ok(bangs)
                 }
                 Err(e) => {
                     if source.required {
-                        error!("Could not read bang source {source}. Error: {e:?}");
-                        exit(1);
-                    } else {
-                        warn!("Skipping bang source {source}. Error: {e:?}");
-                        vec![]
+                        return Err(format!("Could not read bang source {source}. Error: {e:?}"));
                     }
+                    warn!("Skipping bang source {source}. Error: {e:?}");
+                    Ok(vec![])
                 }
             }
         });
     }
 
+    let mut loaded = vec![];
     while let Some(res) = set.join_next().await {
-        let Ok(mut lock) = bangs.write() else {
-            error!("Could not acquire write lock on bangs.");
-            continue;
-        };
-        lock.extend(res.unwrap_or_else(|_| {
+        loaded.extend(res.unwrap_or_else(|_| {
             warn!("Unable to get Redirects from JoinSet");
-            vec![]
-        }));
+            Ok(vec![])
+        })?);
     }
+
+    let Ok(mut lock) = bangs.write() else {
+        error!("Could not acquire write lock on bangs.");
+        return Ok(());
+    };
+    lock.extend(loaded);
+    Ok(())
 }
@@ -0,0 +1,523 @@
+//! A versioned, binary copy of a JSON bang source, stored next to it.
+//!
+//! Parsing the ~2 MB of JSON making up the default bangs dominates a cold start, so the parsed
+//! bangs are written into a flat file which can be memory-mapped and read without any parsing.
+//! The file holds a header, a table of bangs, an index of that table sorted by trigger, and a
+//! text section which every bang points into:
+//!
+//! ```text
+//! header | entries (count * ENTRY_SIZE) | sorted trigger index (count * u32) | text
+//! ```
+//!
+//! Resolving a single query from a cold start only looks up the bangs it needs through the index
+//! (see [`find_bangs`]), whilst everything else copies every bang out of the map (see
+//! [`load_bangs`]).
+//!
+//! Every number is a little-endian `u32` or `u64`, and nothing relies on alignment, so the file
+//! can be read in place. The cache is rebuilt whenever the length or modification time of its
+//! source changes, unless the contents of the source hash to the same value as before.
+
+use std::{
+    collections::HashSet,
+    error::Error,
+    fs::{self, File},
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use memmap2::Mmap;
+use tracing::{debug, warn};
+
+use crate::{Redirect, boom::parse_bangs::parse_bang_file};
+
+const MAGIC: &[u8; 8] = b"BOOMBANG";
+/// Bump whenever the layout of the file changes, so that older caches are rebuilt.
+const FORMAT_VERSION: u32 = 3;
+/// Appended to the path of a source to get the path of its cache.
+pub const CACHE_EXTENSION: &str = "boomcache";
+
+const HEADER_SIZE: usize = 48;
+/// The short name, trigger and url template spans (start and length each), and the relevance.
+const ENTRY_SIZE: usize = 7 * 4;
+
+/// Identifies the version of a source a cache was built from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SourceStamp {
+    len: u64,
+    mtime_secs: u64,
+    mtime_nanos: u32,
+}
+
+impl SourceStamp {
+    fn of(source: &Path) -> Result<Self, Box<dyn Error>> {
+        let metadata = fs::metadata(source)?;
+        let mtime = metadata.modified()?.duration_since(UNIX_EPOCH)?;
+        Ok(Self {
+            len: metadata.len(),
+            mtime_secs: mtime.as_secs(),
+            mtime_nanos: mtime.subsec_nanos(),
+        })
+    }
+}
+
+/// A memory-mapped bang cache.
+#[derive(Debug)]
+pub struct BangCache {
+    map: Mmap,
+    count: usize,
+    text_start: usize,
+}
+
+/// A single bang, borrowed from a [`BangCache`].
+#[derive(Debug, Clone, Copy)]
+pub struct CachedBang<'a> {
+    pub short_name: &'a str,
+    pub trigger: &'a str,
+    pub url_template: &'a str,
+    pub relevance: u32,
+}
+
+impl From<CachedBang<'_>> for Redirect {
+    fn from(bang: CachedBang<'_>) -> Self {
+        Self {
+            short_name: bang.short_name.to_string(),
+            trigger: bang.trigger.to_string(),
+            url_template: bang.url_template.to_string(),
+            relevance: bang.relevance,
+            ..Default::default()
+        }
+    }
+}
+
+/// The path at which the cache of `source` is stored.
+///
+/// # Example
+/// ```
+/// use std::path::Path;
+/// use boom_core::boom::bang_cache::cache_path;
+///
+/// assert_eq!(
+///     cache_path(Path::new("/tmp/bangs.json")),
+///     Path::new("/tmp/bangs.json.boomcache")
+/// );
+/// ```
+#[must_use]
+pub fn cache_path(source: &Path) -> PathBuf {
+    let mut path = source.as_os_str().to_owned();
+    path.push(".");
+    path.push(CACHE_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Loads the bangs of the JSON file at `source`, using its binary cache when it is up to date.
+///
+/// A missing, outdated or corrupt cache is (re)built from the parsed JSON. Failing to write the
+/// cache is only logged, as the bangs have been loaded regardless.
+///
+/// # Errors
+/// If the cache cannot be used, and `source` cannot be parsed (see [`parse_bang_file`]).
+pub fn load_bangs(source: &PathBuf) -> Result<Vec<Redirect>, Box<dyn Error>> {
+    if let Some(cached) = open_cache(source) {
+        debug!("Loaded {} bangs from the cache", cached.len());
+        return Ok(cached.iter().map(Redirect::from).collect());
+    }
+    rebuild_cache(source)
+}
+
+/// Loads only the bangs of the JSON file at `source` whose trigger is within `triggers`, looking
+/// them up through the trigger index of its binary cache (see [`BangCache::find`]).
+///
+/// A missing, outdated or corrupt cache is (re)built as with [`load_bangs`], in which case the
+/// parsed bangs are filtered instead.
+///
+/// # Errors
+/// If the cache cannot be used, and `source` cannot be parsed (see [`parse_bang_file`]).
+pub fn find_bangs(
+    source: &PathBuf,
+    triggers: &HashSet<String>,
+) -> Result<Vec<Redirect>, Box<dyn Error>> {
+    if let Some(cached) = open_cache(source) {
+        return Ok(triggers
+            .iter()
+            .flat_map(|trigger| cached.find(trigger))
+            .map(Redirect::from)
+            .collect());
+    }
+    let mut bangs = rebuild_cache(source)?;
+    bangs.retain(|bang| triggers.contains(&bang.trigger));
+    Ok(bangs)
+}
+
+/// Maps the cache of `source`, if it is up to date.
+fn open_cache(source: &Path) -> Option<BangCache> {
+    let cache = cache_path(source);
+    match BangCache::open(&cache, source) {
+        Ok(Some(cached)) => return Some(cached),
+        Ok(None) => debug!("Cache {} is outdated", cache.display()),
+        Err(e) => debug!("Cache {} could not be used: {e}", cache.display()),
+    }
+    None
+}
+
+/// Parses `source` and writes its cache anew.
+fn rebuild_cache(source: &PathBuf) -> Result<Vec<Redirect>, Box<dyn Error>> {
+    let cache = cache_path(source);
+    let bangs = parse_bang_file(source)?;
+    if let Err(e) = write_cache(&cache, source, &bangs) {
+        warn!(
+            "Could not write bang cache {}. Reason: {e}",
+            cache.display()
+        );
+    }
+    Ok(bangs)
+}
+
+/// Writes `bangs`, parsed from `source`, into the cache at `path`.
+///
+/// The cache is written to a temporary file first and then moved into place, so that a cache
+/// which is currently mapped is never modified.
+///
+/// # Errors
+/// - If the metadata or contents of `source` cannot be read
+/// - If the cache cannot be written
+/// - If the cache would not fit within the limits of the format (4 GiB of text)
+pub fn write_cache(path: &Path, source: &Path, bangs: &[Redirect]) -> Result<(), Box<dyn Error>> {
+    let stamp = SourceStamp::of(source)?;
+    let hash = hash_file(source)?;
+
+    let mut text = String::new();
+    let mut entries = Vec::with_capacity(bangs.len() * ENTRY_SIZE);
+    let mut push_str = |s: &str, entries: &mut Vec<u8>| -> Result<(), Box<dyn Error>> {
+        entries.extend(u32::try_from(text.len())?.to_le_bytes());
+        entries.extend(u32::try_from(s.len())?.to_le_bytes());
+        text.push_str(s);
+        Ok(())
+    };
+    for bang in bangs {
+        push_str(&bang.short_name, &mut entries)?;
+        push_str(&bang.trigger, &mut entries)?;
+        push_str(&bang.url_template, &mut entries)?;
+        entries.extend(bang.relevance.to_le_bytes());
+    }
+
+    // A stable sort, so that the definitions of a trigger stay in the order they were written
+    let mut index: Vec<u32> = (0..u32::try_from(bangs.len())?).collect();
+    index.sort_by(|&a, &b| bangs[a as usize].trigger.cmp(&bangs[b as usize].trigger));
+
+    let mut file = Vec::with_capacity(HEADER_SIZE + entries.len() + index.len() * 4 + text.len());
+    file.extend(MAGIC);
+    file.extend(FORMAT_VERSION.to_le_bytes());
+    file.extend(u32::try_from(bangs.len())?.to_le_bytes());
+    file.extend(stamp.len.to_le_bytes());
+    file.extend(stamp.mtime_secs.to_le_bytes());
+    file.extend(stamp.mtime_nanos.to_le_bytes());
+    file.extend(u32::try_from(text.len())?.to_le_bytes());
+    file.extend(hash.to_le_bytes());
+    debug_assert_eq!(file.len(), HEADER_SIZE);
+    file.extend(entries);
+    file.extend(index.into_iter().flat_map(u32::to_le_bytes));
+    file.extend(text.as_bytes());
+
+    Ok(replace_file(path, &file)?)
+}
+
+/// Records `stamp` within the cache at `path`, so that its source is not hashed again until it
+/// next changes.
+fn restamp(path: &Path, stamp: SourceStamp) -> Result<(), Box<dyn Error>> {
+    let mut file = fs::read(path)?;
+    if file.len() < HEADER_SIZE {
+        return Err("Bang cache is truncated".into());
+    }
+    file[16..24].copy_from_slice(&stamp.len.to_le_bytes());
+    file[24..32].copy_from_slice(&stamp.mtime_secs.to_le_bytes());
+    file[32..36].copy_from_slice(&stamp.mtime_nanos.to_le_bytes());
+    Ok(replace_file(path, &file)?)
+}
+
+/// Writes `contents` to a temporary file and then moves it over `path`, so that a cache which is
+/// currently mapped is never modified.
+fn replace_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = PathBuf::from(tmp_path);
+    let result = File::create(&tmp_path)
+        .and_then(|mut tmp| tmp.write_all(contents))
+        .and_then(|()| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+impl BangCache {
+    /// Maps the cache at `path`, if it was built from the current contents of `source`.
+    ///
+    /// A cache whose source has been modified is still used if the contents of the source hash
+    /// to the same value as they did when the cache was built, in which case the cache is
+    /// restamped with the modification time of the source.
+    ///
+    /// # Errors
+    /// - If either file cannot be read
+    /// - If the cache is not a valid cache, or was written by another version of the format
+    pub fn open(path: &Path, source: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        let file = File::open(path)?;
+        // SAFETY: The cache is only ever replaced by renaming a new file over it, never modified
+        // in place, so the mapped contents cannot change underneath us.
+        let map = unsafe { Mmap::map(&file)? };
+        let cache = Self::from_map(map)?;
+
+        let stamp = SourceStamp::of(source)?;
+        if stamp == cache.stamp() {
+            return Ok(Some(cache));
+        }
+        if stamp.len == cache.stamp().len && hash_file(source)? == cache.read_u64(40) {
+            if let Err(e) = restamp(path, stamp) {
+                warn!(
+                    "Could not restamp bang cache {}. Reason: {e}",
+                    path.display()
+                );
+            }
+            return Ok(Some(cache));
+        }
+        Ok(None)
+    }
+
+    fn from_map(map: Mmap) -> Result<Self, Box<dyn Error>> {
+        if map.len() < HEADER_SIZE || &map[..8] != MAGIC {
+            return Err("Not a bang cache".into());
+        }
+        let version = u32::from_le_bytes(map[8..12].try_into()?);
+        if version != FORMAT_VERSION {
+            return Err(format!("Unsupported bang cache version {version}").into());
+        }
+
+        let count = u32::from_le_bytes(map[12..16].try_into()?) as usize;
+        let text_len = u32::from_le_bytes(map[36..40].try_into()?) as usize;
+        let text_start = HEADER_SIZE + count * (ENTRY_SIZE + 4);
+        if map.len() != text_start + text_len {
+            return Err("Bang cache is truncated".into());
+        }
+
+        let cache = Self {
+            map,
+            count,
+            text_start,
+        };
+        let text = std::str::from_utf8(&cache.map[text_start..])?;
+        let valid_span = |span: Range<usize>| text.get(span).is_some();
+        let entries_valid =
+            (0..count).all(|idx| (0..3).all(|field| valid_span(cache.span(idx, field))));
+        let index_valid = (0..count).all(|pos| cache.index_at(pos) < count);
+        if !entries_valid || !index_valid {
+            return Err("Bang cache is corrupt".into());
+        }
+        Ok(cache)
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.count
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Gets the bang at `idx`, in the order they were written.
+    #[must_use]
+    pub fn get(&self, idx: usize) -> Option<CachedBang<'_>> {
+        if idx >= self.count {
+            return None;
+        }
+        Some(CachedBang {
+            short_name: self.str(self.span(idx, 0)),
+            trigger: self.str(self.span(idx, 1)),
+            url_template: self.str(self.span(idx, 2)),
+            relevance: self.read_u32(self.entry_offset(idx) + 24),
+        })
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = CachedBang<'_>> {
+        (0..self.count).map(|idx| self.get(idx).expect("Index should be within the cache"))
+    }
+
+    /// Every bang for `trigger`, in the order they were written, found through the sorted
+    /// trigger index.
+    pub fn find<'a>(&'a self, trigger: &'a str) -> impl Iterator<Item = CachedBang<'a>> {
+        let trigger_at = |pos| self.str(self.span(self.index_at(pos), 1));
+        let mut low = 0;
+        let mut high = self.count;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if trigger_at(mid) < trigger {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        (low..self.count)
+            .map(|pos| {
+                self.get(self.index_at(pos))
+                    .expect("Index should be within the cache")
+            })
+            .take_while(move |bang| bang.trigger == trigger)
+    }
+
+    fn stamp(&self) -> SourceStamp {
+        SourceStamp {
+            len: self.read_u64(16),
+            mtime_secs: self.read_u64(24),
+            mtime_nanos: self.read_u32(32),
+        }
+    }
+
+    const fn entry_offset(&self, idx: usize) -> usize {
+        HEADER_SIZE + idx * ENTRY_SIZE
+    }
+
+    fn index_at(&self, pos: usize) -> usize {
+        self.read_u32(HEADER_SIZE + self.count * ENTRY_SIZE + pos * 4) as usize
+    }
+
+    fn span(&self, idx: usize, field: usize) -> Range<usize> {
+        let offset = self.entry_offset(idx) + field * 8;
+        let start = self.read_u32(offset) as usize;
+        start..start + self.read_u32(offset + 4) as usize
+    }
+
+    fn str(&self, span: Range<usize>) -> &str {
+        let bytes = &self.map[self.text_start..][span];
+        // The text section, and every span within it, was validated when the cache was opened.
+        std::str::from_utf8(bytes).expect("Cached text should be valid UTF-8")
+    }
+
+    fn read_u32(&self, offset: usize) -> u32 {
+        u32::from_le_bytes(
+            self.map[offset..offset + 4]
+                .try_into()
+                .expect("Slice should be 4 bytes"),
+        )
+    }
+
+    fn read_u64(&self, offset: usize) -> u64 {
+        u64::from_le_bytes(
+            self.map[offset..offset + 8]
+                .try_into()
+                .expect("Slice should be 8 bytes"),
+        )
+    }
+}
+
+/// FNV-1a, which is plenty to tell whether a source has changed.
+fn hash_file(path: &Path) -> Result<u64, Box<dyn Error>> {
+    Ok(fs::read(path)?
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        }))
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use std::{env, fs, path::PathBuf};
+
+    #[allow(unused_imports)]
+    use super::{BangCache, SourceStamp, cache_path, find_bangs, load_bangs};
+
+    #[allow(dead_code)]
+    fn scratch_source(name: &str, contents: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("boom-bang-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join(name);
+        fs::write(&source, contents).unwrap();
+        let _ = fs::remove_file(cache_path(&source));
+        source
+    }
+
+    #[test]
+    fn test_bang_cache_round_trip() {
+        let source = scratch_source(
+            "round_trip.json",
+            r#"[
+                {"s": "YouTube", "t": "yt", "u": "https://youtube.com/results?search_query={{{s}}}", "r": 10},
+                {"s": "GitHub", "t": "gh", "u": "https://github.com/{{{s}}}"},
+                {"s": "Ünïcödé", "t": "ü", "u": "https://ü.com/{{{s}}}", "r": 3},
+                {"s": "GitHub Gists", "t": "gh", "u": "https://gist.github.com/{{{s}}}"}
+            ]"#,
+        );
+
+        let parsed = load_bangs(&source).unwrap();
+        let cache = BangCache::open(&cache_path(&source), &source)
+            .unwrap()
+            .expect("The cache should be up to date");
+        assert_eq!(cache.len(), parsed.len());
+        for (cached, parsed) in cache.iter().zip(&parsed) {
+            assert_eq!(cached.short_name, parsed.short_name);
+            assert_eq!(cached.trigger, parsed.trigger);
+            assert_eq!(cached.url_template, parsed.url_template);
+            assert_eq!(cached.relevance, parsed.relevance);
+        }
+
+        assert_eq!(cache.get(1).unwrap().short_name, "GitHub");
+        assert!(cache.get(4).is_none());
+        let find = |trigger| {
+            cache
+                .find(trigger)
+                .map(|bang| bang.short_name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(find("gh"), vec!["GitHub", "GitHub Gists"]);
+        assert_eq!(cache.find("ü").next().unwrap().relevance, 3);
+        assert!(find("g").is_empty());
+        assert!(find("zz").is_empty());
+
+        let triggers = ["yt", "gh", "g"].map(str::to_string).into();
+        assert_eq!(find_bangs(&source, &triggers).unwrap().len(), 3);
+
+        let reloaded = load_bangs(&source).unwrap();
+        assert_eq!(reloaded.len(), parsed.len());
+    }
+
+    #[test]
+    fn test_bang_cache_outdated() {
+        let source = scratch_source(
+            "outdated.json",
+            r#"[{"s": "a", "t": "a", "u": "https://a.com"}]"#,
+        );
+        assert_eq!(load_bangs(&source).unwrap().len(), 1);
+
+        fs::write(
+            &source,
+            r#"[{"s": "a", "t": "a", "u": "https://a.com"}, {"s": "b", "t": "b", "u": "https://b.com"}]"#,
+        )
+        .unwrap();
+        assert!(
+            BangCache::open(&cache_path(&source), &source)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(load_bangs(&source).unwrap().len(), 2);
+
+        // Rewriting the same contents only restamps the cache
+        let stamp = |source| {
+            BangCache::open(&cache_path(source), source)
+                .unwrap()
+                .unwrap()
+                .stamp()
+        };
+        let before = stamp(&source);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(&source, fs::read(&source).unwrap()).unwrap();
+        assert_ne!(SourceStamp::of(&source).unwrap(), before);
+        // The cache being opened is mapped before it is restamped
+        assert_eq!(stamp(&source), before);
+        assert_eq!(stamp(&source), SourceStamp::of(&source).unwrap());
+
+        fs::write(cache_path(&source), b"BOOMBANG but not really").unwrap();
+        assert!(BangCache::open(&cache_path(&source), &source).is_err());
+        assert_eq!(load_bangs(&source).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,49 @@
+//! Forms submitted on behalf of the user, for bangs whose `method` is `"post"` (see
+//! [`HttpMethod`](boom_config::HttpMethod)).
+//!
+//! Such a bang still resolves to a url, which its form is submitted to. The value of each field is
+//! a template, rendered as plain text (see [`render_parts_raw`]), since it is the browser which
+//! encodes the form.
+
+use serde::Serialize;
+
+use crate::{context::RequestContext, store::BangRef, template::render_parts_raw};
+
+/// The fields to submit to the url a query resolved to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PostForm {
+    /// The charset the form is submitted in, if not UTF-8.
+    pub charset: Option<&'static str>,
+    pub fields: Vec<FormField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FormField {
+    pub name: String,
+    pub value: String,
+}
+
+impl PostForm {
+    /// Renders every field of `bang`, inserting `terms` at every query placeholder and filling in
+    /// variables and parameters from `context`.
+    #[must_use]
+    pub fn render(bang: &BangRef, terms: &[&str], context: &RequestContext) -> Self {
+        let fields = bang
+            .fields()
+            .iter()
+            .map(|(name, template)| {
+                let mut value = String::with_capacity(template.source().len());
+                render_parts_raw(template.parts(), terms, context, &mut value);
+                FormField {
+                    name: name.clone(),
+                    value,
+                }
+            })
+            .collect();
+
+        Self {
+            charset: bang.charset().map(|charset| charset.name()),
+            fields,
+        }
+    }
+}
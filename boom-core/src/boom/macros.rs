@@ -0,0 +1,130 @@
+//! Bang macros: bangs whose template is itself a bang expression, such as
+//! `!gh tobybridle/{{{s}}}`, rather than a url.
+//!
+//! A macro is resolved by expanding its template with the query (unencoded), and then resolving
+//! the expansion in turn. Macros refer to their target by trigger, so they keep working whenever
+//! the target's template changes.
+//!
+//! Macros which form a cycle are rejected when bangs are loaded (see [`macro_cycles`]), whilst
+//! expansions nested deeper than [`MAX_MACRO_DEPTH`] fall back to the default search.
+
+use std::collections::{HashMap, HashSet};
+
+use super::normalize::trigger_candidates;
+
+/// How many macros may be expanded whilst resolving a single query.
+pub const MAX_MACRO_DEPTH: usize = 8;
+
+/// Whether `template` is a bang expression rather than a url.
+#[inline]
+#[must_use]
+pub fn is_macro(template: &str) -> bool {
+    template.starts_with('!')
+}
+
+/// The trigger a macro expands into, if it is known without resolving the macro.
+///
+/// # Example
+/// ```
+/// use boom_core::boom::macros::macro_target;
+///
+/// assert_eq!(macro_target("!gh tobybridle/{{{s}}}"), Some("gh"));
+/// assert_eq!(macro_target("!{{{s}}}"), None);
+/// assert_eq!(macro_target("https://github.com/{{{s}}}"), None);
+/// ```
+#[must_use]
+pub fn macro_target(template: &str) -> Option<&str> {
+    let target = template
+        .strip_prefix('!')?
+        .split_ascii_whitespace()
+        .next()?;
+    (!target.contains("{{{")).then_some(target)
+}
+
+/// Every cycle formed by the macros within `bangs`, given as `(trigger, template)` pairs. Each
+/// cycle lists its triggers in the order they expand into one another.
+///
+/// # Example
+/// ```
+/// use boom_core::boom::macros::macro_cycles;
+///
+/// let cycles = macro_cycles([
+///     ("a", "!b {{{s}}}"),
+///     ("b", "!a {{{s}}}"),
+///     ("c", "!gh {{{s}}}"),
+/// ]);
+/// assert_eq!(cycles.len(), 1);
+/// assert_eq!(cycles[0].len(), 2);
+/// ```
+#[must_use]
+pub fn macro_cycles<'a>(bangs: impl IntoIterator<Item = (&'a str, &'a str)>) -> Vec<Vec<&'a str>> {
+    let macros: HashMap<&str, &str> = bangs
+        .into_iter()
+        .filter(|(_, template)| is_macro(template))
+        .collect();
+    let lookup = |target: &str| {
+        trigger_candidates(target).find_map(|candidate| {
+            macros
+                .get_key_value(candidate)
+                .map(|(trigger, template)| (*trigger, *template))
+        })
+    };
+
+    let mut triggers: Vec<&str> = macros.keys().copied().collect();
+    triggers.sort_unstable();
+
+    let mut in_cycle = HashSet::new();
+    let mut cycles = Vec::new();
+    for trigger in triggers {
+        if in_cycle.contains(trigger) {
+            continue;
+        }
+        let mut chain = vec![trigger];
+        let mut template = macros[trigger];
+        while let Some((next, next_template)) = macro_target(template).and_then(lookup) {
+            if let Some(start) = chain.iter().position(|seen| *seen == next) {
+                // Macros leading into a cycle are reported by the cycle itself
+                if start == 0 {
+                    in_cycle.extend(chain.iter().copied());
+                    cycles.push(chain);
+                }
+                break;
+            }
+            chain.push(next);
+            template = next_template;
+        }
+    }
+    cycles
+}
+
+/// Formats a cycle returned by [`macro_cycles`] as `!a -> !b -> !a`.
+#[must_use]
+pub fn format_cycle(cycle: &[&str]) -> String {
+    cycle
+        .iter()
+        .chain(cycle.first())
+        .map(|trigger| format!("!{trigger}"))
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::{format_cycle, macro_cycles};
+
+    #[test]
+    fn test_macro_cycles() {
+        let cycles = macro_cycles([
+            ("self", "!self {{{s}}}"),
+            ("a", "!b {{{s}}}"),
+            ("b", "!c, {{{s}}}"),
+            ("c", "!a {{{s}}}"),
+            ("into", "!a {{{s}}}"),
+            ("myrepo", "!gh tobybridle/{{{s}}}"),
+            ("gh", "https://github.com/{{{s}}}"),
+        ]);
+        assert_eq!(cycles, vec![vec!["a", "b", "c"], vec!["self"]]);
+        assert!(macro_cycles([("myrepo", "!gh tobybridle/{{{s}}}")]).is_empty());
+        assert_eq!(format_cycle(&cycles[0]), "!a -> !b -> !c -> !a");
+    }
+}
@@ -0,0 +1,161 @@
+//! Bangs which rotate between several instances of the same site (see [`MirrorConfig`]), such as
+//! the instances of a privacy front-end.
+//!
+//! A bang's own template is always the first of its pool. Whenever a template is chosen, those
+//! whose [origin](mirror_origin) is known to be unhealthy are skipped (see
+//! [`set_mirror_health`](crate::cache::set_mirror_health)), unless every template is. Health is
+//! tracked by whoever checks the mirrors, such as the server of `boom-web`.
+
+use std::{
+    hash::{BuildHasher, RandomState},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use boom_config::{BangParamConfig, MirrorConfig, MirrorStrategy, UrlComponent};
+
+use crate::{
+    cache::is_mirror_healthy,
+    template::{Template, TemplateError},
+};
+
+/// The mirrors of a bang, compiled.
+#[derive(Debug)]
+pub struct MirrorPool {
+    pub config: MirrorConfig,
+    templates: Vec<Template>,
+    /// How many times a template has been chosen.
+    chosen: AtomicUsize,
+}
+
+impl MirrorPool {
+    /// Compiles the templates of `config`, as the bang's own template is compiled.
+    ///
+    /// # Errors
+    /// If any of the templates is invalid.
+    pub fn compile(
+        config: MirrorConfig,
+        escape: Option<UrlComponent>,
+        params: &[BangParamConfig],
+    ) -> Result<Self, TemplateError> {
+        let templates = config
+            .templates
+            .iter()
+            .map(|template| Template::compile_escaped(template.as_str(), escape, params))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            config,
+            templates,
+            chosen: AtomicUsize::new(0),
+        })
+    }
+
+    /// The templates of the mirrors, besides the bang's own.
+    #[must_use]
+    pub fn templates(&self) -> &[Template] {
+        &self.templates
+    }
+
+    /// Chooses a template according to the strategy of the pool, where `0` is the bang's own
+    /// (`primary`) and `n` is the `n`th mirror.
+    ///
+    /// Nothing is allocated.
+    #[must_use]
+    pub fn choose(&self, primary: &str) -> usize {
+        let count = self.templates.len() + 1;
+        let chosen = self.chosen.fetch_add(1, Ordering::Relaxed);
+        let start = match self.config.strategy {
+            MirrorStrategy::RoundRobin => chosen % count,
+            #[allow(clippy::cast_possible_truncation)]
+            MirrorStrategy::Random => RandomState::new().hash_one(chosen) as usize % count,
+            MirrorStrategy::FirstHealthy => 0,
+        };
+
+        let healthy = |idx: usize| {
+            let source = if idx == 0 {
+                primary
+            } else {
+                self.templates[idx - 1].source()
+            };
+            mirror_origin(source).is_none_or(is_mirror_healthy)
+        };
+        (0..count)
+            .map(|offset| (start + offset) % count)
+            .find(|idx| healthy(*idx))
+            .unwrap_or(start)
+    }
+}
+
+/// The origin (scheme and host) a template is checked through, unless it depends on the query.
+///
+/// # Example
+/// ```
+/// use boom_core::boom::mirrors::mirror_origin;
+///
+/// assert_eq!(mirror_origin("https://yewtu.be/search?q={{{s}}}"), Some("https://yewtu.be"));
+/// assert_eq!(mirror_origin("https://{{{s}}}.example.com"), None);
+/// assert_eq!(mirror_origin("!gh {{{s}}}"), None);
+/// ```
+#[must_use]
+pub fn mirror_origin(template: &str) -> Option<&str> {
+    let host = template.find("://")? + 3;
+    let end = template[host..]
+        .find(['/', '?', '#'])
+        .map_or(template.len(), |end| host + end);
+    let origin = &template[..end];
+    (end > host && !origin.contains("{{{")).then_some(origin)
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use boom_config::{MirrorConfig, MirrorStrategy};
+
+    #[allow(unused_imports)]
+    use crate::cache::set_mirror_health;
+
+    #[allow(unused_imports)]
+    use super::MirrorPool;
+
+    /// A pool whose templates are `a`, `b` and `c` of `domain`, along with the first of them.
+    #[allow(dead_code)]
+    fn pool(strategy: MirrorStrategy, domain: &str) -> (MirrorPool, String) {
+        let template = |host: &str| format!("https://{host}.{domain}/search?q={{{{{{s}}}}}}");
+        let pool = MirrorPool::compile(
+            MirrorConfig {
+                templates: vec![template("b"), template("c")],
+                strategy,
+            },
+            None,
+            &[],
+        )
+        .unwrap();
+        (pool, template("a"))
+    }
+
+    #[test]
+    fn test_mirror_strategies() {
+        let (round_robin, primary) = pool(MirrorStrategy::RoundRobin, "example.com");
+        let chosen: Vec<_> = (0..4).map(|_| round_robin.choose(&primary)).collect();
+        assert_eq!(chosen, vec![0, 1, 2, 0]);
+
+        let (first, primary) = pool(MirrorStrategy::FirstHealthy, "example.com");
+        assert_eq!(first.choose(&primary), 0);
+
+        let (random, primary) = pool(MirrorStrategy::Random, "example.com");
+        assert!((0..16).all(|_| random.choose(&primary) < 3));
+    }
+
+    #[test]
+    fn test_mirror_health() {
+        set_mirror_health("https://a.example.org", false).unwrap();
+        let (first, primary) = pool(MirrorStrategy::FirstHealthy, "example.org");
+        assert_eq!(first.choose(&primary), 1);
+
+        set_mirror_health("https://b.example.org", false).unwrap();
+        let (round_robin, primary) = pool(MirrorStrategy::RoundRobin, "example.org");
+        assert!((0..4).all(|_| round_robin.choose(&primary) == 2));
+
+        // Without any healthy mirror, the strategy is followed regardless
+        set_mirror_health("https://c.example.org", false).unwrap();
+        assert_eq!(first.choose(&primary), 0);
+    }
+}
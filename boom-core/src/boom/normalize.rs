@@ -0,0 +1,119 @@
+//! Normalization and tokenization of queries, ahead of bang detection.
+//!
+//! Queries are resolved in three steps:
+//! 1. [`normalize_query`] turns every run of Unicode whitespace (tabs, newlines, non-breaking
+//!    spaces, ...) into a single ASCII space, and trims the query at both ends.
+//! 2. [`parse_bang_indexes`](super::parse_bangs::parse_bang_indexes) finds the first bang: a `!`
+//!    followed by at least one character, at the start of a word. A word may start with opening
+//!    punctuation (see [`is_opening_punctuation`]), such that `(!gh` is a bang.
+//! 3. [`trigger_candidates`] strips closing punctuation (see [`is_closing_punctuation`]) from the
+//!    end of the trigger, one character at a time, until a known trigger is found. `!gh,` is
+//!    therefore `!gh`, whilst `!c++` is still `!c++`.
+//!
+//! The whole word holding the bang, including any punctuation around it, is then removed from
+//! the query, with the words on either side of it being joined by a single space
+//! (see [`split_terms`]).
+
+use std::borrow::Cow;
+
+use super::Match;
+
+/// Punctuation which may open a word holding a bang, such as the `(` of `(!gh`.
+#[inline]
+#[must_use]
+pub const fn is_opening_punctuation(byte: u8) -> bool {
+    matches!(byte, b'(' | b'[' | b'{' | b'"' | b'\'')
+}
+
+/// Punctuation which may trail a trigger, such as the `,` of `!gh,`.
+#[inline]
+#[must_use]
+pub const fn is_closing_punctuation(byte: u8) -> bool {
+    matches!(
+        byte,
+        b')' | b']' | b'}' | b'"' | b'\'' | b',' | b'.' | b';' | b':' | b'!' | b'?'
+    )
+}
+
+/// Collapses every run of (Unicode) whitespace within `query` into a single space, and trims
+/// whitespace from either end.
+///
+/// Nothing is allocated when `query` is already normalized.
+///
+/// # Example
+/// ```
+/// use boom_core::boom::normalize::normalize_query;
+///
+/// assert_eq!(normalize_query("  rust\t\u{a0}traits  !gh "), "rust traits !gh");
+/// ```
+#[must_use]
+pub fn normalize_query(query: &str) -> Cow<'_, str> {
+    let trimmed = query.trim();
+    let mut previous_space = false;
+    let normalized = trimmed.chars().all(|c| {
+        let ok = c == ' ' && !previous_space || !c.is_whitespace();
+        previous_space = c == ' ';
+        ok
+    });
+    if normalized {
+        return Cow::Borrowed(trimmed);
+    }
+
+    let mut out = String::with_capacity(trimmed.len());
+    for word in trimmed.split_whitespace() {
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+    Cow::Owned(out)
+}
+
+/// The triggers `trigger` could refer to, from most to least specific: `trigger` itself,
+/// followed by `trigger` without each trailing [closing punctuation](is_closing_punctuation)
+/// character in turn.
+///
+/// # Example
+/// ```
+/// use boom_core::boom::normalize::trigger_candidates;
+///
+/// assert_eq!(trigger_candidates("gh),").collect::<Vec<_>>(), vec!["gh),", "gh)", "gh"]);
+/// assert_eq!(trigger_candidates("?").collect::<Vec<_>>(), vec!["?"]);
+/// ```
+pub fn trigger_candidates(trigger: &str) -> impl Iterator<Item = &str> {
+    let mut next = Some(trigger);
+    std::iter::from_fn(move || {
+        let current = next?;
+        next = current
+            .as_bytes()
+            .split_last()
+            .filter(|(last, rest)| is_closing_punctuation(**last) && !rest.is_empty())
+            .map(|_| &current[..current.len() - 1]);
+        Some(current)
+    })
+}
+
+/// Splits a normalized `query` around the word holding the bang at `bang_idx`, returning the
+/// words before it, the space joining them (if both sides have words), and the words after it.
+///
+/// # Example
+/// ```
+/// use boom_core::boom::{Match, normalize::split_terms};
+///
+/// assert_eq!(split_terms("rust (!gh) traits", Match::new(6, 10)), ["rust", " ", "traits"]);
+/// assert_eq!(split_terms("!gh rust", Match::new(0, 3)), ["", "", "rust"]);
+/// ```
+#[must_use]
+pub fn split_terms(query: &str, bang_idx: Match) -> [&str; 3] {
+    let word_start = query[..bang_idx.start]
+        .trim_end_matches(|c: char| c.is_ascii() && is_opening_punctuation(c as u8))
+        .len();
+    let before = query[..word_start].trim_end();
+    let after = query[bang_idx.end..].trim_start();
+    let separator = if before.is_empty() || after.is_empty() {
+        ""
+    } else {
+        " "
+    };
+    [before, separator, after]
+}
@@ -0,0 +1,220 @@
+//! Parameters of custom bangs (see [`BangParamConfig`]), given within queries as `name=value`,
+//! such as `!jira project=BOOM login`.
+//!
+//! Values given through the [`RequestContext`] take precedence over those within the query, which
+//! take precedence over defaults. A parameter is missing when it is left without a value, or given
+//! a value which is not among its choices. Missing parameters are rendered as empty, unless the
+//! query is resolved through
+//! [`try_resolve_with_context`](super::resolver::try_resolve_with_context), which returns them as
+//! [`MissingParams`] so that they can be asked for.
+
+use boom_config::BangParamConfig;
+use serde::Serialize;
+
+use crate::{context::RequestContext, store::BangRef};
+
+/// The parameters of a bang, bound to the values given for them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundParams<'a> {
+    /// The query terms, without any of the `name=value` words which were bound.
+    pub query: String,
+    /// The value of every parameter which has one, by name.
+    pub values: Vec<(&'a str, &'a str)>,
+    /// Whether any parameter is missing.
+    pub missing: bool,
+}
+
+/// Binds every parameter within `params` to its value, taking `name=value` words out of `terms`.
+///
+/// # Example
+/// ```
+/// use boom_config::BangParamConfig;
+/// use boom_core::{boom::params::bind_params, context::RequestContext};
+///
+/// let params = [BangParamConfig {
+///     name: "project".to_string(),
+///     ..Default::default()
+/// }];
+/// let bound = bind_params(&params, &["project=BOOM login"], &RequestContext::default());
+/// assert_eq!(bound.query, "login");
+/// assert_eq!(bound.values, vec![("project", "BOOM")]);
+/// assert!(!bound.missing);
+/// ```
+#[must_use]
+pub fn bind_params<'a>(
+    params: &'a [BangParamConfig],
+    terms: &[&'a str],
+    context: &RequestContext<'a>,
+) -> BoundParams<'a> {
+    let mut query = String::with_capacity(terms.iter().map(|term| term.len()).sum());
+    let mut given: Vec<(&str, &str)> = Vec::with_capacity(params.len());
+    for word in terms
+        .iter()
+        .flat_map(|term| term.split(' '))
+        .filter(|word| !word.is_empty())
+    {
+        match word.split_once('=') {
+            Some((name, value))
+                if !value.is_empty() && params.iter().any(|param| param.name == name) =>
+            {
+                given.push((name, value));
+            }
+            _ => {
+                if !query.is_empty() {
+                    query.push(' ');
+                }
+                query.push_str(word);
+            }
+        }
+    }
+
+    let mut missing = false;
+    let values = params
+        .iter()
+        .filter_map(|param| {
+            let value = context
+                .param(&param.name)
+                .filter(|value| !value.is_empty())
+                .or_else(|| {
+                    given
+                        .iter()
+                        .find_map(|(name, value)| (*name == param.name).then_some(*value))
+                })
+                .or(param.default.as_deref());
+            missing |= !value.is_some_and(|value| param.accepts(value));
+            value.map(|value| (param.name.as_str(), value))
+        })
+        .collect();
+
+    BoundParams {
+        query,
+        values,
+        missing,
+    }
+}
+
+/// The parameters of a bang which are yet to be given, along with everything needed to ask for
+/// them and then complete the resolution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MissingParams {
+    pub trigger: String,
+    pub short_name: String,
+    /// The query being resolved, which is resolved once more alongside the parameters.
+    pub query: String,
+    /// Every parameter of the bang, in the order they are asked for.
+    pub params: Vec<ParamField>,
+}
+
+/// A parameter as it is asked for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParamField {
+    pub name: String,
+    pub label: String,
+    /// The value given so far, if any, otherwise the default.
+    pub value: String,
+    pub choices: Vec<String>,
+    /// Whether the parameter still needs a (valid) value.
+    pub missing: bool,
+}
+
+impl MissingParams {
+    /// The parameters of `bang` left missing by `bound`, whilst resolving `query`.
+    #[must_use]
+    pub fn new(bang: &BangRef, query: &str, bound: &BoundParams) -> Self {
+        let params = bang
+            .params()
+            .iter()
+            .map(|param| {
+                let value = bound
+                    .values
+                    .iter()
+                    .find_map(|(name, value)| (*name == param.name).then_some(*value))
+                    .unwrap_or_default();
+                ParamField {
+                    name: param.name.clone(),
+                    label: param.label().to_string(),
+                    value: value.to_string(),
+                    choices: param.choices.clone(),
+                    missing: value.is_empty() || !param.accepts(value),
+                }
+            })
+            .collect();
+
+        Self {
+            trigger: bang.trigger().to_string(),
+            short_name: bang.short_name().to_string(),
+            query: query.to_string(),
+            params,
+        }
+    }
+
+    /// The names of the parameters which still need a value.
+    pub fn missing(&self) -> impl Iterator<Item = &str> {
+        self.params
+            .iter()
+            .filter(|param| param.missing)
+            .map(|param| param.name.as_str())
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use boom_config::BangParamConfig;
+
+    #[allow(unused_imports)]
+    use crate::context::RequestContext;
+
+    #[allow(unused_imports)]
+    use super::bind_params;
+
+    #[allow(dead_code)]
+    fn params() -> Vec<BangParamConfig> {
+        vec![
+            BangParamConfig {
+                name: "project".to_string(),
+                label: "Project".to_string(),
+                ..Default::default()
+            },
+            BangParamConfig {
+                name: "type".to_string(),
+                default: Some("bug".to_string()),
+                choices: vec!["bug".to_string(), "story".to_string()],
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_bind_params() {
+        let params = params();
+        let context = RequestContext::default();
+
+        let bound = bind_params(
+            &params,
+            &["type=story login", " ", "project=BOOM"],
+            &context,
+        );
+        assert_eq!(bound.query, "login");
+        assert_eq!(bound.values, vec![("project", "BOOM"), ("type", "story")]);
+        assert!(!bound.missing);
+
+        // Only declared parameters are taken out of the query
+        let bound = bind_params(&params, &["a=b login"], &context);
+        assert_eq!(bound.query, "a=b login");
+        assert_eq!(bound.values, vec![("type", "bug")]);
+        assert!(bound.missing);
+
+        // Values must be among the choices
+        let bound = bind_params(&params, &["project=BOOM type=task"], &context);
+        assert!(bound.missing);
+
+        // The context takes precedence over the query
+        let given = [("project", "CORE")];
+        let bound = bind_params(
+            &params,
+            &["project=BOOM login"],
+            &context.with_params(&given),
+        );
+        assert_eq!(bound.values, vec![("project", "CORE"), ("type", "bug")]);
+    }
+}
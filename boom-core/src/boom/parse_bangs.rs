@@ -1,17 +1,13 @@
-#[cfg(all(
-    any(target_arch = "x86", target_arch = "x86_64"),
-    target_feature = "avx2"
-))]
+#[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::{
-    __m128i, _MM_HINT_NTA, _mm_cmpeq_epi8, _mm_load_si128, _mm_loadu_si128, _mm_movemask_epi8,
-    _mm_prefetch, _mm_set1_epi8,
+    __m256i, _mm256_cmpeq_epi8, _mm256_loadu_si256, _mm256_movemask_epi8, _mm256_set1_epi8,
 };
 
 use std::path::PathBuf;
 
 use crate::Redirect;
 
-use super::Match;
+use super::{Match, normalize::is_opening_punctuation};
 
 /// Parses bangs from using a path to a JSON file OR `./default_bangs.json`
 /// and returns a vector of [Redirect]
@@ -50,91 +46,101 @@ pub fn parse_bang_file(bangs: &PathBuf) -> Result<Vec<Redirect>, Box<dyn std::er
     )?)
 }
 
+/// The bang starting at `start`, if the `!` there begins a word and is followed by a trigger.
+/// See [`normalize`](super::normalize) for how words are separated.
 #[inline]
-fn parse_bang_indexes_iter(bang: &str) -> Option<Match> {
+fn bang_at(bytes: &[u8], start: usize) -> Option<Match> {
+    let begins_word = start == 0
+        || bytes[start - 1].is_ascii_whitespace()
+        || is_opening_punctuation(bytes[start - 1]);
+    if begins_word
+        && bytes
+            .get(start + 1)
+            .is_some_and(|b| !b.is_ascii_whitespace())
+    {
+        let end = bytes[start + 1..]
+            .iter()
+            .position(u8::is_ascii_whitespace)
+            .map_or(bytes.len(), |len| start + 1 + len);
+        return Some(Match { start, end });
+    }
+    None
+}
+
+/// Finds the first bang within `bytes` whose `!` is at or after `from`.
+#[inline]
+fn scan_bang_indexes(bytes: &[u8], from: usize) -> Option<Match> {
+    let mut i = from;
+    while let Some(found) = bytes[i..].iter().position(|&b| b == b'!') {
+        let start = i + found;
+        if let Some(bang) = bang_at(bytes, start) {
+            return Some(bang);
+        }
+        i = start + 1;
+    }
+    None
+}
+
+/// The scalar implementation of [`parse_bang_indexes`], used when SIMD is unavailable.
+#[inline]
+#[must_use]
+pub fn parse_bang_indexes_scalar(bang: &str) -> Option<Match> {
+    scan_bang_indexes(bang.as_bytes(), 0)
+}
+
+/// The AVX2 implementation of [`parse_bang_indexes`].
+///
+/// Every `!` within each 32 byte chunk is considered, in order, until one begins a bang.
+///
+/// # Safety
+/// The CPU must support AVX2 (see [`simd_available`](super::simd_available)).
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[must_use]
+pub unsafe fn parse_bang_indexes_avx2(bang: &str) -> Option<Match> {
     let bytes = bang.as_bytes();
-    let len = bytes.len();
-    let mut i = 0;
-    while i < len {
-        if bytes[i] == b'!' && (i == 0 || bytes[i - 1] == b' ') && (i < len && bytes[i + 1] != b' ')
-        {
-            let start = i;
-            let mut end = start + 1;
-            while end < len && bytes[end] != b' ' {
-                end += 1;
+    let needle = _mm256_set1_epi8(b'!'.cast_signed());
+    let mut i = 0usize;
+
+    while i + 32 <= bytes.len() {
+        // SAFETY: The 32 bytes from `i` are within `bytes`, and the load is unaligned.
+        let chunk = unsafe { _mm256_loadu_si256(bytes.as_ptr().add(i).cast::<__m256i>()) };
+        let mut mask = _mm256_movemask_epi8(_mm256_cmpeq_epi8(chunk, needle)).cast_unsigned();
+
+        while mask != 0 {
+            if let Some(bang) = bang_at(bytes, i + mask.trailing_zeros() as usize) {
+                return Some(bang);
             }
-            return Some(Match { start, end });
+            mask &= mask - 1;
         }
-        i += 1;
+
+        i += 32;
     }
-    None
+
+    scan_bang_indexes(bytes, i)
 }
 
-/// Parse the indexes of the bang within the string.
-/// Will use a SIMD approach when possible, defaulting to an iterative one.
+/// Parse the indexes of the bang within the string, which should already have been normalized
+/// (see [`normalize`](super::normalize)).
+/// Will use a SIMD approach when the CPU supports it, defaulting to an iterative one.
 /// The performance between the two is negligible.
 ///
 /// **NOTE**: the start index is __inclusive__, whereas the end is __exclusive__.
 ///
 /// # Example
-/// ```rs
-/// let bang_str = "!gh tobybridle";
-/// if let Some(indexes) = parse_bang_indexes(bang_str) {
-///     dbg!(indexes); // Match { start: 0, end: 3}
-/// }
 /// ```
+/// use boom_core::boom::{Match, parse_bangs::parse_bang_indexes};
 ///
+/// assert_eq!(parse_bang_indexes("!gh tobybridle"), Some(Match::new(0, 3)));
+/// ```
 #[inline]
 #[must_use]
 pub fn parse_bang_indexes(bang: &str) -> Option<Match> {
-    #[cfg(all(
-        any(target_arch = "x86", target_arch = "x86_64"),
-        target_feature = "avx2"
-    ))]
-    return unsafe {
-        let bytes = bang.as_bytes();
-        let len = bytes.len();
-        let mut i = 0usize;
-        let mask = _mm_set1_epi8(b'!' as i8);
-
-        while i + 16 <= len {
-            let ptr = bytes[i..].as_ptr() as *const __m128i;
-            if i + 32 <= len {
-                _mm_prefetch(ptr.byte_add(16) as *const i8, _MM_HINT_NTA);
-            }
-
-            let chunk = {
-                if ptr.align_offset(16) == 0 {
-                    _mm_load_si128(ptr) // Aligned load
-                } else {
-                    _mm_loadu_si128(ptr) // Unaligned load fallback
-                }
-            };
-
-            let cmp_mask = _mm_cmpeq_epi8(chunk, mask);
-            let mask_map = _mm_movemask_epi8(cmp_mask);
-
-            if mask_map != 0 {
-                let first_bit = mask_map.trailing_zeros() as usize;
-                let start = i + first_bit;
-
-                if start == 0 || bytes[start - 1] == b' ' {
-                    let mut end = start + 1;
-                    while end < len && bytes[end] != b' ' {
-                        end += 1;
-                    }
-                    return Some(Match { start, end });
-                }
-            }
-
-            i += 16;
-        }
-
-        parse_bang_indexes_iter(&bang[i..]).map(|Match { start, end }| Match {
-            start: start + i,
-            end: end + i,
-        })
-    };
+    #[cfg(target_arch = "x86_64")]
+    if super::simd_available() {
+        // SAFETY: AVX2 is supported.
+        return unsafe { parse_bang_indexes_avx2(bang) };
+    }
 
-    parse_bang_indexes_iter(bang)
+    parse_bang_indexes_scalar(bang)
 }
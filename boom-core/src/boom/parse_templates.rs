@@ -1,168 +1,129 @@
-#[cfg(all(
-    any(target_arch = "x86", target_arch = "x86_64"),
-    target_feature = "avx2"
-))]
-use std::arch::x86_64::*;
-use std::ptr;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{
+    __m256i, _mm256_cmpeq_epi8, _mm256_loadu_si256, _mm256_movemask_epi8, _mm256_set1_epi8,
+};
 
 use super::Match;
 
 const MAX_TEMPLATE_TERMS: usize = 2;
 
-#[inline]
-#[allow(dead_code)] // The SIMD approach uses 
-fn merge_slices(a: &mut [Match], b: &[Match]) {
-    let mut a_idx = 0usize;
-    let mut b_idx = 0usize;
-
-    let a_len = a.len();
-    let b_len = b.len();
-
-    unsafe {
-        while a_idx < a_len && b_idx < b_len {
-            let a_ptr = a.as_mut_ptr().add(a_idx);
-            let b_val = *b.get_unchecked(b_idx);
-
-            if *a_ptr == Match::new(0, 0) {
-                ptr::write(a_ptr, b_val);
-                b_idx += 1;
-            }
-            a_idx += 1;
+const PLACEHOLDER: &[u8] = b"{{{s}}}";
+
+/// The placeholders found within a template, collected in order.
+struct TemplateMatches {
+    results: [Match; MAX_TEMPLATE_TERMS],
+    len: usize,
+}
+
+impl TemplateMatches {
+    const fn new() -> Self {
+        Self {
+            results: [Match::new(0, 0); MAX_TEMPLATE_TERMS],
+            len: 0,
         }
     }
-}
 
-#[inline]
-fn iterative_parse_template_indexes(template: &str) -> Option<[Match; MAX_TEMPLATE_TERMS]> {
-    let mut results = [Match::new(0, 0); MAX_TEMPLATE_TERMS];
-    let mut idx = 0usize;
-    let mut slice_index = 0;
-
-    while let Some(start_index) = template[slice_index..].find("{{{s}}}") {
-        let start = start_index + slice_index;
-        let end = (start + "{{{s}}}".len()).min(template.len());
-        results[idx] = Match::new(start, end);
-
-        // Move the slice_index forward to continue searching after the current match
-        slice_index = end;
-        idx += 1;
+    /// Records the placeholder at `start`, returning whether there is room for another.
+    #[inline]
+    const fn push(&mut self, start: usize) -> bool {
+        self.results[self.len] = Match::new(start, start + PLACEHOLDER.len());
+        self.len += 1;
+        self.len < MAX_TEMPLATE_TERMS
     }
 
-    if results[0].is_empty() {
-        None
-    } else {
-        Some(results)
+    #[inline]
+    const fn finish(self) -> Option<[Match; MAX_TEMPLATE_TERMS]> {
+        if self.len == 0 {
+            None
+        } else {
+            Some(self.results)
+        }
     }
 }
 
+/// Collects the placeholders within `bytes` which start at or after `from`.
 #[inline]
-#[must_use]
-pub fn parse_template_indexes(template: &str) -> Option<[Match; MAX_TEMPLATE_TERMS]> {
-    #[cfg(all(
-        any(target_arch = "x86", target_arch = "x86_64"),
-        target_feature = "avx2"
-    ))]
-    return match template.len() {
-        0..16 => iterative_parse_template_indexes(template),
-        16..38 => {
-            let (prefix, aligned, suffix) = unsafe { template.as_bytes().align_to::<[u8; 16]>() };
-            let mut offset = prefix.len();
-
-            let mask = unsafe { _mm_set1_epi8('{' as i8) };
-
-            let mut results = [Match::new(0, 0); MAX_TEMPLATE_TERMS];
-            let mut res_idx = 0usize;
-
-            for chunk in aligned {
-                let simd_chunk = unsafe { _mm_load_si128(chunk.as_ptr() as *const __m128i) };
-                let eq_chunk = unsafe { _mm_cmpeq_epi8(mask, simd_chunk) };
-                let mask_chunk = unsafe { _mm_movemask_epi8(eq_chunk) };
-
-                if mask_chunk != 0 {
-                    let idx: usize = offset + mask_chunk.trailing_zeros() as usize;
-                    if &template[idx..idx + "{{{s}}}".len()] == "{{{s}}}" {
-                        results[res_idx] = Match::new(idx, idx + 7);
-                        res_idx += 1;
-                    }
-                }
-
-                offset += 16;
+fn scan_template_indexes(bytes: &[u8], from: usize, matches: &mut TemplateMatches) {
+    let mut i = from;
+    while let Some(found) = bytes[i..].iter().position(|&b| b == b'{') {
+        let start = i + found;
+        if bytes[start..].starts_with(PLACEHOLDER) {
+            if !matches.push(start) {
+                return;
             }
+            i = start + PLACEHOLDER.len();
+        } else {
+            i = start + 1;
+        }
+    }
+}
 
-            if let Some(suffix_pairs) = iterative_parse_template_indexes(
-                &template[template.len() - suffix.len()..],
-            )
-            .map(|offset_pairs| {
-                let mut result = [Match::new(0, 0); MAX_TEMPLATE_TERMS]; // Preallocate stack memory
-                for (i, Match { start, end }) in
-                    offset_pairs.iter().enumerate().take(MAX_TEMPLATE_TERMS)
-                {
-                    if *end == 0 {
-                        continue;
-                    }
-                    result[i] = Match::new(offset + start, offset + end);
-                }
-                result
-            }) {
-                merge_slices(&mut results, &suffix_pairs);
-            }
+/// The scalar implementation of [`parse_template_indexes`], used when SIMD is unavailable.
+#[inline]
+#[must_use]
+pub fn parse_template_indexes_scalar(template: &str) -> Option<[Match; MAX_TEMPLATE_TERMS]> {
+    let mut matches = TemplateMatches::new();
+    scan_template_indexes(template.as_bytes(), 0, &mut matches);
+    matches.finish()
+}
 
-            if results[0].is_empty() {
-                None
-            } else {
-                Some(results)
+/// The AVX2 implementation of [`parse_template_indexes`].
+///
+/// Every `{` within each 32 byte chunk is checked for a placeholder, so placeholders sharing a
+/// chunk, or spanning two, are all found.
+///
+/// # Safety
+/// The CPU must support AVX2 (see [`simd_available`](super::simd_available)).
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[must_use]
+pub unsafe fn parse_template_indexes_avx2(template: &str) -> Option<[Match; MAX_TEMPLATE_TERMS]> {
+    let bytes = template.as_bytes();
+    let needle = _mm256_set1_epi8(b'{'.cast_signed());
+    let mut matches = TemplateMatches::new();
+    let mut i = 0usize;
+
+    while i + 32 <= bytes.len() {
+        // SAFETY: The 32 bytes from `i` are within `bytes`, and the load is unaligned.
+        let chunk = unsafe { _mm256_loadu_si256(bytes.as_ptr().add(i).cast::<__m256i>()) };
+        let mut mask = _mm256_movemask_epi8(_mm256_cmpeq_epi8(chunk, needle)).cast_unsigned();
+
+        while mask != 0 {
+            // Placeholders cannot overlap, so the `{`s within a match are rejected here.
+            let start = i + mask.trailing_zeros() as usize;
+            if bytes[start..].starts_with(PLACEHOLDER) && !matches.push(start) {
+                return matches.finish();
             }
+            mask &= mask - 1;
         }
-        38.. => {
-            let (prefix, aligned, suffix) = unsafe { template.as_bytes().align_to::<[u8; 32]>() };
-            let mut offset = prefix.len();
-
-            let mask = unsafe { _mm256_set1_epi8('{' as i8) };
-
-            let mut results = [Match::new(0, 0); MAX_TEMPLATE_TERMS];
-            let mut res_idx = 0usize;
 
-            for chunk in aligned {
-                let simd_chunk = unsafe { _mm256_load_si256(chunk.as_ptr() as *const __m256i) };
-                let eq_chunk = unsafe { _mm256_cmpeq_epi8(mask, simd_chunk) };
-                let mask_chunk = unsafe { _mm256_movemask_epi8(eq_chunk) };
-
-                if mask_chunk != 0 {
-                    let idx: usize = offset + mask_chunk.trailing_zeros() as usize;
-                    if &template[idx..idx + "{{{s}}}".len()] == "{{{s}}}" {
-                        results[res_idx] = Match::new(idx, idx + 7);
-                        res_idx += 1;
-                    }
-                }
-
-                offset += 32;
-            }
+        i += 32;
+    }
 
-            if let Some(suffix_pairs) = iterative_parse_template_indexes(
-                &template[template.len() - suffix.len()..],
-            )
-            .map(|offset_pairs| {
-                let mut result = [Match::new(0, 0); MAX_TEMPLATE_TERMS]; // Preallocate stack memory
-                for (i, Match { start, end }) in
-                    offset_pairs.iter().enumerate().take(MAX_TEMPLATE_TERMS)
-                {
-                    if *end == 0 {
-                        continue;
-                    }
-                    result[i] = Match::new(offset + start, offset + end);
-                }
-                result
-            }) {
-                merge_slices(&mut results, &suffix_pairs);
-            }
+    scan_template_indexes(bytes, i, &mut matches);
+    matches.finish()
+}
 
-            if results[0].is_empty() {
-                None
-            } else {
-                Some(results)
-            }
-        }
-    };
+/// Parses the indexes of (up to two) `{{{s}}}` placeholders within the template.
+/// Will use a SIMD approach when the CPU supports it, defaulting to an iterative one.
+///
+/// # Example
+/// ```
+/// use boom_core::boom::{Match, parse_templates::parse_template_indexes};
+///
+/// assert_eq!(
+///     parse_template_indexes("https://github.com/{{{s}}}"),
+///     Some([Match::new(19, 26), Match::new(0, 0)])
+/// );
+/// ```
+#[inline]
+#[must_use]
+pub fn parse_template_indexes(template: &str) -> Option<[Match; MAX_TEMPLATE_TERMS]> {
+    #[cfg(target_arch = "x86_64")]
+    if super::simd_available() {
+        // SAFETY: AVX2 is supported.
+        return unsafe { parse_template_indexes_avx2(template) };
+    }
 
-    iterative_parse_template_indexes(template)
+    parse_template_indexes_scalar(template)
 }
@@ -0,0 +1,627 @@
+//! The stages a query passes through whilst being resolved, which may be inserted, reordered or
+//! removed.
+//!
+//! A [`Pipeline`] runs each of its stages in turn over the [`Resolving`] state. Every stage
+//! belongs to a [`Phase`]: those which render the url (and the phases before them) run until one
+//! of them resolves the query, after which only the [`PostProcess`](Phase::PostProcess) and
+//! [`Record`](Phase::Record) stages run. The default pipeline is:
+//!
+//! | Stage            | Phase     |                                                             |
+//! |------------------|-----------|-------------------------------------------------------------|
+//! | `normalize`      | Normalize | [`normalize_query`], then [`expand_snippets`]               |
+//! | `detect`         | Detect    | Finds the bang (see [`parse_bang_indexes`])                 |
+//! | `lookup`         | Lookup    | Finds the bang within the store (see [`trigger_candidates`])|
+//! | `render-bang`    | Render    | Renders the bang, or expands its [macro](super::macros)     |
+//! | `plugins`        | Render    | Asks the [plugins](super::plugins) (`plugins` feature only) |
+//! | `render-default` | Render    | Renders the `default_search_template`                       |
+//! | `record`         | Record    | Records the query within the history (`history` only)       |
+//!
+//! The pipeline used by the [`resolver`](super::resolver) can be replaced with
+//! [`set_pipeline`](crate::cache::set_pipeline).
+//!
+//! # Example
+//! ```
+//! use boom_config::Config;
+//! use boom_core::{
+//!     boom::{
+//!         pipeline::{Flow, Phase, Pipeline, Resolving, Stage},
+//!         resolver::Resolution,
+//!     },
+//!     context::RequestContext,
+//! };
+//!
+//! /// Sends every query to the docs, unless it has a bang.
+//! struct Docs;
+//!
+//! impl Stage for Docs {
+//!     fn name(&self) -> &'static str {
+//!         "docs"
+//!     }
+//!
+//!     fn phase(&self) -> Phase {
+//!         Phase::Render
+//!     }
+//!
+//!     fn run(&self, state: &mut Resolving) -> Result<Flow, boom_core::boom::params::MissingParams> {
+//!         if state.bang.is_some() {
+//!             return Ok(Flow::Continue);
+//!         }
+//!         state.url.push_str("https://docs.rs/releases/search?query=");
+//!         state.url.push_str(&state.query);
+//!         Ok(Flow::Resolved(Resolution::Redirect))
+//!     }
+//! }
+//!
+//! let mut pipeline = Pipeline::default();
+//! assert!(pipeline.insert_before("render-default", Docs));
+//!
+//! let (config, context) = (Config::default(), RequestContext::new());
+//! let mut url = String::new();
+//! pipeline.run(&mut Resolving::new("serde", &config, &context, &mut url)).unwrap();
+//! assert_eq!(url, "https://docs.rs/releases/search?query=serde");
+//! ```
+
+use std::borrow::Cow;
+
+use boom_config::{Config, HttpMethod, unix_timestamp};
+use tracing::warn;
+
+use crate::{
+    SourceIdentifier,
+    boom::Match,
+    cache::{default_template, get_bang, get_redirects},
+    context::RequestContext,
+    store::BangStore,
+    template::{render_parts, render_parts_raw},
+};
+
+use super::{
+    form::PostForm,
+    macros::{MAX_MACRO_DEPTH, is_macro},
+    normalize::{normalize_query, split_terms, trigger_candidates},
+    params::{MissingParams, bind_params},
+    parse_bangs::parse_bang_indexes,
+    resolver::Resolution,
+    snippets::expand_snippets,
+    webhook::WebhookCall,
+};
+
+/// The state of a query being resolved, which every stage reads from and writes to.
+pub struct Resolving<'a> {
+    /// The query as it was given.
+    pub input: &'a str,
+    /// The query as it is being resolved, once normalized and expanded.
+    pub query: Cow<'a, str>,
+    pub config: &'a Config,
+    pub context: &'a RequestContext<'a>,
+    /// Where the url is rendered into.
+    pub url: &'a mut String,
+    /// Where the bang sits within `query`, once detected.
+    pub bang: Option<Match>,
+    /// The index of the bang within the store, once looked up, if it is known.
+    pub found: Option<usize>,
+    /// How many macros have been expanded.
+    pub depth: usize,
+    /// Whether missing parameters are returned, rather than being left empty (see
+    /// [`params`](super::params)).
+    pub required: bool,
+    /// Who to record the query within the history for, if it is recorded at all.
+    pub record: Option<&'a SourceIdentifier>,
+    /// How `url` is reached, once the query has been resolved.
+    pub resolution: Option<Resolution>,
+}
+
+impl<'a> Resolving<'a> {
+    /// A url-decoded `query` about to be resolved into `url`.
+    #[must_use]
+    pub fn new(
+        query: &'a str,
+        config: &'a Config,
+        context: &'a RequestContext<'a>,
+        url: &'a mut String,
+    ) -> Self {
+        Self {
+            input: query,
+            query: Cow::Borrowed(query),
+            config,
+            context,
+            url,
+            bang: None,
+            found: None,
+            depth: 0,
+            required: false,
+            record: None,
+            resolution: None,
+        }
+    }
+
+    /// Returns missing parameters, rather than leaving them empty.
+    #[must_use]
+    pub const fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Records the query within the history for `source_identifier`.
+    #[must_use]
+    pub const fn recorded_for(mut self, source_identifier: &'a SourceIdentifier) -> Self {
+        self.record = Some(source_identifier);
+        self
+    }
+
+    /// The bang within `query`, including any punctuation trailing its trigger, once detected.
+    #[must_use]
+    pub fn bang_text(&self) -> Option<&str> {
+        self.bang.map(|bang| &self.query[bang.start + 1..bang.end])
+    }
+
+    /// The words of `query` besides the bang.
+    #[must_use]
+    pub fn terms(&self) -> [&str; 3] {
+        self.bang
+            .map_or([&self.query, "", ""], |bang| split_terms(&self.query, bang))
+    }
+}
+
+/// The phases of resolving a query, in the order they are expected to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    Normalize,
+    Detect,
+    Lookup,
+    Render,
+    PostProcess,
+    Record,
+}
+
+impl Phase {
+    /// Whether stages of this phase run once the query has been resolved, rather than until then.
+    #[must_use]
+    pub const fn after_render(self) -> bool {
+        matches!(self, Self::PostProcess | Self::Record)
+    }
+}
+
+/// What a [`Pipeline`] does once a stage has run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Flow {
+    /// Carries on with the next stage.
+    Continue,
+    /// The url has been rendered, so only the stages which run
+    /// [after rendering](Phase::after_render) are left.
+    Resolved(Resolution),
+    /// Starts over from the first stage, with the query as it has been rewritten (such as by a
+    /// macro). Stages should bound how many times they restart.
+    Restart,
+}
+
+/// A step of resolving a query.
+pub trait Stage: Send + Sync {
+    /// Identifies the stage within a [`Pipeline`].
+    fn name(&self) -> &'static str;
+
+    fn phase(&self) -> Phase;
+
+    /// Runs the stage over `state`.
+    ///
+    /// # Errors
+    /// If the bang has parameters which are missing, and they are [required](Resolving::required).
+    fn run(&self, state: &mut Resolving) -> Result<Flow, MissingParams>;
+}
+
+/// The stages a query is resolved through, in order.
+pub struct Pipeline {
+    stages: Vec<Box<dyn Stage>>,
+}
+
+impl Default for Pipeline {
+    /// The stages `boom` resolves queries through (see [`pipeline`](self)).
+    fn default() -> Self {
+        let pipeline = Self::new()
+            .with_stage(Normalize)
+            .with_stage(Detect)
+            .with_stage(Lookup)
+            .with_stage(RenderBang);
+        #[cfg(feature = "plugins")]
+        let pipeline = pipeline.with_stage(super::plugins::Plugins);
+        let pipeline = pipeline.with_stage(RenderDefault);
+        #[cfg(feature = "history")]
+        let pipeline = pipeline.with_stage(Record);
+        pipeline
+    }
+}
+
+impl Pipeline {
+    /// A pipeline without any stages.
+    #[must_use]
+    pub fn new() -> Self {
+        Self { stages: vec![] }
+    }
+
+    /// Appends `stage` to the end of the pipeline.
+    #[must_use]
+    pub fn with_stage(mut self, stage: impl Stage + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Inserts `stage` before the stage named `name`. Returns whether it was found.
+    pub fn insert_before(&mut self, name: &str, stage: impl Stage + 'static) -> bool {
+        self.position(name)
+            .map(|idx| self.stages.insert(idx, Box::new(stage)))
+            .is_some()
+    }
+
+    /// Inserts `stage` after the stage named `name`. Returns whether it was found.
+    pub fn insert_after(&mut self, name: &str, stage: impl Stage + 'static) -> bool {
+        self.position(name)
+            .map(|idx| self.stages.insert(idx + 1, Box::new(stage)))
+            .is_some()
+    }
+
+    /// Removes the stage named `name`, returning it so that it can be inserted elsewhere.
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn Stage>> {
+        self.position(name).map(|idx| self.stages.remove(idx))
+    }
+
+    /// The names of the stages, in order.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> {
+        self.stages.iter().map(|stage| stage.name())
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.stages.iter().position(|stage| stage.name() == name)
+    }
+
+    /// Runs `state` through every stage (see [`pipeline`](self)), returning how its url is
+    /// reached. Should no stage resolve the query, the url is redirected to as it is.
+    ///
+    /// Nothing is allocated by the default stages once the url has the capacity to hold the
+    /// result, provided the query is already normalized and no snippet or macro is expanded.
+    ///
+    /// # Errors
+    /// If the bang has parameters which are missing, and they are [required](Resolving::required).
+    pub fn run(&self, state: &mut Resolving) -> Result<Resolution, MissingParams> {
+        let mut idx = 0;
+        while let Some(stage) = self.stages.get(idx) {
+            idx += 1;
+            if stage.phase().after_render() != state.resolution.is_some() {
+                continue;
+            }
+            match stage.run(state)? {
+                Flow::Continue => {}
+                Flow::Resolved(resolution) => state.resolution = Some(resolution),
+                Flow::Restart => idx = 0,
+            }
+        }
+        Ok(state.resolution.take().unwrap_or(Resolution::Redirect))
+    }
+}
+
+/// Normalizes the query, then expands the snippets within it which apply to its bang. Snippets
+/// are not expanded within macros.
+pub struct Normalize;
+
+impl Stage for Normalize {
+    fn name(&self) -> &'static str {
+        "normalize"
+    }
+
+    fn phase(&self) -> Phase {
+        Phase::Normalize
+    }
+
+    fn run(&self, state: &mut Resolving) -> Result<Flow, MissingParams> {
+        state.query = normalized(std::mem::take(&mut state.query));
+        if state.depth > 0 || state.config.snippets.is_empty() {
+            return Ok(Flow::Continue);
+        }
+
+        let trigger =
+            find_bang(&state.query).and_then(|(_, found)| found.map(|(trigger, _)| trigger));
+        if let Cow::Owned(expanded) = expand_snippets(&state.query, &state.config.snippets, trigger)
+        {
+            // Snippets may add or remove whitespace
+            state.query = normalized(Cow::Owned(expanded));
+        }
+        Ok(Flow::Continue)
+    }
+}
+
+/// Normalizes `query` (see [`normalize_query`]), without copying a borrowed query which only needs
+/// trimming, nor an owned query which is already normalized.
+fn normalized(query: Cow<'_, str>) -> Cow<'_, str> {
+    match query {
+        Cow::Borrowed(query) => normalize_query(query),
+        Cow::Owned(query) => {
+            let normalized = match normalize_query(&query) {
+                Cow::Borrowed(trimmed) if trimmed.len() == query.len() => None,
+                normalized => Some(normalized.into_owned()),
+            };
+            Cow::Owned(normalized.unwrap_or(query))
+        }
+    }
+}
+
+/// Finds the bang within the query.
+pub struct Detect;
+
+impl Stage for Detect {
+    fn name(&self) -> &'static str {
+        "detect"
+    }
+
+    fn phase(&self) -> Phase {
+        Phase::Detect
+    }
+
+    fn run(&self, state: &mut Resolving) -> Result<Flow, MissingParams> {
+        state.bang = parse_bang_indexes(&state.query);
+        Ok(Flow::Continue)
+    }
+}
+
+/// Finds the bang within the store, by its trigger.
+pub struct Lookup;
+
+impl Stage for Lookup {
+    fn name(&self) -> &'static str {
+        "lookup"
+    }
+
+    fn phase(&self) -> Phase {
+        Phase::Lookup
+    }
+
+    fn run(&self, state: &mut Resolving) -> Result<Flow, MissingParams> {
+        state.found = state.bang_text().and_then(|text| {
+            trigger_candidates(text).find_map(|trigger| get_bang(trigger).unwrap())
+        });
+        Ok(Flow::Continue)
+    }
+}
+
+/// Renders the bang which was found (or one of its [`mirrors`](super::mirrors)), binding its
+/// parameters, or expands it if it is a macro. Bangs which have expired are passed over, in favour
+/// of whatever they shadowed (see [`Redirect::unshadow`](crate::Redirect::unshadow)).
+pub struct RenderBang;
+
+impl Stage for RenderBang {
+    fn name(&self) -> &'static str {
+        "render-bang"
+    }
+
+    fn phase(&self) -> Phase {
+        Phase::Render
+    }
+
+    fn run(&self, state: &mut Resolving) -> Result<Flow, MissingParams> {
+        let Some(bang_idx) = state.bang else {
+            return Ok(Flow::Continue);
+        };
+        let redirects = get_redirects().expect("Redirect list should be initialised");
+        // An expired bang gives way to whatever it shadowed, until it is swept
+        let restored: BangStore;
+        let bang = match state.found.and_then(|idx| redirects.get(idx)) {
+            Some(bang) if bang.is_expired(unix_timestamp()) => {
+                restored = bang.to_redirect().unshadow().into_iter().collect();
+                restored.get(0)
+            }
+            found => found,
+        };
+        let Some(bang) = bang else {
+            eprintln!(
+                "Bang ({}) could not be found in cache. Assuming default search.",
+                &state.query[bang_idx.start + 1..bang_idx.end]
+            );
+            return Ok(Flow::Continue);
+        };
+
+        let terms = split_terms(&state.query, bang_idx);
+        let terms_len = terms.iter().map(|term| term.len()).sum::<usize>();
+        let bound =
+            (!bang.params().is_empty()).then(|| bind_params(bang.params(), &terms, state.context));
+        let (bound_terms, bound_context);
+        let (bang_terms, bang_context) = match &bound {
+            Some(bound) if bound.missing && state.required => {
+                return Err(MissingParams::new(&bang, &state.query, bound));
+            }
+            Some(bound) => {
+                bound_terms = [bound.query.as_str()];
+                bound_context = state.context.with_params(&bound.values);
+                (&bound_terms[..], &bound_context)
+            }
+            None => (&terms[..], state.context),
+        };
+
+        if !is_macro(bang.url_template()) {
+            let url = &mut *state.url;
+            if let Some(webhook) = bang.webhook() {
+                let call = WebhookCall::new(&bang, webhook, bang_terms, bang_context);
+                match &webhook.fallback {
+                    Some(fallback) => {
+                        render_parts(
+                            fallback.parts(),
+                            bang_terms,
+                            bang.charset(),
+                            bang_context,
+                            url,
+                        );
+                    }
+                    None => {
+                        let template =
+                            default_template(&state.config.bangs.default_search_template);
+                        render_parts(template.parts(), bang_terms, None, bang_context, url);
+                    }
+                }
+                return Ok(Flow::Resolved(Resolution::Webhook(call)));
+            }
+
+            url.reserve(bang.url_template().len() + terms_len * 3);
+            match bang
+                .mirrors()
+                .map(|mirrors| (mirrors, mirrors.choose(bang.url_template())))
+            {
+                Some((mirrors, idx)) if idx > 0 => render_parts(
+                    mirrors.templates()[idx - 1].parts(),
+                    bang_terms,
+                    bang.charset(),
+                    bang_context,
+                    url,
+                ),
+                _ => render_parts(
+                    bang.segments(),
+                    bang_terms,
+                    bang.charset(),
+                    bang_context,
+                    url,
+                ),
+            }
+            return Ok(Flow::Resolved(match bang.method() {
+                HttpMethod::Get => Resolution::Redirect,
+                HttpMethod::Post => {
+                    Resolution::Form(PostForm::render(&bang, bang_terms, bang_context))
+                }
+            }));
+        }
+
+        if state.depth >= MAX_MACRO_DEPTH {
+            warn!(
+                "Macro !{} is nested more than {MAX_MACRO_DEPTH} deep. Assuming default search.",
+                bang.trigger()
+            );
+            return Ok(Flow::Continue);
+        }
+
+        let mut expansion = String::with_capacity(bang.url_template().len() + terms_len);
+        render_parts_raw(bang.segments(), bang_terms, bang_context, &mut expansion);
+        // The target may be replaced whilst the expansion is resolved
+        drop(bound);
+        drop(redirects);
+        state.query = normalized(Cow::Owned(expansion));
+        state.depth += 1;
+        state.bang = None;
+        state.found = None;
+        Ok(Flow::Restart)
+    }
+}
+
+/// Renders the `default_search_template`, with the query (besides any bang).
+pub struct RenderDefault;
+
+impl Stage for RenderDefault {
+    fn name(&self) -> &'static str {
+        "render-default"
+    }
+
+    fn phase(&self) -> Phase {
+        Phase::Render
+    }
+
+    fn run(&self, state: &mut Resolving) -> Result<Flow, MissingParams> {
+        let template = default_template(&state.config.bangs.default_search_template);
+        // Borrows the query alone, so that the url can be written to
+        let terms = state.bang.map_or([&*state.query, "", ""], |bang| {
+            split_terms(&state.query, bang)
+        });
+        state.url.reserve(
+            template.source().len() + terms.iter().map(|term| term.len()).sum::<usize>() * 3,
+        );
+        render_parts(template.parts(), &terms, None, state.context, state.url);
+        Ok(Flow::Resolved(Resolution::Redirect))
+    }
+}
+
+/// Records the query within the in-memory search history (see
+/// [`record_query`](super::resolver::record_query)), if it is [to be
+/// recorded](Resolving::recorded_for).
+#[cfg(feature = "history")]
+pub struct Record;
+
+#[cfg(feature = "history")]
+impl Stage for Record {
+    fn name(&self) -> &'static str {
+        "record"
+    }
+
+    fn phase(&self) -> Phase {
+        Phase::Record
+    }
+
+    fn run(&self, state: &mut Resolving) -> Result<Flow, MissingParams> {
+        if let Some(source_identifier) = state.record {
+            super::resolver::record_query(state.input, source_identifier);
+        }
+        Ok(Flow::Continue)
+    }
+}
+
+/// Finds the bang within a normalized `query`, along with its trigger and index if it is known.
+pub(crate) fn find_bang(query: &str) -> Option<(Match, Option<(&str, usize)>)> {
+    let bang_idx = parse_bang_indexes(query)?;
+    let found = trigger_candidates(&query[bang_idx.start + 1..bang_idx.end])
+        .find_map(|trigger| get_bang(trigger).unwrap().map(|idx| (trigger, idx)));
+    Some((bang_idx, found))
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use boom_config::Config;
+
+    #[allow(unused_imports)]
+    use crate::{
+        boom::{params::MissingParams, resolver::Resolution},
+        context::RequestContext,
+    };
+
+    #[allow(unused_imports)]
+    use super::{Flow, Phase, Pipeline, Resolving, Stage};
+
+    /// Appends `#cleaned` to every url.
+    #[allow(dead_code)]
+    struct Clean;
+
+    impl Stage for Clean {
+        fn name(&self) -> &'static str {
+            "clean"
+        }
+
+        fn phase(&self) -> Phase {
+            Phase::PostProcess
+        }
+
+        fn run(&self, state: &mut Resolving) -> Result<Flow, MissingParams> {
+            state.url.push_str("#cleaned");
+            Ok(Flow::Continue)
+        }
+    }
+
+    #[test]
+    fn test_pipeline_stages() {
+        let mut pipeline = Pipeline::default();
+        assert!(pipeline.insert_after("render-default", Clean));
+        assert!(!pipeline.insert_after("missing", Clean));
+
+        let (config, context) = (Config::default(), RequestContext::new());
+        let mut url = String::new();
+        let resolution = pipeline
+            .run(&mut Resolving::new(
+                "  test   query ",
+                &config,
+                &context,
+                &mut url,
+            ))
+            .unwrap();
+        assert_eq!(resolution, Resolution::Redirect);
+        assert_eq!(url, "https://google.com/search?q=test%20query#cleaned");
+
+        // Without anything to render the url, it is left as it is
+        let render = pipeline.remove("render-default").unwrap();
+        assert_eq!(render.name(), "render-default");
+        url.clear();
+        pipeline
+            .run(&mut Resolving::new("test", &config, &context, &mut url))
+            .unwrap();
+        assert_eq!(url, "");
+    }
+}
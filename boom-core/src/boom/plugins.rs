@@ -0,0 +1,247 @@
+//! WebAssembly plugins, which are given every query that would otherwise fall back onto the
+//! `default_search_template`, such as those without a bang or with an unknown one (see the
+//! [`Plugins`] stage).
+//!
+//! Plugins are loaded from the directory set by [`PluginConfig`] (`plugins/` beside the config,
+//! by default), in order of their file name. The first plugin to resolve a query wins.
+//!
+//! A plugin is a module which exports:
+//! - `memory`, its linear memory.
+//! - `boom_alloc(len: i32) -> i32`, which allocates `len` bytes for the request.
+//! - `boom_resolve(ptr: i32, len: i32) -> i64`, which is given the request (see [`PluginRequest`])
+//!   as JSON, and returns `0` to pass, or `(ptr << 32) | len` of its response as JSON, such as
+//!   `{ "url": "https://..." }`.
+//!
+//! Plugins cannot import anything, and are run within a fresh instance for every query, with the
+//! fuel and memory set by [`PluginConfig`]. A plugin which traps, runs out of fuel or responds
+//! with anything but an http(s) url is treated as passing.
+
+use std::{error::Error, fs, path::Path};
+
+use boom_config::{Config, PluginConfig};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use wasmi::{Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::{
+    cache::{get_plugins, set_plugins},
+    context::RequestContext,
+};
+
+use super::{
+    params::MissingParams,
+    pipeline::{Flow, Phase, Resolving, Stage},
+    resolver::Resolution,
+};
+
+/// What a plugin is given, as JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PluginRequest<'a> {
+    /// The query, with any snippets expanded.
+    pub query: &'a str,
+    /// The bang within the query, if it is unknown.
+    pub trigger: Option<&'a str>,
+    pub lang: Option<&'a str>,
+    pub source_identifier: Option<&'a str>,
+    pub host: Option<&'a str>,
+}
+
+impl<'a> PluginRequest<'a> {
+    #[must_use]
+    pub const fn new(
+        query: &'a str,
+        trigger: Option<&'a str>,
+        context: &RequestContext<'a>,
+    ) -> Self {
+        Self {
+            query,
+            trigger,
+            lang: context.lang,
+            source_identifier: context.source_identifier,
+            host: context.host,
+        }
+    }
+}
+
+/// What a plugin responds with, as JSON.
+#[derive(Debug, Deserialize)]
+struct PluginResponse {
+    url: String,
+}
+
+/// A compiled plugin, along with its limits.
+#[derive(Debug)]
+pub struct Plugin {
+    pub name: String,
+    engine: Engine,
+    module: Module,
+    fuel: u64,
+    max_memory: usize,
+}
+
+impl Plugin {
+    /// Compiles the plugin `name` from `wasm`.
+    ///
+    /// # Errors
+    /// If `wasm` is not a valid module, or it imports anything.
+    pub fn compile(
+        name: String,
+        wasm: &[u8],
+        config: &PluginConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut engine_config = wasmi::Config::default();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config);
+        let module = Module::new(&engine, wasm)?;
+        if let Some(import) = module.imports().next() {
+            return Err(format!(
+                "plugins cannot import anything, but `{}::{}` is imported",
+                import.module(),
+                import.name()
+            )
+            .into());
+        }
+
+        Ok(Self {
+            name,
+            engine,
+            module,
+            fuel: config.fuel,
+            max_memory: config.max_memory,
+        })
+    }
+
+    /// Gives `request` to the plugin, returning the url it resolved to, or `None` if it passed.
+    ///
+    /// # Errors
+    /// If the plugin traps, runs out of fuel or memory, or responds with anything other than an
+    /// http(s) url.
+    pub fn resolve(&self, request: &PluginRequest) -> Result<Option<String>, Box<dyn Error>> {
+        let input = serde_json::to_vec(request)?;
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.max_memory)
+            .build();
+        let mut store: Store<StoreLimits> = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.fuel).map_err(|e| e.to_string())?;
+
+        let instance = Linker::new(&self.engine)
+            .instantiate(&mut store, &self.module)?
+            .start(&mut store)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or("`memory` is not exported")?;
+        let alloc = instance.get_typed_func::<i32, i32>(&store, "boom_alloc")?;
+        let resolve = instance.get_typed_func::<(i32, i32), i64>(&store, "boom_resolve")?;
+
+        let len = i32::try_from(input.len())?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory
+            .write(&mut store, usize::try_from(ptr)?, &input)
+            .map_err(|e| e.to_string())?;
+
+        let packed = resolve.call(&mut store, (ptr, len))?;
+        if packed == 0 {
+            return Ok(None);
+        }
+        #[allow(clippy::cast_sign_loss)]
+        let packed = packed as u64;
+        let (ptr, len) = (packed >> 32, packed & u64::from(u32::MAX));
+        // The response is read in place, so that the plugin cannot make the host allocate more
+        // than the plugin itself was allowed to
+        let start = usize::try_from(ptr)?;
+        let output = start
+            .checked_add(usize::try_from(len)?)
+            .and_then(|end| memory.data(&store).get(start..end))
+            .ok_or("the response lies outside of its memory")?;
+
+        let response: PluginResponse = serde_json::from_slice(output)?;
+        if !(response.url.starts_with("https://") || response.url.starts_with("http://")) {
+            return Err(format!("expected an http(s) url, got {:?}", response.url).into());
+        }
+        Ok(Some(response.url))
+    }
+}
+
+/// Compiles every plugin (`*.wasm`) within `dir`, in order of their file name, logging those
+/// which are invalid.
+#[must_use]
+pub fn load_plugins(dir: &Path, config: &PluginConfig) -> Vec<Plugin> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            let plugin = fs::read(&path)
+                .map_err(Into::into)
+                .and_then(|wasm| Plugin::compile(name, &wasm, config));
+            match plugin {
+                Ok(plugin) => Some(plugin),
+                Err(e) => {
+                    error!("Could not load the plugin {}: {e}", path.display());
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Loads the plugins for `config` (see [`load_plugins`]), replacing those currently loaded.
+pub fn update_plugins_from_config(config: &Config) {
+    let plugins = load_plugins(&config.plugins.dir(&config.config_source), &config.plugins);
+    if !plugins.is_empty() {
+        info!("Loaded {} plugin(s).", plugins.len());
+    }
+    set_plugins(plugins).unwrap_or_else(|e| error!("Could not set the plugins: {e}"));
+}
+
+/// Asks the loaded plugins to resolve queries which are about to fall back onto the default
+/// search, giving them the bang if it is unknown.
+pub struct Plugins;
+
+impl Stage for Plugins {
+    fn name(&self) -> &'static str {
+        "plugins"
+    }
+
+    fn phase(&self) -> Phase {
+        Phase::Render
+    }
+
+    fn run(&self, state: &mut Resolving) -> Result<Flow, MissingParams> {
+        let trigger = state.found.is_none().then(|| state.bang_text()).flatten();
+        let Some(target) =
+            resolve_with_plugins(&PluginRequest::new(&state.query, trigger, state.context))
+        else {
+            return Ok(Flow::Continue);
+        };
+        state.url.push_str(&target);
+        Ok(Flow::Resolved(Resolution::Redirect))
+    }
+}
+
+/// Gives `request` to every loaded plugin in turn, returning the first url resolved to.
+///
+/// Nothing is allocated when no plugins are loaded.
+#[must_use]
+pub fn resolve_with_plugins(request: &PluginRequest) -> Option<String> {
+    let plugins = get_plugins().ok()?;
+    plugins
+        .iter()
+        .find_map(|plugin| match plugin.resolve(request) {
+            Ok(url) => url,
+            Err(e) => {
+                warn!("The plugin {} failed, passing: {e}", plugin.name);
+                None
+            }
+        })
+}
@@ -1,15 +1,36 @@
 use boom_config::Config;
-use concat_string::concat_string;
 
 use crate::{
-    SourceIdentifier,
-    boom::Match,
-    cache::{get_bang, get_redirects},
+    Redirect, SourceIdentifier,
+    cache::{get_pipeline, get_redirect},
+    context::RequestContext,
 };
 
-use super::{parse_bangs::parse_bang_indexes, parse_templates::parse_template_indexes};
+use super::{
+    form::PostForm,
+    normalize::normalize_query,
+    params::MissingParams,
+    pipeline::{Resolving, find_bang},
+    webhook::WebhookCall,
+};
+
+/// How the url a query resolved to is reached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// By redirecting to it.
+    Redirect,
+    /// By submitting the form to it (see [`form`](super::form)).
+    Form(PostForm),
+    /// By asking the webhook for the url to redirect to instead, falling back onto the url should
+    /// it fail (see [`webhook`](super::webhook)).
+    Webhook(WebhookCall),
+}
 
-/// Resolves a url-decoded query to its correct search url
+/// Resolves a url-decoded query to its correct search url, recording it within the search
+/// history. The query is normalized first (see [`normalize`](super::normalize)), and then any
+/// snippets within it are expanded (see [`snippets`](super::snippets)).
+///
+/// Queries are resolved through the stages of the current [`pipeline`](super::pipeline).
 ///
 /// # Panics
 /// Panics if the query is an empty string.
@@ -17,63 +38,101 @@ use super::{parse_bangs::parse_bang_indexes, parse_templates::parse_template_ind
 pub fn resolve(query: &str, config: &Config, source_identifier: &SourceIdentifier) -> String {
     assert!(!query.is_empty());
 
-    let template = config.bangs.default_search_template.as_str();
-    let indexes = parse_template_indexes(template).map_or_else(Match::default, |matches| {
-        matches.first().copied().unwrap_or_default()
-    });
-
-    parse_bang_indexes(query).map_or_else(
-        || {
-            #[cfg(feature = "history")]
-            add_to_history_cache(None, &query.replace("%2F", "/"), source_identifier);
-
-            concat_string!(
-                template[..indexes.start],
-                urlencoding::encode(query).replace("%2F", "/"),
-                template[indexes.end..]
-            )
-        },
-        |bang_idx| {
-            let bang = &query[bang_idx.start + 1..bang_idx.end];
-
-            let query = concat_string!(
-                query[..(bang_idx.start).max(1) - 1],
-                &query[(bang_idx.end + 1).clamp(1, query.len())..]
-            );
-            let encoded_query = urlencoding::encode(query.as_str()).replace("%2F", "/");
-
-            #[cfg(feature = "history")]
-            add_to_history_cache(Some(bang.to_string()), &query, source_identifier);
-
-            let Some(redirect_idx) = get_bang(bang).unwrap() else {
-                eprintln!("Bang ({bang}) could not be found in cache. Assuming default search.");
-                return concat_string!(
-                    template[..indexes.start],
-                    encoded_query,
-                    template[indexes.end..]
-                );
-            };
-            let mut template = get_redirects().expect("Redirect list should be initialised")
-                [redirect_idx]
-                .url_template
-                .clone();
-
-            if let Some(template_idx) = parse_template_indexes(template.as_str()) {
-                let mut result = String::with_capacity(1024);
-                for templates in template_idx {
-                    if !templates.is_empty() {
-                        result.push_str(&template[..templates.start]);
-                        result.push_str(&encoded_query);
-                        template = template[..templates.start].to_string();
-                    }
-                }
-
-                result
-            } else {
-                template
-            }
-        },
-    )
+    let mut url = String::new();
+    let context = RequestContext::new().with_source_identifier(source_identifier);
+    get_pipeline()
+        .run(&mut Resolving::new(query, config, &context, &mut url).recorded_for(source_identifier))
+        .expect("Missing parameters are left empty unless they are required");
+    url
+}
+
+/// Resolves a url-decoded query to its correct search url, appending it to `url`.
+///
+/// Unlike [`resolve`], the query is not recorded within the search history, and nothing is
+/// allocated once `url` has the capacity to hold the result (provided the query is already
+/// normalized). This makes it suited to reusing a buffer across queries.
+///
+/// # Panics
+/// Panics if the query is an empty string.
+///
+/// # Example
+/// ```
+/// use boom_config::Config;
+/// use boom_core::boom::resolver::resolve_into;
+///
+/// let mut url = String::with_capacity(256);
+/// resolve_into("test query", &Config::default(), &mut url);
+/// assert_eq!(url, "https://google.com/search?q=test%20query");
+/// ```
+pub fn resolve_into(query: &str, config: &Config, url: &mut String) {
+    resolve_with_context(query, config, &RequestContext::default(), url);
+}
+
+/// Resolves a url-decoded query into `url`, as [`resolve_into`] does, filling in any template
+/// variables (such as `{{{lang}}}`) from `context`.
+///
+/// Any parameters of the bang which are missing are left empty (see [`params`](super::params)).
+/// Bangs which submit a form resolve to the url it is submitted to, and bangs whose url is given by
+/// a webhook resolve to their fallback. The form or webhook itself is only given by
+/// [`try_resolve_with_context`].
+///
+/// # Panics
+/// Panics if the query is an empty string.
+///
+/// # Example
+/// ```
+/// use boom_config::Config;
+/// use boom_core::{boom::resolver::resolve_with_context, context::RequestContext};
+///
+/// let mut config = Config::default();
+/// config.bangs.default_search_template = "https://google.com/search?q={{{s}}}&hl={{{lang}}}".into();
+///
+/// let mut url = String::new();
+/// let context = RequestContext::new().with_accept_language("en-GB,en;q=0.9");
+/// resolve_with_context("test query", &config, &context, &mut url);
+/// assert_eq!(url, "https://google.com/search?q=test%20query&hl=en-GB");
+/// ```
+pub fn resolve_with_context(
+    query: &str,
+    config: &Config,
+    context: &RequestContext,
+    url: &mut String,
+) {
+    assert!(!query.is_empty());
+
+    get_pipeline()
+        .run(&mut Resolving::new(query, config, context, url))
+        .expect("Missing parameters are left empty unless they are required");
+}
+
+/// Resolves a url-decoded query into `url`, as [`resolve_with_context`] does, unless the bang
+/// declares parameters which are missing. Parameters may be given through `context`, as well as
+/// within the query.
+///
+/// Returns how `url` is reached (see [`Resolution`]).
+///
+/// # Errors
+/// If any parameter is missing, in which case nothing is written to `url`.
+///
+/// # Panics
+/// Panics if the query is an empty string.
+pub fn try_resolve_with_context(
+    query: &str,
+    config: &Config,
+    context: &RequestContext,
+    url: &mut String,
+) -> Result<Resolution, MissingParams> {
+    assert!(!query.is_empty());
+
+    get_pipeline().run(&mut Resolving::new(query, config, context, url).required())
+}
+
+/// Finds the [`Redirect`] which [`resolve`] would use for `query`, if it contains a known bang.
+#[must_use]
+pub fn find_redirect(query: &str) -> Option<Redirect> {
+    let query = normalize_query(query);
+    let (_, found) = find_bang(&query)?;
+    get_redirect(found?.0).ok().flatten()
 }
 
 mod tests {
@@ -103,6 +162,7 @@ mod tests {
                 short_name: "YouTube".to_string(),
                 trigger: "yt".to_string(),
                 url_template: "https://youtube.com/results?search_query={{{s}}}".to_string(),
+                ..Default::default()
             }],
             true,
         )
@@ -123,6 +183,7 @@ mod tests {
                 short_name: "YouTube".to_string(),
                 trigger: "yt".to_string(),
                 url_template: "https://youtube.com/results?search_query={{{s}}}".to_string(),
+                ..Default::default()
             }],
             true,
         )
@@ -141,6 +202,7 @@ mod tests {
                 short_name: "GitHub".to_string(),
                 trigger: "gh".to_string(),
                 url_template: "https://github.com/{{{s}}}".to_string(),
+                ..Default::default()
             }],
             true,
         )
@@ -155,6 +217,26 @@ mod tests {
     }
 }
 
+/// Records `query` within the in-memory search history, as [`resolve`] does.
+#[cfg(feature = "history")]
+pub fn record_query(query: &str, source_identifier: &SourceIdentifier) {
+    let query = normalize_query(query);
+    match find_bang(&query) {
+        Some((bang_idx, found)) => add_to_history_cache(
+            Some(
+                found
+                    .map_or(&query[bang_idx.start + 1..bang_idx.end], |(trigger, _)| {
+                        trigger
+                    })
+                    .to_string(),
+            ),
+            &super::normalize::split_terms(&query, bang_idx).concat(),
+            source_identifier,
+        ),
+        None => add_to_history_cache(None, &query.replace("%2F", "/"), source_identifier),
+    }
+}
+
 #[cfg(feature = "history")]
 fn add_to_history_cache(bang: Option<String>, query: &str, source_identifier: &SourceIdentifier) {
     use std::time::{SystemTime, UNIX_EPOCH};
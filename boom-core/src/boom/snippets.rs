@@ -0,0 +1,125 @@
+//! Expansion of user-defined snippets (see [`SnippetConfig`]) within queries.
+//!
+//! A snippet is expanded wherever its name starts a word and is not followed by a letter, digit
+//! or `_`, so `@org` expands within `@org/boom` but not within `@organic`. Bangs themselves are
+//! never expanded.
+
+use std::{borrow::Cow, collections::HashMap};
+
+use boom_config::SnippetConfig;
+
+/// Expands every snippet within a normalized `query` which applies to the bang `trigger`.
+///
+/// Where more than one snippet starts a word, the longest name wins. Nothing is allocated when no
+/// snippet is expanded.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+///
+/// use boom_config::SnippetConfig;
+/// use boom_core::boom::snippets::expand_snippets;
+///
+/// let snippets = HashMap::from([(
+///     "@org".to_string(),
+///     SnippetConfig::Value("tobybridle".to_string()),
+/// )]);
+/// assert_eq!(expand_snippets("@org/boom !gh", &snippets, Some("gh")), "tobybridle/boom !gh");
+/// assert_eq!(expand_snippets("@organic !gh", &snippets, Some("gh")), "@organic !gh");
+/// ```
+#[must_use]
+pub fn expand_snippets<'a>(
+    query: &'a str,
+    snippets: &HashMap<String, SnippetConfig>,
+    trigger: Option<&str>,
+) -> Cow<'a, str> {
+    if snippets.is_empty() {
+        return Cow::Borrowed(query);
+    }
+
+    let mut expanded: Option<String> = None;
+    let mut copied = 0;
+    let mut word_start = 0;
+    for word in query.split(' ') {
+        if !word.starts_with('!')
+            && let Some((name, snippet)) = snippets
+                .iter()
+                .filter(|(name, snippet)| {
+                    starts_with_name(word, name) && snippet.applies_to(trigger)
+                })
+                .max_by_key(|(name, _)| name.len())
+        {
+            let out = expanded.get_or_insert_with(|| String::with_capacity(query.len() * 2));
+            out.push_str(&query[copied..word_start]);
+            out.push_str(snippet.value());
+            copied = word_start + name.len();
+        }
+        word_start += word.len() + 1;
+    }
+
+    expanded.map_or(Cow::Borrowed(query), |mut out| {
+        out.push_str(&query[copied..]);
+        Cow::Owned(out)
+    })
+}
+
+/// Whether `word` starts with the snippet `name`, as a whole name.
+fn starts_with_name(word: &str, name: &str) -> bool {
+    !name.is_empty()
+        && word.strip_prefix(name).is_some_and(|rest| {
+            rest.chars()
+                .next()
+                .is_none_or(|c| !(c.is_alphanumeric() || c == '_'))
+        })
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use std::{borrow::Cow, collections::HashMap};
+
+    #[allow(unused_imports)]
+    use boom_config::SnippetConfig;
+
+    #[allow(unused_imports)]
+    use super::expand_snippets;
+
+    #[allow(dead_code)]
+    fn snippets() -> HashMap<String, SnippetConfig> {
+        HashMap::from([
+            (
+                "@org".to_string(),
+                SnippetConfig::Value("tobybridle".to_string()),
+            ),
+            (
+                "@org-repo".to_string(),
+                SnippetConfig::Scoped {
+                    value: "tobybridle/boom".to_string(),
+                    bangs: vec!["gh".to_string()],
+                },
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_expand_snippets() {
+        let snippets = snippets();
+        assert_eq!(
+            expand_snippets("@org @org/boom !gh", &snippets, Some("gh")),
+            "tobybridle tobybridle/boom !gh"
+        );
+        assert_eq!(
+            expand_snippets("issues @org-repo !gh", &snippets, Some("gh")),
+            "issues tobybridle/boom !gh"
+        );
+        // Scoped to `!gh`, so only `@org` applies
+        assert_eq!(
+            expand_snippets("@org-repo !yt", &snippets, Some("yt")),
+            "tobybridle-repo !yt"
+        );
+        assert_eq!(expand_snippets("!@org test", &snippets, None), "!@org test");
+        assert!(matches!(
+            expand_snippets("nothing to expand", &snippets, None),
+            Cow::Borrowed(_)
+        ));
+    }
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::{get_redirects, with_trigger_index},
+    store::{BangRef, BangStore},
+};
+
+/// How many DuckDuckGo relevance points a single local use of a bang is worth.
+/// Local usage should quickly outweigh the global popularity of a bang.
+const LOCAL_USAGE_WEIGHT: u64 = 10_000;
+
+/// A prefix-searchable index over the triggers and short names of every loaded bang.
+///
+/// Keys are lowercased into a single text buffer and kept sorted, so every key sharing a prefix
+/// sits within one contiguous run which can be found with a binary search.
+#[derive(Debug, Default, Clone)]
+pub struct TriggerIndex {
+    text: String,
+    /// The start and end of each key within `text`, followed by the index of its bang.
+    keys: Vec<(u32, u32, u32)>,
+}
+
+impl TriggerIndex {
+    /// Builds the index over `bangs`, pointing each key at the index of its bang.
+    ///
+    /// # Panics
+    /// If the keys do not fit within [`u32::MAX`] bytes.
+    #[must_use]
+    pub fn build(bangs: &BangStore) -> Self {
+        let mut text = String::new();
+        let mut keys = Vec::with_capacity(bangs.len() * 2);
+        let mut push_key = |key: &str, idx: usize| {
+            let start = u32::try_from(text.len()).expect("Keys should fit within u32");
+            text.extend(key.chars().flat_map(char::to_lowercase));
+            let end = u32::try_from(text.len()).expect("Keys should fit within u32");
+            let idx = u32::try_from(idx).expect("Bang index should fit within u32");
+            keys.push((start, end, idx));
+        };
+
+        for bang in bangs.iter() {
+            push_key(bang.trigger(), bang.index());
+            if !bang.short_name().eq_ignore_ascii_case(bang.trigger()) {
+                push_key(bang.short_name(), bang.index());
+            }
+        }
+
+        keys.sort_unstable_by(|a, b| {
+            text[a.0 as usize..a.1 as usize]
+                .cmp(&text[b.0 as usize..b.1 as usize])
+                .then(a.2.cmp(&b.2))
+        });
+        text.shrink_to_fit();
+        keys.shrink_to_fit();
+        Self { text, keys }
+    }
+
+    /// Returns the (possibly repeated) indexes of every bang with a trigger or short name
+    /// starting with `prefix`, ignoring case.
+    pub fn search(&self, prefix: &str) -> impl Iterator<Item = usize> + '_ {
+        let prefix = prefix.to_lowercase();
+        let start = self
+            .keys
+            .partition_point(|key| self.key(key) < prefix.as_str());
+        self.keys[start..]
+            .iter()
+            .take_while(move |key| self.key(key).starts_with(prefix.as_str()))
+            .map(|(_, _, idx)| *idx as usize)
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    fn key(&self, &(start, end, _): &(u32, u32, u32)) -> &str {
+        &self.text[start as usize..end as usize]
+    }
+}
+
+/// A single autocompletion candidate for a partially typed trigger.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    #[serde(rename = "s")]
+    pub short_name: String,
+    #[serde(rename = "t")]
+    pub trigger: String,
+    #[serde(rename = "u")]
+    pub url_template: String,
+}
+
+/// Finds up to `limit` bangs whose trigger or short name starts with `prefix`.
+///
+/// A leading `!` on `prefix` is ignored. An exact trigger match always comes first, followed by
+/// the remaining matches ordered by their DuckDuckGo relevance (`r`) and, when the `history`
+/// feature is enabled, how often they have been used locally.
+///
+/// # Example
+/// ```
+/// use boom_core::boom::trigger_index::complete;
+///
+/// // Nothing has been loaded, so nothing can be completed
+/// assert!(complete("!gi", 10).is_empty());
+/// ```
+#[must_use]
+pub fn complete(prefix: &str, limit: usize) -> Vec<Completion> {
+    let prefix = prefix.trim_start_matches('!');
+    if prefix.is_empty() || limit == 0 {
+        return vec![];
+    }
+
+    let Ok(redirects) = get_redirects() else {
+        return vec![];
+    };
+
+    let mut candidates: Vec<usize> =
+        with_trigger_index(&redirects, |index| index.search(prefix).collect());
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    let mut ranked: Vec<(bool, u64, BangRef<'_>)> = candidates
+        .into_iter()
+        .filter_map(|idx| redirects.get(idx))
+        .map(|bang| {
+            (
+                bang.trigger().eq_ignore_ascii_case(prefix),
+                score(&bang),
+                bang,
+            )
+        })
+        .collect();
+    ranked.sort_unstable_by(|(a_exact, a_score, a), (b_exact, b_score, b)| {
+        b_exact
+            .cmp(a_exact)
+            .then(b_score.cmp(a_score))
+            .then(a.trigger().len().cmp(&b.trigger().len()))
+            .then(a.trigger().cmp(b.trigger()))
+    });
+
+    ranked
+        .into_iter()
+        .take(limit)
+        .map(|(_, _, bang)| Completion {
+            short_name: bang.short_name().to_string(),
+            trigger: bang.trigger().to_string(),
+            url_template: bang.url_template().to_string(),
+        })
+        .collect()
+}
+
+fn score(bang: &BangRef<'_>) -> u64 {
+    #[cfg(feature = "history")]
+    let usage = u64::from(crate::cache::get_bang_usage(bang.trigger()));
+    #[cfg(not(feature = "history"))]
+    let usage = 0;
+
+    u64::from(bang.relevance()) + usage * LOCAL_USAGE_WEIGHT
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use crate::{Redirect, store::BangStore};
+
+    #[allow(unused_imports)]
+    use super::TriggerIndex;
+
+    #[allow(dead_code)]
+    fn redirect(short_name: &str, trigger: &str) -> Redirect {
+        Redirect {
+            short_name: short_name.to_string(),
+            trigger: trigger.to_string(),
+            url_template: format!("https://{trigger}.com/?q={{{{{{s}}}}}}"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_trigger_index_prefix() {
+        let index = TriggerIndex::build(
+            &[
+                redirect("GitHub", "gh"),
+                redirect("Gitea", "gitea"),
+                redirect("Google", "g"),
+                redirect("YouTube", "yt"),
+            ]
+            .into_iter()
+            .collect::<BangStore>(),
+        );
+
+        let mut matches: Vec<usize> = index.search("Gi").collect();
+        matches.sort_unstable();
+        matches.dedup();
+        assert_eq!(matches, vec![0, 1]);
+
+        let mut matches: Vec<usize> = index.search("g").collect();
+        matches.sort_unstable();
+        matches.dedup();
+        assert_eq!(matches, vec![0, 1, 2]);
+
+        assert_eq!(index.search("x").count(), 0);
+    }
+}
@@ -1,30 +1,76 @@
-use std::sync::{Arc, RwLock};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    path::PathBuf,
+    process::exit,
+    sync::{Arc, RwLock},
+};
 
-use boom_config::BangConfig;
+use boom_config::{BangConfig, Config, unix_timestamp};
 use tracing::{error, info, warn};
 
 use crate::{
-    Redirect,
-    boom::add_external_sources::add_external_sources,
-    cache::{insert_bang, set_redirects, update_redirect},
+    BangOrigin, Redirect,
+    boom::{
+        add_external_sources::{try_add_external_sources, try_add_external_triggers},
+        macros::{format_cycle, is_macro, macro_cycles, macro_target},
+        normalize::{is_opening_punctuation, trigger_candidates},
+    },
+    cache::{get_redirects, rebuild_trigger_index, set_default_template, set_redirects},
+    store::BangStore,
+    template::Template,
 };
 
 /// Updates internal caches for bangs from the given configuration
 ///
-/// If `overwrite` is specified, [`update_redirect`] will be used, otherwise, if `overwrite` is
-/// false, each bang will attempt to be inserted using [`insert_bang`] and [`set_redirects`]
+/// The loaded bangs replace the current ones through [`set_redirects`]. If `overwrite` is
+/// specified, bangs which are currently loaded but no longer defined are kept, as they would be
+/// with [`update_redirect`](crate::cache::update_redirect).
+///
+/// Bangs imported from sources take their charset from [`BangConfig::encodings`], whilst custom
+/// bangs declare their own.
+///
+/// Macros which form a cycle are skipped, as they could never be resolved. Bangs which have
+/// expired give way to whatever they shadowed (see [`Redirect::unshadow`]).
+///
+/// Custom bangs are attributed to `config_source`. Whenever a trigger is defined more than once,
+/// the losing definitions are recorded on the winner (see [`Redirect::shadow`]).
+///
+/// The process exits should a required source fail, unlike with [`try_update_bangs_from_config`].
 pub async fn update_bangs_from_config(
     config_bangs: Arc<BangConfig>,
+    config_source: PathBuf,
     bangs: Arc<RwLock<Vec<Redirect>>>,
     use_cache: bool,
     overwrite: bool,
 ) {
-    add_external_sources(
+    if let Err(e) =
+        try_update_bangs_from_config(config_bangs, config_source, bangs, use_cache, overwrite).await
+    {
+        error!("{e}");
+        exit(1);
+    }
+}
+
+/// Like [`update_bangs_from_config`], but without exiting the process should a required source
+/// fail, for when `boom` is embedded within another program.
+///
+/// # Errors
+/// If a required source could not be fetched or read (see [`try_add_external_sources`]), in which
+/// case the current bangs are left as they are.
+pub async fn try_update_bangs_from_config(
+    config_bangs: Arc<BangConfig>,
+    config_source: PathBuf,
+    bangs: Arc<RwLock<Vec<Redirect>>>,
+    use_cache: bool,
+    overwrite: bool,
+) -> Result<(), Box<dyn Error>> {
+    try_add_external_sources(
         Arc::new(&config_bangs.sources),
         Arc::clone(&bangs),
         use_cache,
     )
-    .await;
+    .await?;
 
     if let Ok(rlock) = &bangs.try_read()
         && rlock.is_empty()
@@ -32,6 +78,148 @@ pub async fn update_bangs_from_config(
         warn!("No bangs were loaded. Is this intended?");
     }
 
+    store_bangs(&config_bangs, config_source, &bangs, overwrite);
+    Ok(())
+}
+
+/// Like [`update_bangs_from_config`], but only loads the bangs of sources which `query` could
+/// resolve through, for resolving a single query from a cold start. They are looked up through
+/// the trigger index of each source's cache (see [`find_bangs`]), so no source is parsed whilst
+/// its cache is up to date.
+///
+/// The bangs needed are those named within `query`, any snippet or any custom bang, followed by
+/// whatever the macros among them expand into, in turn. Every bang is loaded should a macro only
+/// name its target once it is expanded, as with `!{{{s}}}`.
+///
+/// The process exits should a required source fail.
+///
+/// [`find_bangs`]: crate::boom::bang_cache::find_bangs
+pub async fn update_bangs_for_query(config: &Config, query: &str, use_cache: bool) {
+    if let Err(e) = try_update_bangs_for_query(config, query, use_cache).await {
+        error!("{e}");
+        exit(1);
+    }
+}
+
+async fn try_update_bangs_for_query(
+    config: &Config,
+    query: &str,
+    use_cache: bool,
+) -> Result<(), Box<dyn Error>> {
+    let config_bangs = Arc::new(config.bangs.clone());
+    let bangs = Arc::new(RwLock::new(vec![]));
+    let load_all = || {
+        try_update_bangs_from_config(
+            Arc::clone(&config_bangs),
+            config.config_source.clone(),
+            Arc::new(RwLock::new(vec![])),
+            use_cache,
+            false,
+        )
+    };
+
+    if config_bangs
+        .custom
+        .values()
+        .any(|custom| expands_dynamically(&custom.template))
+    {
+        return load_all().await;
+    }
+    let mut pending: Vec<String> = bang_triggers(query)
+        .chain(
+            config
+                .snippets
+                .values()
+                .flat_map(|snippet| bang_triggers(snippet.value())),
+        )
+        .chain(
+            config_bangs
+                .custom
+                .values()
+                .flat_map(|custom| bang_triggers(&custom.template)),
+        )
+        .map(str::to_string)
+        .collect();
+
+    let mut wanted = HashSet::new();
+    // Remote sources are only fetched for the first round of triggers
+    let mut use_cache = use_cache;
+    loop {
+        let triggers: HashSet<String> = pending
+            .drain(..)
+            .filter(|trigger| !wanted.contains(trigger))
+            .collect();
+        if triggers.is_empty() {
+            break;
+        }
+        wanted.extend(triggers.iter().cloned());
+
+        let found = Arc::new(RwLock::new(vec![]));
+        try_add_external_triggers(
+            Arc::new(&config_bangs.sources),
+            Arc::clone(&found),
+            use_cache,
+            Arc::new(triggers),
+        )
+        .await?;
+        use_cache = true;
+
+        let found = std::mem::take(&mut *found.write().map_err(|e| e.to_string())?);
+        if found
+            .iter()
+            .any(|bang| expands_dynamically(&bang.url_template))
+        {
+            return load_all().await;
+        }
+        pending.extend(
+            found
+                .iter()
+                .flat_map(|bang| bang_triggers(&bang.url_template))
+                .map(str::to_string),
+        );
+        bangs.write().map_err(|e| e.to_string())?.extend(found);
+    }
+
+    store_bangs(&config_bangs, config.config_source.clone(), &bangs, false);
+    Ok(())
+}
+
+/// Every trigger which the bangs within `text` could refer to (see [`trigger_candidates`]).
+fn bang_triggers(text: &str) -> impl Iterator<Item = &str> {
+    text.split_ascii_whitespace()
+        .filter_map(|word| {
+            word.trim_start_matches(|c: char| c.is_ascii() && is_opening_punctuation(c as u8))
+                .strip_prefix('!')
+        })
+        .filter(|trigger| !trigger.is_empty())
+        .flat_map(trigger_candidates)
+}
+
+/// Whether `template` is a macro whose target is only known once it is expanded.
+fn expands_dynamically(template: &str) -> bool {
+    is_macro(template) && macro_target(template).is_none()
+}
+
+/// Stores the bangs loaded from sources alongside the custom bangs of `config_bangs`, as
+/// described by [`update_bangs_from_config`].
+fn store_bangs(
+    config_bangs: &BangConfig,
+    config_source: PathBuf,
+    bangs: &RwLock<Vec<Redirect>>,
+    overwrite: bool,
+) {
+    match Template::compile(config_bangs.default_search_template.as_str()) {
+        Ok(template) => set_default_template(template)
+            .unwrap_or_else(|e| error!("Could not set the default search template: {e}")),
+        Err(e) => error!(
+            "The default search template ({}) is invalid: {e}",
+            config_bangs.default_search_template
+        ),
+    }
+
+    let origin = Arc::new(BangOrigin::Config {
+        path: config_source,
+    });
     let custom_bangs = config_bangs
         .custom
         .iter()
@@ -39,27 +227,227 @@ pub async fn update_bangs_from_config(
             short_name: custom.short_name.clone(),
             trigger: trigger.clone(),
             url_template: custom.template.clone(),
+            escape: custom.escape,
+            encoding: custom.encoding.clone(),
+            params: custom.params.clone(),
+            method: custom.method,
+            fields: custom.fields.clone(),
+            webhook: custom.webhook.clone(),
+            mirrors: custom.mirrors.clone(),
+            expires_at: custom.expires_at,
+            origin: Arc::clone(&origin),
+            ..Default::default()
         });
 
     info!("Loaded {} bangs from config file.", custom_bangs.len());
 
-    if let Ok(mut wlock) = bangs.try_write() {
-        wlock.extend(custom_bangs);
+    let Ok(mut wlock) = bangs.try_write() else {
+        error!("Could not acquire write lock on bangs.");
+        return;
+    };
+    apply_encodings(&mut wlock, &config_bangs.encodings);
+    wlock.extend(custom_bangs);
+    let mut merged = merge_duplicates(std::mem::take(&mut *wlock));
+    drop(wlock);
+
+    if overwrite {
+        match get_redirects() {
+            Ok(current) => carry_over(&mut merged, &current),
+            Err(e) => warn!("Could not read the current bangs. Reason: {e}"),
+        }
+    }
+
+    let now = unix_timestamp();
+    merged = merged
+        .into_iter()
+        .filter_map(|redirect| {
+            if redirect
+                .expires_at
+                .is_some_and(|expires_at| expires_at <= now)
+            {
+                return redirect.unshadow();
+            }
+            Some(redirect)
+        })
+        .collect();
+    remove_macro_cycles(&mut merged);
+
+    let store: BangStore = merged.into_iter().collect();
+    info!(
+        "Stored {} bangs using {} KiB",
+        store.len(),
+        store.heap_size() / 1024
+    );
+    set_redirects(store).unwrap_or_else(|_| error!("Could not write redirects."));
+
+    rebuild_trigger_index().unwrap_or_else(|e| warn!("Could not build the trigger index: {e}"));
+}
+
+/// Sets the charset of every bang whose trigger has one within `encodings`.
+fn apply_encodings(redirects: &mut [Redirect], encodings: &HashMap<String, String>) {
+    if encodings.is_empty() {
+        return;
+    }
+    for redirect in redirects {
+        if let Some(encoding) = encodings.get(&redirect.trigger) {
+            redirect.encoding = Some(encoding.clone());
+        }
+    }
+}
+
+/// Drops every macro which forms a cycle (see [`macro_cycles`]), with an error.
+fn remove_macro_cycles(redirects: &mut Vec<Redirect>) {
+    let cyclic: HashSet<String> = macro_cycles(
+        redirects
+            .iter()
+            .map(|redirect| (redirect.trigger.as_str(), redirect.url_template.as_str())),
+    )
+    .into_iter()
+    .inspect(|cycle| {
+        error!(
+            "Skipping macros which form a cycle: {}",
+            format_cycle(cycle)
+        )
+    })
+    .flatten()
+    .map(str::to_string)
+    .collect();
+
+    if !cyclic.is_empty() {
+        redirects.retain(|redirect| !cyclic.contains(&redirect.trigger));
+    }
+}
+
+/// Collapses every definition of a trigger into the last one seen, which is the one that would
+/// have won the lookup in `CACHE`. Earlier definitions are kept as shadowed by the winner.
+fn merge_duplicates(redirects: Vec<Redirect>) -> Vec<Redirect> {
+    let mut positions: HashMap<String, usize> = HashMap::with_capacity(redirects.len());
+    let mut merged: Vec<Redirect> = Vec::with_capacity(redirects.len());
+
+    for redirect in redirects {
+        if let Some(&idx) = positions.get(&redirect.trigger) {
+            let previous = std::mem::replace(&mut merged[idx], redirect);
+            merged[idx].shadow(previous);
+        } else {
+            positions.insert(redirect.trigger.clone(), merged.len());
+            merged.push(redirect);
+        }
+    }
+
+    merged
+}
 
-        if overwrite {
-            wlock.iter().for_each(|r| {
-                update_redirect(r).unwrap_or_else(|_| warn!("Could not update `!{}`", r.trigger));
-            });
+/// Keeps every bang within `current` which `merged` does not define, recording those it does
+/// define as shadowed by the new definition.
+fn carry_over(merged: &mut Vec<Redirect>, current: &BangStore) {
+    let positions: HashMap<String, usize> = merged
+        .iter()
+        .enumerate()
+        .map(|(idx, redirect)| (redirect.trigger.clone(), idx))
+        .collect();
+
+    for bang in current.iter() {
+        if let Some(&idx) = positions.get(bang.trigger()) {
+            merged[idx].shadow(bang.to_redirect());
         } else {
-            wlock.iter().enumerate().for_each(|(i, r)| {
-                insert_bang(r.trigger.clone(), i).unwrap_or_else(|_| {
-                    warn!(
-                        "Bang ({}) should not already exist within the cache",
-                        r.trigger
-                    );
-                });
-            });
-            set_redirects(wlock.to_vec()).unwrap_or_else(|_| error!("Could not write redirects."));
+            merged.push(bang.to_redirect());
         }
     }
 }
+
+mod tests {
+    #[allow(unused_imports)]
+    use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+    #[allow(unused_imports)]
+    use crate::{BangOrigin, Redirect};
+
+    #[allow(unused_imports)]
+    use super::{apply_encodings, merge_duplicates, remove_macro_cycles};
+
+    #[allow(dead_code)]
+    fn redirect(trigger: &str, template: &str, origin: &Arc<BangOrigin>) -> Redirect {
+        Redirect {
+            short_name: trigger.to_uppercase(),
+            trigger: trigger.to_string(),
+            url_template: template.to_string(),
+            origin: Arc::clone(origin),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merge_duplicates_records_shadowed() {
+        let source = Arc::new(BangOrigin::Source {
+            filepath: PathBuf::from("bangs.json"),
+            remote: None,
+        });
+        let config = Arc::new(BangOrigin::Config {
+            path: PathBuf::from("config.toml"),
+        });
+
+        let merged = merge_duplicates(vec![
+            redirect("gh", "https://github.com/search?q={{{s}}}", &source),
+            redirect(
+                "yt",
+                "https://youtube.com/results?search_query={{{s}}}",
+                &source,
+            ),
+            redirect("gh", "https://github.com/{{{s}}}", &config),
+        ]);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].url_template, "https://github.com/{{{s}}}");
+        assert_eq!(merged[0].origin, config);
+        assert_eq!(merged[0].shadowed.len(), 1);
+        assert_eq!(merged[0].shadowed[0].origin, source);
+        assert!(merged[1].shadowed.is_empty());
+    }
+
+    #[test]
+    fn test_shadow_skips_same_origin() {
+        let config = Arc::new(BangOrigin::Config {
+            path: PathBuf::from("config.toml"),
+        });
+
+        let mut reloaded = redirect("gh", "https://github.com/{{{s}}}", &config);
+        reloaded.shadow(redirect("gh", "https://github.com/{{{s}}}", &config));
+
+        assert!(reloaded.shadowed.is_empty());
+    }
+
+    #[test]
+    fn test_apply_encodings() {
+        let source = Arc::new(BangOrigin::default());
+        let mut redirects = vec![
+            redirect(
+                "kakaku",
+                "https://kakaku.com/search_results/{{{s}}}",
+                &source,
+            ),
+            redirect("gh", "https://github.com/{{{s}}}", &source),
+        ];
+        apply_encodings(
+            &mut redirects,
+            &HashMap::from([("kakaku".to_string(), "shift_jis".to_string())]),
+        );
+
+        assert_eq!(redirects[0].encoding.as_deref(), Some("shift_jis"));
+        assert!(redirects[1].encoding.is_none());
+    }
+
+    #[test]
+    fn test_remove_macro_cycles() {
+        let config = Arc::new(BangOrigin::default());
+        let mut redirects = vec![
+            redirect("a", "!b {{{s}}}", &config),
+            redirect("b", "!a {{{s}}}", &config),
+            redirect("myrepo", "!gh tobybridle/{{{s}}}", &config),
+            redirect("gh", "https://github.com/{{{s}}}", &config),
+        ];
+        remove_macro_cycles(&mut redirects);
+
+        let triggers: Vec<_> = redirects.iter().map(|r| r.trigger.as_str()).collect();
+        assert_eq!(triggers, vec!["myrepo", "gh"]);
+    }
+}
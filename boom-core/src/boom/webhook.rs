@@ -0,0 +1,167 @@
+//! Bangs whose url is given by an endpoint (see [`WebhookConfig`]), such as "the latest build of
+//! this branch", or "the on-call dashboard of this team".
+//!
+//! Resolving such a bang renders its template as the endpoint to ask, and its fallback as the url,
+//! so that a query always resolves somewhere without waiting on the endpoint. Callers which can
+//! wait then [`fetch`](WebhookCall::fetch) the url from the endpoint, keeping the fallback should
+//! it fail.
+
+use std::{collections::BTreeMap, error::Error, sync::LazyLock, time::Duration};
+
+use boom_config::{BangParamConfig, WebhookConfig};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    cache::{cache_webhook_url, get_webhook_url},
+    context::RequestContext,
+    store::BangRef,
+    template::{Template, TemplateError, render_parts},
+};
+
+static CLIENT: LazyLock<Client> = LazyLock::new(Client::new);
+
+/// The webhook of a bang, with its fallback compiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Webhook {
+    pub config: WebhookConfig,
+    pub fallback: Option<Template>,
+}
+
+impl Webhook {
+    /// Compiles the fallback of `config`, which may reference any of `params`.
+    ///
+    /// # Errors
+    /// If the fallback is an invalid template.
+    pub fn compile(
+        config: WebhookConfig,
+        params: &[BangParamConfig],
+    ) -> Result<Self, TemplateError> {
+        let fallback = config
+            .fallback
+            .as_deref()
+            .map(|fallback| Template::compile_with_params(fallback, params))
+            .transpose()?;
+        Ok(Self { config, fallback })
+    }
+}
+
+/// What an endpoint is sent, as JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WebhookRequest {
+    pub trigger: String,
+    /// The query, without the bang.
+    pub query: String,
+    pub lang: Option<String>,
+    pub source_identifier: Option<String>,
+    pub host: Option<String>,
+    pub params: BTreeMap<String, String>,
+}
+
+/// What an endpoint responds with, as JSON.
+#[derive(Debug, Deserialize)]
+struct WebhookResponse {
+    url: String,
+}
+
+/// A request to make to the endpoint of a bang, for the url a query resolves to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookCall {
+    pub endpoint: String,
+    pub request: WebhookRequest,
+    pub timeout: Duration,
+    pub cache_ttl: Duration,
+}
+
+impl WebhookCall {
+    /// Renders the endpoint of `bang`, inserting `terms` and filling in from `context`.
+    #[must_use]
+    pub fn new(
+        bang: &BangRef,
+        webhook: &Webhook,
+        terms: &[&str],
+        context: &RequestContext,
+    ) -> Self {
+        let mut endpoint = String::with_capacity(bang.url_template().len());
+        render_parts(
+            bang.segments(),
+            terms,
+            bang.charset(),
+            context,
+            &mut endpoint,
+        );
+
+        Self {
+            endpoint,
+            request: WebhookRequest {
+                trigger: bang.trigger().to_string(),
+                query: terms.concat(),
+                lang: context.lang.map(str::to_string),
+                source_identifier: context.source_identifier.map(str::to_string),
+                host: context.host.map(str::to_string),
+                params: context
+                    .params
+                    .iter()
+                    .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+                    .collect(),
+            },
+            timeout: Duration::from_millis(webhook.config.timeout_ms),
+            cache_ttl: Duration::from_secs(webhook.config.cache_ttl),
+        }
+    }
+
+    /// Asks the endpoint for the url, unless it was cached. Failures are logged, and give `None`.
+    pub async fn fetch(&self) -> Option<String> {
+        let key = self.cache_key();
+        if let Some(url) = get_webhook_url(&key) {
+            return Some(url);
+        }
+
+        match self.request().await {
+            Ok(url) => {
+                if !self.cache_ttl.is_zero()
+                    && let Err(e) = cache_webhook_url(key, url.clone(), self.cache_ttl)
+                {
+                    warn!(
+                        "Could not cache the url given for !{}: {e}",
+                        self.request.trigger
+                    );
+                }
+                Some(url)
+            }
+            Err(e) => {
+                warn!(
+                    "The webhook for !{} ({}) failed, using its fallback: {e}",
+                    self.request.trigger, self.endpoint
+                );
+                None
+            }
+        }
+    }
+
+    async fn request(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let response: WebhookResponse = CLIENT
+            .post(&self.endpoint)
+            .timeout(self.timeout)
+            .json(&self.request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if !(response.url.starts_with("https://") || response.url.starts_with("http://")) {
+            return Err(format!("expected an http(s) url, got {:?}", response.url).into());
+        }
+        Ok(response.url)
+    }
+
+    /// Identifies the request, such that identical requests share a cached url.
+    fn cache_key(&self) -> String {
+        format!(
+            "{}\n{}",
+            self.endpoint,
+            serde_json::to_string(&self.request).unwrap_or_default()
+        )
+    }
+}
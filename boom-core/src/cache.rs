@@ -1,17 +1,59 @@
 use std::{
     collections::HashMap,
     error::Error,
-    sync::{LazyLock, RwLock, RwLockReadGuard},
+    sync::{Arc, LazyLock, PoisonError, RwLock, RwLockReadGuard},
+    time::{Duration, Instant},
 };
 
+use boom_config::BangConfig;
+use tracing::error;
+
 #[cfg(feature = "history")]
 use crate::HistoryEntry;
-use crate::Redirect;
+#[cfg(feature = "plugins")]
+use crate::boom::plugins::Plugin;
+use crate::{
+    Redirect,
+    boom::{pipeline::Pipeline, trigger_index::TriggerIndex},
+    store::BangStore,
+    template::Template,
+};
 
 pub static CACHE: LazyLock<RwLock<HashMap<String, usize>>> =
     LazyLock::new(|| RwLock::new(HashMap::with_capacity(128)));
 
-static REDIRECT_LIST: LazyLock<RwLock<Vec<Redirect>>> = LazyLock::new(|| RwLock::new(vec![]));
+static REDIRECT_LIST: LazyLock<RwLock<BangStore>> = LazyLock::new(|| RwLock::new(BangStore::new()));
+
+/// Built at load time, and lazily rebuilt after being invalidated by a change to the
+/// `REDIRECT_LIST`.
+static TRIGGER_INDEX: LazyLock<RwLock<Option<TriggerIndex>>> = LazyLock::new(|| RwLock::new(None));
+
+/// The compiled `default_search_template`, set whenever the config is loaded.
+static DEFAULT_TEMPLATE: LazyLock<RwLock<Option<Arc<Template>>>> =
+    LazyLock::new(|| RwLock::new(None));
+
+/// The stages queries are resolved through (see [`pipeline`](crate::boom::pipeline)).
+static PIPELINE: LazyLock<RwLock<Arc<Pipeline>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(Pipeline::default())));
+
+/// Urls given by webhooks, by request, along with when they expire (see
+/// [`webhook`](crate::boom::webhook)).
+static WEBHOOK_CACHE: LazyLock<RwLock<HashMap<String, (Instant, String)>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Whether the origin of each mirror passed its last health check (see
+/// [`mirrors`](crate::boom::mirrors)).
+static MIRROR_HEALTH: LazyLock<RwLock<HashMap<String, bool>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// The loaded plugins, in the order they are given queries (see [`plugins`](crate::boom::plugins)).
+#[cfg(feature = "plugins")]
+static PLUGINS: LazyLock<RwLock<Vec<Plugin>>> = LazyLock::new(|| RwLock::new(vec![]));
+
+/// How many times each bang has been used, according to the search history.
+#[cfg(feature = "history")]
+static BANG_USAGE: LazyLock<RwLock<HashMap<String, u32>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
 
 #[cfg(feature = "history")]
 pub static SEARCH_HISTORY_CACHE: LazyLock<RwLock<Vec<HistoryEntry>>> =
@@ -33,7 +75,7 @@ pub static SEARCH_HISTORY_CACHE: LazyLock<RwLock<Vec<HistoryEntry>>> =
 /// init_list(bangs, false).unwrap();
 /// ```
 pub fn init_list(
-    mut redirects: Vec<Redirect>,
+    redirects: Vec<Redirect>,
     overwrite: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     {
@@ -41,7 +83,10 @@ pub fn init_list(
             return Err("List already initialised".into());
         }
     }
-    REDIRECT_LIST.try_write()?.append(&mut redirects);
+    let mut list = REDIRECT_LIST.try_write()?;
+    list.extend(redirects);
+    invalidate_trigger_index();
+    drop(list);
     Ok(())
 }
 
@@ -50,24 +95,205 @@ pub fn init_list(
 /// # Errors
 /// This function will error if the `try_read` call fails.
 /// Please check the documentation of [`std::sync::poison::rwlock::RwLock::try_read`] for more info
-pub fn get_redirects<'a>() -> Result<RwLockReadGuard<'a, Vec<Redirect>>, Box<dyn Error>> {
+pub fn get_redirects<'a>() -> Result<RwLockReadGuard<'a, BangStore>, Box<dyn Error>> {
     match REDIRECT_LIST.try_read() {
         Ok(list) => Ok(list),
         Err(e) => Err(Box::new(e)),
     }
 }
 
-/// Set the value of the global `REDIRECT_LIST`.
-/// **This does not append, it overwrites.**
+/// Set the value of the global `REDIRECT_LIST`, mapping each trigger within `CACHE` to its new
+/// index. **This does not append, it overwrites.**
+///
+/// Both locks are waited on, so that a reload is not abandoned because of an in-flight request.
 ///
 /// # Errors
-/// This function will error if the `try_write` call fails.
-/// Please check the documentation of [`std::sync::poison::rwlock::RwLock::try_write`] for more info
-pub fn set_redirects(redirects: Vec<Redirect>) -> Result<(), Box<dyn std::error::Error>> {
-    (*REDIRECT_LIST.try_write()?) = redirects;
+/// This function will error if either `REDIRECT_LIST` or `CACHE` has been poisoned.
+pub fn set_redirects(redirects: BangStore) -> Result<(), Box<dyn std::error::Error>> {
+    let triggers: HashMap<String, usize> = redirects
+        .iter()
+        .map(|bang| (bang.trigger().to_string(), bang.index()))
+        .collect();
+
+    let mut list = REDIRECT_LIST
+        .write()
+        .map_err(|e| format!("RwLock poisoned: {e}"))?;
+    let mut cache = CACHE.write().map_err(|e| format!("RwLock poisoned: {e}"))?;
+    *list = redirects;
+    *cache = triggers;
+    invalidate_trigger_index();
+    drop(cache);
+    drop(list);
+    Ok(())
+}
+
+/// Rebuilds the [`TriggerIndex`] over the current `REDIRECT_LIST`.
+///
+/// # Errors
+/// Errors if a read lock is unable to be acquired on the `REDIRECT_LIST`, or a write lock on the
+/// index.
+pub fn rebuild_trigger_index() -> Result<(), Box<dyn std::error::Error>> {
+    let redirects = get_redirects()?;
+    let index = TriggerIndex::build(&redirects);
+    *TRIGGER_INDEX
+        .write()
+        .map_err(|e| format!("RwLock poisoned: {e}"))? = Some(index);
+    drop(redirects);
     Ok(())
 }
 
+/// Runs `f` against the [`TriggerIndex`], first building it from `redirects` if it has been
+/// invalidated. `redirects` should be the contents of the `REDIRECT_LIST`.
+pub fn with_trigger_index<T>(redirects: &BangStore, f: impl FnOnce(&TriggerIndex) -> T) -> T {
+    if let Ok(index) = TRIGGER_INDEX.read()
+        && let Some(index) = index.as_ref()
+    {
+        return f(index);
+    }
+
+    let index = TriggerIndex::build(redirects);
+    let result = f(&index);
+    if let Ok(mut lock) = TRIGGER_INDEX.write() {
+        *lock = Some(index);
+    }
+    result
+}
+
+fn invalidate_trigger_index() {
+    if let Ok(mut index) = TRIGGER_INDEX.write() {
+        *index = None;
+    }
+}
+
+/// Replaces the compiled default search template.
+///
+/// # Errors
+/// If the lock on the template has been poisoned.
+pub fn set_default_template(template: Template) -> Result<(), Box<dyn std::error::Error>> {
+    *DEFAULT_TEMPLATE
+        .write()
+        .map_err(|e| format!("RwLock poisoned: {e}"))? = Some(Arc::new(template));
+    Ok(())
+}
+
+/// Gets the compiled form of `source`, the configured `default_search_template`.
+///
+/// The template compiled at load time is reused whenever it matches `source`. Otherwise `source`
+/// is compiled on the spot, falling back to the default of [`BangConfig`] if it is invalid.
+///
+/// # Panics
+/// If the default of [`BangConfig`] is itself an invalid template.
+///
+/// # Example
+/// ```
+/// use boom_core::cache::default_template;
+///
+/// let template = default_template("https://duckduckgo.com/?q={{{s}}}");
+/// assert_eq!(template.render("boom"), "https://duckduckgo.com/?q=boom");
+/// ```
+#[must_use]
+pub fn default_template(source: &str) -> Arc<Template> {
+    if let Ok(template) = DEFAULT_TEMPLATE.read()
+        && let Some(template) = template.as_ref()
+        && template.source() == source
+    {
+        return Arc::clone(template);
+    }
+
+    Arc::new(Template::compile(source).unwrap_or_else(|e| {
+        error!("The default search template ({source}) is invalid: {e}");
+        Template::compile(BangConfig::default().default_search_template)
+            .expect("The default search template should be valid")
+    }))
+}
+
+/// Replaces the pipeline queries are resolved through by the
+/// [`resolver`](crate::boom::resolver).
+///
+/// # Errors
+/// If the lock on the pipeline has been poisoned.
+pub fn set_pipeline(pipeline: Pipeline) -> Result<(), Box<dyn Error>> {
+    *PIPELINE
+        .write()
+        .map_err(|e| format!("RwLock poisoned: {e}"))? = Arc::new(pipeline);
+    Ok(())
+}
+
+/// Gets the pipeline queries are resolved through.
+#[must_use]
+pub fn get_pipeline() -> Arc<Pipeline> {
+    Arc::clone(&PIPELINE.read().unwrap_or_else(PoisonError::into_inner))
+}
+
+/// Gets the url cached for a webhook request `key`, unless it has expired.
+#[must_use]
+pub fn get_webhook_url(key: &str) -> Option<String> {
+    let cache = WEBHOOK_CACHE.read().ok()?;
+    cache
+        .get(key)
+        .filter(|(expires, _)| *expires > Instant::now())
+        .map(|(_, url)| url.clone())
+}
+
+/// Caches the `url` given for a webhook request `key`, for `ttl`. Expired urls are dropped.
+///
+/// # Errors
+/// If the lock on the cache has been poisoned.
+pub fn cache_webhook_url(key: String, url: String, ttl: Duration) -> Result<(), Box<dyn Error>> {
+    let now = Instant::now();
+    let mut cache = WEBHOOK_CACHE.write().map_err(|e| e.to_string())?;
+    cache.retain(|_, (expires, _)| *expires > now);
+    cache.insert(key, (now + ttl, url));
+    Ok(())
+}
+
+/// Records whether the mirror `origin` passed its last health check.
+///
+/// # Errors
+/// If the lock on the health of the mirrors has been poisoned.
+pub fn set_mirror_health(origin: &str, healthy: bool) -> Result<(), Box<dyn Error>> {
+    let mut health = MIRROR_HEALTH.write().map_err(|e| e.to_string())?;
+    match health.get_mut(origin) {
+        Some(status) => *status = healthy,
+        None => {
+            health.insert(origin.to_string(), healthy);
+        }
+    }
+    Ok(())
+}
+
+/// Whether the mirror `origin` passed its last health check. Mirrors which have not been checked
+/// are assumed to be healthy.
+#[must_use]
+pub fn is_mirror_healthy(origin: &str) -> bool {
+    MIRROR_HEALTH
+        .read()
+        .map_or(true, |health| health.get(origin).copied().unwrap_or(true))
+}
+
+/// Replaces the loaded plugins.
+///
+/// # Errors
+/// If the lock on the plugins has been poisoned.
+#[cfg(feature = "plugins")]
+pub fn set_plugins(plugins: Vec<Plugin>) -> Result<(), Box<dyn Error>> {
+    *PLUGINS
+        .write()
+        .map_err(|e| format!("RwLock poisoned: {e}"))? = plugins;
+    Ok(())
+}
+
+/// Returns a read lock on the loaded plugins.
+///
+/// # Errors
+/// If the lock on the plugins has been poisoned.
+#[cfg(feature = "plugins")]
+pub fn get_plugins<'a>() -> Result<RwLockReadGuard<'a, Vec<Plugin>>, Box<dyn Error>> {
+    PLUGINS
+        .read()
+        .map_err(|e| format!("RwLock poisoned: {e}").into())
+}
+
 /// Insert (or update) a bang and its index in the list of valid bangs
 ///
 /// # Errors
@@ -105,11 +331,33 @@ pub fn get_bang(bang: &str) -> Result<Option<usize>, Box<dyn std::error::Error>>
     Ok(CACHE.try_read()?.get(bang).copied())
 }
 
+/// Get a copy of the redirect registered for `bang`, if any.
+///
+/// # Errors
+/// Errors if a read lock is unable to be acquired on `CACHE` or the `REDIRECT_LIST`.
+pub fn get_redirect(bang: &str) -> Result<Option<Redirect>, Box<dyn std::error::Error>> {
+    Ok(get_bang(bang)?.and_then(|idx| Some(get_redirects().ok()?.get(idx)?.to_redirect())))
+}
+
+/// Collects every redirect which took precedence over another definition of its trigger.
+///
+/// # Errors
+/// Errors if a read lock is unable to be acquired on the `REDIRECT_LIST`.
+pub fn get_conflicts() -> Result<Vec<Redirect>, Box<dyn std::error::Error>> {
+    Ok(get_redirects()?
+        .iter()
+        .filter(|r| !r.shadowed().is_empty())
+        .map(|r| r.to_redirect())
+        .collect())
+}
+
 /// Attempt to update a redirect, replacing it if found, and pushing it onto the [`REDIRECT_LIST`]
 /// if not found.
+/// A replaced redirect is recorded as shadowed by the new one (see [`Redirect::shadow`]).
 ///
 /// # Errors
 /// - if a write lock could not be optained on the [`REDIRECT_LIST`]
+/// - if the url template of `redirect` is invalid
 /// - if the [`get_bang`] fails
 /// - if the bang insertion fails
 pub fn update_redirect(redirect: &Redirect) -> Result<(), Box<dyn std::error::Error>> {
@@ -117,18 +365,64 @@ pub fn update_redirect(redirect: &Redirect) -> Result<(), Box<dyn std::error::Er
         .write()
         .map_err(|e| format!("RwLock poisoned: {e}"))?;
 
-    if let Some(idx) = get_bang(&redirect.trigger)? {
-        write_lock[idx] = redirect.clone();
+    if let Some(idx) = get_bang(&redirect.trigger)?
+        && let Some(previous) = write_lock.get(idx).map(|r| r.to_redirect())
+    {
+        let mut redirect = redirect.clone();
+        redirect.shadow(previous);
+        write_lock.replace(idx, redirect)?;
     } else {
-        write_lock.push(redirect.clone());
-        insert_bang(redirect.trigger.clone(), write_lock.len() - 1)
+        let idx = write_lock.push(redirect.clone())?;
+        insert_bang(redirect.trigger.clone(), idx)
             .map_err(|e| format!("Insert bang failed: {e}"))?;
     }
+    invalidate_trigger_index();
     drop(write_lock);
 
     Ok(())
 }
 
+/// Removes every bang which has expired by `now` (in seconds since the Unix epoch) from the list,
+/// returning their triggers.
+///
+/// The definition an expired bang shadowed most recently takes its place (see
+/// [`Redirect::unshadow`]).
+///
+/// # Errors
+/// If the locks on the list or the cache have been poisoned.
+pub fn remove_expired_bangs(now: u64) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut list = REDIRECT_LIST
+        .write()
+        .map_err(|e| format!("RwLock poisoned: {e}"))?;
+    if !list.iter().any(|bang| bang.is_expired(now)) {
+        return Ok(vec![]);
+    }
+
+    let mut expired = vec![];
+    let kept: BangStore = list
+        .iter()
+        .filter_map(|bang| {
+            if bang.is_expired(now) {
+                expired.push(bang.trigger().to_string());
+                return bang.to_redirect().unshadow();
+            }
+            Some(bang.to_redirect())
+        })
+        .collect();
+    let triggers: HashMap<String, usize> = kept
+        .iter()
+        .map(|bang| (bang.trigger().to_string(), bang.index()))
+        .collect();
+
+    let mut cache = CACHE.write().map_err(|e| format!("RwLock poisoned: {e}"))?;
+    *list = kept;
+    *cache = triggers;
+    invalidate_trigger_index();
+    drop(cache);
+    drop(list);
+    Ok(expired)
+}
+
 /// Set the value of the global `SEARCH_HISTORY_CACHE`.
 /// **This does not append, it overwrites.**
 ///
@@ -137,6 +431,13 @@ pub fn update_redirect(redirect: &Redirect) -> Result<(), Box<dyn std::error::Er
 /// Please check the documentation of [`std::sync::poison::rwlock::RwLock::try_write`] for more info
 #[cfg(feature = "history")]
 pub fn set_history_queries(queries: &[HistoryEntry]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut usage = BANG_USAGE.try_write()?;
+    usage.clear();
+    for entry in queries.iter().filter(|e| !e.query.0.is_empty()) {
+        *usage.entry(entry.query.0.clone()).or_default() += 1;
+    }
+    drop(usage);
+
     (*SEARCH_HISTORY_CACHE.try_write()?) = queries.to_vec();
     Ok(())
 }
@@ -148,6 +449,21 @@ pub fn set_history_queries(queries: &[HistoryEntry]) -> Result<(), Box<dyn std::
 /// Please check the documentation of [`std::sync::poison::rwlock::RwLock::try_write`] for more info
 #[cfg(feature = "history")]
 pub fn add_history_query(query: HistoryEntry) -> Result<(), Box<dyn std::error::Error>> {
+    if !query.query.0.is_empty() {
+        *BANG_USAGE
+            .try_write()?
+            .entry(query.query.0.clone())
+            .or_default() += 1;
+    }
     SEARCH_HISTORY_CACHE.try_write()?.push(query);
     Ok(())
 }
+
+/// How many times `bang` appears within the search history.
+#[cfg(feature = "history")]
+#[must_use]
+pub fn get_bang_usage(bang: &str) -> u32 {
+    BANG_USAGE
+        .read()
+        .map_or(0, |usage| usage.get(bang).copied().unwrap_or_default())
+}
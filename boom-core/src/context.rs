@@ -0,0 +1,187 @@
+//! Details of the request a query was made in, which templates may reference alongside the query
+//! itself (see [`Variable`](crate::template::Variable)).
+
+use std::{
+    fmt::Write,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::SourceIdentifier;
+
+const SECONDS_PER_DAY: u64 = 60 * 60 * 24;
+
+/// Where, when and by whom a query was made.
+///
+/// Anything which is not known renders as an empty string.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestContext<'a> {
+    /// The preferred language of the user, such as `en-GB`.
+    pub lang: Option<&'a str>,
+    /// The source identifier the query was made with.
+    pub source_identifier: Option<&'a str>,
+    /// The host `boom` is being served from, such as `localhost:3000`.
+    pub host: Option<&'a str>,
+    /// When the query was made. Dates are rendered in UTC.
+    pub now: SystemTime,
+    /// Values for the parameters of the bang being resolved, by name, which take precedence over
+    /// those given within the query.
+    pub params: &'a [(&'a str, &'a str)],
+}
+
+impl Default for RequestContext<'_> {
+    fn default() -> Self {
+        Self {
+            lang: None,
+            source_identifier: None,
+            host: None,
+            now: SystemTime::now(),
+            params: &[],
+        }
+    }
+}
+
+impl<'a> RequestContext<'a> {
+    /// A context made now, with nothing else known.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the preferred language from the value of an `Accept-Language` header.
+    #[must_use]
+    pub fn with_accept_language(mut self, accept_language: &'a str) -> Self {
+        self.lang = preferred_language(accept_language);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_source_identifier(mut self, source_identifier: &'a SourceIdentifier) -> Self {
+        self.source_identifier = match source_identifier {
+            SourceIdentifier::Identifier(si) => Some(si.as_str()),
+            SourceIdentifier::Empty => None,
+        };
+        self
+    }
+
+    #[must_use]
+    pub const fn with_host(mut self, host: &'a str) -> Self {
+        self.host = Some(host);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_params(mut self, params: &'a [(&'a str, &'a str)]) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// The value given for the parameter `name`, if any.
+    #[must_use]
+    pub fn param(&self, name: &str) -> Option<&'a str> {
+        self.params
+            .iter()
+            .find_map(|(param, value)| (*param == name).then_some(*value))
+    }
+
+    /// Writes the date `days_ago` days before [`Self::now`] as `YYYY-MM-DD`.
+    ///
+    /// # Errors
+    /// If `out` errors.
+    pub fn write_date<W: Write>(&self, days_ago: u16, out: &mut W) -> std::fmt::Result {
+        let then = self
+            .now
+            .checked_sub(Duration::from_secs(u64::from(days_ago) * SECONDS_PER_DAY))
+            .unwrap_or(UNIX_EPOCH);
+        let days = then
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() / SECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        write!(out, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+/// The most preferred language within an `Accept-Language` header, ignoring any wildcard.
+///
+/// # Example
+/// ```
+/// use boom_core::context::preferred_language;
+///
+/// assert_eq!(preferred_language("en-GB,en;q=0.9,fr;q=0.8"), Some("en-GB"));
+/// assert_eq!(preferred_language("fr;q=0.5, de"), Some("de"));
+/// assert_eq!(preferred_language("*"), None);
+/// ```
+#[must_use]
+pub fn preferred_language(accept_language: &str) -> Option<&str> {
+    let mut best: Option<(&str, f32)> = None;
+    for entry in accept_language.split(',') {
+        let mut params = entry.split(';');
+        let tag = params.next().unwrap_or_default().trim();
+        if tag.is_empty() || tag == "*" {
+            continue;
+        }
+        let quality = params
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .and_then(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if best.is_none_or(|(_, best)| quality > best) {
+            best = Some((tag, quality));
+        }
+    }
+    best.filter(|(_, quality)| *quality > 0.0)
+        .map(|(tag, _)| tag)
+}
+
+/// The `(year, month, day)` which is `days` days after 1970-01-01, in the proleptic Gregorian
+/// calendar.
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+const fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Shift the epoch to 0000-03-01, such that leap days fall at the end of each year.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[allow(unused_imports)]
+    use super::{RequestContext, civil_from_days};
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(20_453), (2025, 12, 31));
+    }
+
+    #[test]
+    fn test_write_date() {
+        let context = RequestContext {
+            // 2024-03-05T12:00:00Z
+            now: UNIX_EPOCH + Duration::from_secs(1_709_640_000),
+            ..RequestContext::default()
+        };
+        let mut date = String::new();
+        context.write_date(0, &mut date).unwrap();
+        assert_eq!(date, "2024-03-05");
+
+        date.clear();
+        context.write_date(7, &mut date).unwrap();
+        assert_eq!(date, "2024-02-27");
+    }
+}
@@ -0,0 +1,217 @@
+//! Percent-encoding of search queries, written straight into the url being built.
+//!
+//! How a query is encoded depends on the [`UrlComponent`] its placeholder sits within, following
+//! RFC 3986:
+//! - [`UrlComponent::Path`] keeps `/`, so that templates such as `https://github.com/{{{s}}}`
+//!   can be given a path.
+//! - [`UrlComponent::Query`] escapes everything except unreserved characters, as
+//!   [`urlencoding::encode`] would, so that the query cannot leak into other parameters.
+//! - [`UrlComponent::Fragment`] keeps `/` and `?`, which are commonly used by client-side routers.
+//!
+//! Queries are percent-encoded as UTF-8, unless a bang names a legacy charset for its site (see
+//! [`encode_charset_into`]).
+
+use std::fmt::{self, Display, Write};
+
+use boom_config::UrlComponent;
+use encoding_rs::{Encoding, UTF_8};
+
+const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Whether `byte` can be copied into `component` without being escaped.
+#[inline]
+const fn is_unescaped(byte: u8, component: UrlComponent) -> bool {
+    match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => true,
+        b'/' => matches!(component, UrlComponent::Path | UrlComponent::Fragment),
+        b'?' => matches!(component, UrlComponent::Fragment),
+        _ => false,
+    }
+}
+
+/// Writes `query` into `out`, percent-encoded for `component`.
+///
+/// Runs of characters which need no escaping are written in one go, so that nothing needs to be
+/// buffered.
+///
+/// # Errors
+/// If `out` errors.
+pub fn encode_to<W: Write>(query: &str, component: UrlComponent, out: &mut W) -> fmt::Result {
+    encode_bytes_to(query.as_bytes(), component, out)
+}
+
+fn encode_bytes_to<W: Write>(bytes: &[u8], component: UrlComponent, out: &mut W) -> fmt::Result {
+    let mut run_start = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        if is_unescaped(byte, component) {
+            continue;
+        }
+        if run_start < i {
+            out.write_str(ascii(&bytes[run_start..i]))?;
+        }
+        let escaped = [
+            b'%',
+            HEX[usize::from(byte >> 4)],
+            HEX[usize::from(byte & 0xF)],
+        ];
+        out.write_str(ascii(&escaped))?;
+        run_start = i + 1;
+    }
+    if run_start < bytes.len() {
+        out.write_str(ascii(&bytes[run_start..]))?;
+    }
+    Ok(())
+}
+
+/// Every non-ASCII byte is escaped, so every run of unescaped bytes is ASCII.
+#[inline]
+fn ascii(run: &[u8]) -> &str {
+    std::str::from_utf8(run).expect("Unescaped runs should be ASCII")
+}
+
+/// Appends `query` to `out`, percent-encoded for `component`. Nothing is allocated if `out` has
+/// the capacity.
+///
+/// # Example
+/// ```
+/// use boom_config::UrlComponent;
+/// use boom_core::encode::encode_into;
+///
+/// let mut url = String::from("https://github.com/");
+/// encode_into("tobybridle/boom issues", UrlComponent::Path, &mut url);
+/// assert_eq!(url, "https://github.com/tobybridle/boom%20issues");
+///
+/// let mut url = String::from("https://google.com/search?q=");
+/// encode_into("tobybridle/boom issues", UrlComponent::Query, &mut url);
+/// assert_eq!(url, "https://google.com/search?q=tobybridle%2Fboom%20issues");
+/// ```
+#[inline]
+pub fn encode_into(query: &str, component: UrlComponent, out: &mut String) {
+    encode_to(query, component, out).expect("Writing into a String should not fail");
+}
+
+/// Appends `query` to `out`, transcoded into `encoding` and then percent-encoded for `component`.
+///
+/// Characters which `encoding` cannot represent are written as HTML numeric character references
+/// (such as `&#129408;`), as browsers do when submitting a form.
+///
+/// # Example
+/// ```
+/// use boom_config::UrlComponent;
+/// use boom_core::encode::{encode_charset_into, lookup_charset};
+///
+/// let mut url = String::from("https://kakaku.com/search_results/");
+/// let shift_jis = lookup_charset("shift_jis").unwrap();
+/// encode_charset_into("日本", shift_jis, UrlComponent::Path, &mut url);
+/// assert_eq!(url, "https://kakaku.com/search_results/%93%FA%96%7B");
+/// ```
+pub fn encode_charset_into(
+    query: &str,
+    encoding: &'static Encoding,
+    component: UrlComponent,
+    out: &mut String,
+) {
+    if encoding == UTF_8 {
+        return encode_into(query, component, out);
+    }
+    let (bytes, _, _) = encoding.encode(query);
+    encode_bytes_to(&bytes, component, out).expect("Writing into a String should not fail");
+}
+
+/// The charset named by `label`, such as `"shift_jis"`, `"gbk"` or `"windows-1251"`.
+///
+/// Labels are those of the [WHATWG Encoding Standard](https://encoding.spec.whatwg.org/#names-and-labels),
+/// and are matched case-insensitively.
+#[must_use]
+pub fn lookup_charset(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes())
+}
+
+/// Displays a query percent-encoded for a [`UrlComponent`], for use with any [`Write`]r.
+///
+/// # Example
+/// ```
+/// use boom_config::UrlComponent;
+/// use boom_core::encode::Encoded;
+///
+/// assert_eq!(Encoded("a b/c?", UrlComponent::Fragment).to_string(), "a%20b/c?");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Encoded<'a>(pub &'a str, pub UrlComponent);
+
+impl Display for Encoded<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        encode_to(self.0, self.1, f)
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use boom_config::UrlComponent;
+
+    #[allow(unused_imports)]
+    use super::{encode_charset_into, encode_into, lookup_charset};
+
+    #[allow(dead_code)]
+    const QUERIES: [&str; 7] = [
+        "",
+        "test query",
+        "tobybridle/boom",
+        "100% of ~everything~ & more?=#",
+        "ünïcödé 日本語 ∑ 🦀",
+        "a-b_c.d~e/f%2Fg",
+        "\t\n\r\0",
+    ];
+
+    #[allow(dead_code)]
+    fn encode(query: &str, component: UrlComponent) -> String {
+        let mut encoded = String::new();
+        encode_into(query, component, &mut encoded);
+        encoded
+    }
+
+    #[test]
+    fn test_encode_matches_urlencoding() {
+        for query in QUERIES {
+            assert_eq!(
+                encode(query, UrlComponent::Query),
+                urlencoding::encode(query),
+                "{query:?}"
+            );
+            assert_eq!(
+                encode(query, UrlComponent::Path),
+                urlencoding::encode(query).replace("%2F", "/"),
+                "{query:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_encode_fragment() {
+        assert_eq!(
+            encode("search/all?q=a&b", UrlComponent::Fragment),
+            "search/all?q%3Da%26b"
+        );
+    }
+
+    #[test]
+    fn test_encode_charset() {
+        let encode_as = |query: &str, label: &str| {
+            let mut encoded = String::new();
+            let encoding = lookup_charset(label).unwrap();
+            encode_charset_into(query, encoding, UrlComponent::Query, &mut encoded);
+            encoded
+        };
+        assert_eq!(encode_as("日本 a/b", "Shift_JIS"), "%93%FA%96%7B%20a%2Fb");
+        assert_eq!(encode_as("中文", "gbk"), "%D6%D0%CE%C4");
+        assert_eq!(encode_as("привет", "windows-1251"), "%EF%F0%E8%E2%E5%F2");
+        assert_eq!(encode_as("🦀", "windows-1251"), "%26%23129408%3B");
+        for query in QUERIES {
+            assert_eq!(
+                encode_as(query, "utf-8"),
+                encode(query, UrlComponent::Query)
+            );
+        }
+        assert!(lookup_charset("not-a-charset").is_none());
+    }
+}
@@ -4,16 +4,23 @@
 //! It provides functions for efficiently extracting data from queries and templates,
 //! as well as higher-level functions such as `resolve`
 
-use std::{cmp::Ordering, time::Duration};
+use std::{
+    cmp::Ordering, collections::BTreeMap, fmt::Display, path::PathBuf, sync::Arc, time::Duration,
+};
 
+use boom_config::{BangParamConfig, HttpMethod, MirrorConfig, UrlComponent, WebhookConfig};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
 pub mod boom;
 pub mod cache;
+pub mod context;
+pub mod encode;
+pub mod store;
+pub mod template;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Redirect {
     /// The short name or abbreviation of the bang command.
     #[serde(rename = "s")]
@@ -24,6 +31,162 @@ pub struct Redirect {
     /// The URL template where the search term is inserted.
     #[serde(rename = "u")]
     pub url_template: String,
+    /// Encodes every placeholder as part of this component, rather than the one it sits within.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escape: Option<UrlComponent>,
+    /// The charset the site expects its queries in, if other than UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// Named values the url template references, besides the query.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<BangParamConfig>,
+    /// How the url is requested.
+    #[serde(default, skip_serializing_if = "HttpMethod::is_get")]
+    pub method: HttpMethod,
+    /// Form fields submitted to the url by `"post"` bangs, whose values are templates.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+    /// Asks the url template, as an endpoint, for the url to redirect to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<WebhookConfig>,
+    /// Other instances of the same site, chosen between alongside the url template.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirrors: Option<MirrorConfig>,
+    /// When the bang expires, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// How popular the bang is according to its source, used to rank completions.
+    #[serde(rename = "r", default)]
+    pub relevance: u32,
+    /// Where the bang was defined.
+    #[serde(default)]
+    pub origin: Arc<BangOrigin>,
+    /// Definitions of the same trigger which this bang took precedence over.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shadowed: Vec<ShadowedBang>,
+}
+
+impl Redirect {
+    /// Records `other`, along with anything it had shadowed itself, as being shadowed by `self`.
+    ///
+    /// Definitions sharing an origin with `self` are skipped, so reloading a source does not
+    /// report the source as conflicting with itself.
+    pub fn shadow(&mut self, mut other: Self) {
+        let shadowed = std::mem::take(&mut other.shadowed);
+        for definition in shadowed.into_iter().chain([ShadowedBang::from(other)]) {
+            if definition.origin != self.origin
+                && !self.shadowed.iter().any(|s| s.origin == definition.origin)
+            {
+                self.shadowed.push(definition);
+            }
+        }
+    }
+
+    /// The definition `self` shadowed most recently, with whatever else `self` had shadowed, to
+    /// take its place once it is removed (such as when it expires).
+    #[must_use]
+    pub fn unshadow(mut self) -> Option<Self> {
+        let definition = self.shadowed.pop()?;
+        Some(Self {
+            short_name: definition.short_name,
+            trigger: self.trigger,
+            url_template: definition.url_template,
+            escape: definition.escape,
+            encoding: definition.encoding,
+            params: definition.params,
+            method: definition.method,
+            fields: definition.fields,
+            webhook: definition.webhook,
+            mirrors: definition.mirrors,
+            expires_at: definition.expires_at,
+            relevance: definition.relevance,
+            origin: definition.origin,
+            shadowed: self.shadowed,
+        })
+    }
+}
+
+/// Where a [`Redirect`] was loaded from.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BangOrigin {
+    /// An external source (`[[bangs.source]]`), read from `filepath` and fetched from `remote`.
+    Source {
+        filepath: PathBuf,
+        remote: Option<String>,
+    },
+    /// A `[bangs.custom]` entry within the config file at `path`.
+    Config { path: PathBuf },
+    /// Added at runtime through `/api/add-bang`.
+    Api,
+    #[default]
+    Unknown,
+}
+
+impl Display for BangOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Source {
+                filepath,
+                remote: Some(remote),
+            } => write!(f, "source \"{}\" ({remote})", filepath.display()),
+            Self::Source {
+                filepath,
+                remote: None,
+            } => write!(f, "source \"{}\"", filepath.display()),
+            Self::Config { path } => write!(f, "config \"{}\"", path.display()),
+            Self::Api => f.write_str("api"),
+            Self::Unknown => f.write_str("unknown"),
+        }
+    }
+}
+
+/// A definition of a trigger which lost out to another [`Redirect`], kept in full so that it can
+/// take the place of the other again (see [`Redirect::unshadow`]).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShadowedBang {
+    #[serde(rename = "s")]
+    pub short_name: String,
+    #[serde(rename = "u")]
+    pub url_template: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escape: Option<UrlComponent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<BangParamConfig>,
+    #[serde(default, skip_serializing_if = "HttpMethod::is_get")]
+    pub method: HttpMethod,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<WebhookConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirrors: Option<MirrorConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    #[serde(rename = "r", default)]
+    pub relevance: u32,
+    pub origin: Arc<BangOrigin>,
+}
+
+impl From<Redirect> for ShadowedBang {
+    fn from(redirect: Redirect) -> Self {
+        Self {
+            short_name: redirect.short_name,
+            url_template: redirect.url_template,
+            escape: redirect.escape,
+            encoding: redirect.encoding,
+            params: redirect.params,
+            method: redirect.method,
+            fields: redirect.fields,
+            webhook: redirect.webhook,
+            mirrors: redirect.mirrors,
+            expires_at: redirect.expires_at,
+            relevance: redirect.relevance,
+            origin: redirect.origin,
+        }
+    }
 }
 
 const SOURCE_IDENTIFIER_EMPTY: &str = "n/a";
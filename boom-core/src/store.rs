@@ -0,0 +1,694 @@
+//! Compact storage for the loaded bangs.
+//!
+//! Rather than holding three owned [`String`]s per bang, a [`BangStore`] copies every string into
+//! a single arena and refers to them through [`Span`]s. Url templates are compiled into their
+//! [`Segment`]s as they are stored, so they never need to be parsed again whilst resolving.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem::size_of,
+    sync::Arc,
+};
+
+use boom_config::{BangParamConfig, HttpMethod, UrlComponent};
+use encoding_rs::{Encoding, UTF_8};
+use serde::{Serialize, ser::SerializeStruct};
+use tracing::warn;
+
+use crate::{
+    BangOrigin, Redirect, ShadowedBang,
+    boom::{mirrors::MirrorPool, webhook::Webhook},
+    encode::lookup_charset,
+    template::{Segment, Span, Template, TemplateError, TemplatePart, compile_segments},
+};
+
+#[derive(Debug, Clone)]
+struct Entry {
+    short_name: Span,
+    trigger: Span,
+    url_template: Span,
+    /// Index of the first of this entry's segments, followed by how many there are.
+    segments: (u32, u32),
+    relevance: u32,
+    origin: u32,
+    escape: Option<UrlComponent>,
+    /// Absent for UTF-8, which needs no transcoding.
+    charset: Option<&'static Encoding>,
+    method: HttpMethod,
+}
+
+/// Every loaded bang, stored within a single text arena.
+///
+/// Bangs are addressed by their index, which is what `CACHE` maps each trigger to.
+#[derive(Debug, Default)]
+pub struct BangStore {
+    text: String,
+    segments: Vec<Segment>,
+    entries: Vec<Entry>,
+    /// Distinct origins, shared between the bangs defined by them.
+    origins: Vec<Arc<BangOrigin>>,
+    /// Shadowed definitions are rare, so only the bangs which have them get an entry here.
+    shadowed: HashMap<u32, Vec<ShadowedBang>>,
+    /// As are parameters, form fields, webhooks, mirrors and expiries, which only custom bangs
+    /// declare.
+    params: HashMap<u32, Vec<BangParamConfig>>,
+    fields: HashMap<u32, Vec<(String, Template)>>,
+    webhooks: HashMap<u32, Webhook>,
+    mirrors: HashMap<u32, MirrorPool>,
+    expiries: HashMap<u32, u64>,
+}
+
+impl BangStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of bangs within the store.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get a view of the bang at `idx`.
+    #[must_use]
+    pub fn get(&self, idx: usize) -> Option<BangRef<'_>> {
+        self.entries.get(idx).map(|entry| BangRef {
+            store: self,
+            idx,
+            entry,
+        })
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = BangRef<'_>> {
+        self.entries.iter().enumerate().map(|(idx, entry)| BangRef {
+            store: self,
+            idx,
+            entry,
+        })
+    }
+
+    /// Copies `redirect` into the store, compiling its url template, and returns its index.
+    ///
+    /// # Errors
+    /// If the url template of `redirect` is invalid, in which case nothing is stored.
+    ///
+    /// # Panics
+    /// If the text arena grows beyond [`u32::MAX`] bytes.
+    pub fn push(&mut self, redirect: Redirect) -> Result<usize, TemplateError> {
+        let (entry, extras) = self.store_entry(redirect)?;
+        let idx = self.entries.len();
+        self.entries.push(entry);
+        self.set_extras(idx, extras);
+        Ok(idx)
+    }
+
+    /// Replaces the bang at `idx` with `redirect`.
+    ///
+    /// The text of the replaced bang is not reclaimed until the store is rebuilt, which happens
+    /// whenever the config is reloaded.
+    ///
+    /// # Errors
+    /// If the url template of `redirect` is invalid, in which case the bang is left untouched.
+    ///
+    /// # Panics
+    /// If `idx` is out of bounds, or the text arena grows beyond [`u32::MAX`] bytes.
+    pub fn replace(&mut self, idx: usize, redirect: Redirect) -> Result<(), TemplateError> {
+        assert!(idx < self.entries.len(), "Bang index out of bounds");
+        let (entry, extras) = self.store_entry(redirect)?;
+        self.entries[idx] = entry;
+        self.set_extras(idx, extras);
+        Ok(())
+    }
+
+    /// Bytes allocated on the heap by the store.
+    #[must_use]
+    pub fn heap_size(&self) -> usize {
+        self.text.capacity()
+            + self.segments.capacity() * size_of::<Segment>()
+            + self.entries.capacity() * size_of::<Entry>()
+            + self.origins.capacity() * (size_of::<Arc<BangOrigin>>() + size_of::<BangOrigin>())
+            + self.shadowed.capacity() * size_of::<(u32, Vec<ShadowedBang>)>()
+            + self
+                .shadowed
+                .values()
+                .flatten()
+                .map(|s| size_of::<ShadowedBang>() + s.short_name.len() + s.url_template.len())
+                .sum::<usize>()
+            + self.params.capacity() * size_of::<(u32, Vec<BangParamConfig>)>()
+            + self
+                .params
+                .values()
+                .flatten()
+                .map(|p| size_of::<BangParamConfig>() + p.name.len() + p.label.len())
+                .sum::<usize>()
+            + self.fields.capacity() * size_of::<(u32, Vec<(String, Template)>)>()
+            + self
+                .fields
+                .values()
+                .flatten()
+                .map(|(name, template)| {
+                    size_of::<(String, Template)>() + name.len() + template.source().len()
+                })
+                .sum::<usize>()
+            + self.webhooks.capacity() * size_of::<(u32, Webhook)>()
+            + self.mirrors.capacity() * size_of::<(u32, MirrorPool)>()
+            + self
+                .mirrors
+                .values()
+                .flat_map(MirrorPool::templates)
+                .map(|template| size_of::<Template>() + template.source().len())
+                .sum::<usize>()
+            + self.expiries.capacity() * size_of::<(u32, u64)>()
+    }
+
+    /// Releases any capacity which is not in use.
+    pub fn shrink_to_fit(&mut self) {
+        self.text.shrink_to_fit();
+        self.segments.shrink_to_fit();
+        self.entries.shrink_to_fit();
+        self.origins.shrink_to_fit();
+        self.shadowed.shrink_to_fit();
+        self.params.shrink_to_fit();
+        self.fields.shrink_to_fit();
+        self.webhooks.shrink_to_fit();
+        self.mirrors.shrink_to_fit();
+        self.expiries.shrink_to_fit();
+    }
+
+    fn store_entry(&mut self, redirect: Redirect) -> Result<(Entry, Extras), TemplateError> {
+        let Redirect {
+            short_name,
+            trigger,
+            url_template,
+            escape,
+            encoding,
+            params,
+            method,
+            fields,
+            webhook,
+            mirrors,
+            expires_at,
+            relevance,
+            origin,
+            shadowed,
+        } = redirect;
+
+        let fields = fields
+            .into_iter()
+            .map(|(name, value)| Ok((name, Template::compile_with_params(value, &params)?)))
+            .collect::<Result<Vec<_>, TemplateError>>()?;
+        let webhook = webhook
+            .map(|webhook| Webhook::compile(webhook, &params))
+            .transpose()?;
+        let mirrors = mirrors
+            .map(|mirrors| MirrorPool::compile(mirrors, escape, &params))
+            .transpose()?;
+
+        let first_segment = self.segments.len();
+        compile_segments(
+            &url_template,
+            self.text.len(),
+            escape,
+            &params,
+            &mut self.segments,
+        )?;
+        let segments = (
+            u32::try_from(first_segment).expect("Segments should fit within u32"),
+            u32::try_from(self.segments.len() - first_segment)
+                .expect("Segments should fit within u32"),
+        );
+        let url_template = self.push_str(&url_template);
+        let charset = encoding.and_then(|label| {
+            let charset = lookup_charset(&label);
+            if charset.is_none() {
+                warn!("`!{trigger}` has an unknown encoding ({label}), using UTF-8.");
+            }
+            charset.filter(|charset| *charset != UTF_8)
+        });
+        let origin = self.intern_origin(origin);
+
+        let entry = Entry {
+            short_name: self.push_str(&short_name),
+            trigger: self.push_str(&trigger),
+            url_template,
+            segments,
+            relevance,
+            origin,
+            escape,
+            charset,
+            method,
+        };
+        Ok((
+            entry,
+            Extras {
+                shadowed,
+                params,
+                fields,
+                webhook,
+                mirrors,
+                expires_at,
+            },
+        ))
+    }
+
+    fn set_extras(&mut self, idx: usize, extras: Extras) {
+        set_or_remove(&mut self.shadowed, Self::key(idx), extras.shadowed);
+        set_or_remove(&mut self.params, Self::key(idx), extras.params);
+        set_or_remove(&mut self.fields, Self::key(idx), extras.fields);
+        if let Some(webhook) = extras.webhook {
+            self.webhooks.insert(Self::key(idx), webhook);
+        } else {
+            self.webhooks.remove(&Self::key(idx));
+        }
+        if let Some(mirrors) = extras.mirrors {
+            self.mirrors.insert(Self::key(idx), mirrors);
+        } else {
+            self.mirrors.remove(&Self::key(idx));
+        }
+        if let Some(expires_at) = extras.expires_at {
+            self.expiries.insert(Self::key(idx), expires_at);
+        } else {
+            self.expiries.remove(&Self::key(idx));
+        }
+    }
+
+    fn push_str(&mut self, s: &str) -> Span {
+        let start = self.text.len();
+        self.text.push_str(s);
+        Span::new(start, self.text.len()).expect("Bang text should fit within u32")
+    }
+
+    fn intern_origin(&mut self, origin: Arc<BangOrigin>) -> u32 {
+        let idx = self
+            .origins
+            .iter()
+            .position(|o| Arc::ptr_eq(o, &origin) || *o == origin)
+            .unwrap_or_else(|| {
+                self.origins.push(origin);
+                self.origins.len() - 1
+            });
+        u32::try_from(idx).expect("Origins should fit within u32")
+    }
+
+    fn key(idx: usize) -> u32 {
+        u32::try_from(idx).expect("Bang index should fit within u32")
+    }
+}
+
+/// Whatever few bangs have, which is kept aside from their [`Entry`].
+struct Extras {
+    shadowed: Vec<ShadowedBang>,
+    params: Vec<BangParamConfig>,
+    fields: Vec<(String, Template)>,
+    webhook: Option<Webhook>,
+    mirrors: Option<MirrorPool>,
+    expires_at: Option<u64>,
+}
+
+/// Keeps `values` within `map`, unless there are none.
+fn set_or_remove<T>(map: &mut HashMap<u32, Vec<T>>, key: u32, values: Vec<T>) {
+    if values.is_empty() {
+        map.remove(&key);
+    } else {
+        map.insert(key, values);
+    }
+}
+
+impl FromIterator<Redirect> for BangStore {
+    fn from_iter<T: IntoIterator<Item = Redirect>>(iter: T) -> Self {
+        let mut store = Self::new();
+        store.extend(iter);
+        store.shrink_to_fit();
+        store
+    }
+}
+
+/// Bangs with an invalid url template are skipped, with a warning.
+impl Extend<Redirect> for BangStore {
+    fn extend<T: IntoIterator<Item = Redirect>>(&mut self, iter: T) {
+        let iter = iter.into_iter();
+        self.entries.reserve(iter.size_hint().0);
+        for redirect in iter {
+            let trigger = redirect.trigger.clone();
+            if let Err(e) = self.push(redirect) {
+                warn!("Skipping `!{trigger}`, its template is invalid: {e}");
+            }
+        }
+    }
+}
+
+/// A borrowed view of a single bang within a [`BangStore`].
+#[derive(Debug, Clone, Copy)]
+pub struct BangRef<'a> {
+    store: &'a BangStore,
+    idx: usize,
+    entry: &'a Entry,
+}
+
+impl<'a> BangRef<'a> {
+    /// Index of the bang within its store.
+    #[must_use]
+    pub const fn index(&self) -> usize {
+        self.idx
+    }
+
+    #[must_use]
+    pub fn short_name(&self) -> &'a str {
+        &self.store.text[self.entry.short_name.range()]
+    }
+
+    #[must_use]
+    pub fn trigger(&self) -> &'a str {
+        &self.store.text[self.entry.trigger.range()]
+    }
+
+    #[must_use]
+    pub fn url_template(&self) -> &'a str {
+        &self.store.text[self.entry.url_template.range()]
+    }
+
+    /// The component every placeholder is encoded for, if overridden.
+    #[must_use]
+    pub const fn escape(&self) -> Option<UrlComponent> {
+        self.entry.escape
+    }
+
+    /// The charset queries are transcoded into before being percent-encoded, if not UTF-8.
+    #[must_use]
+    pub const fn charset(&self) -> Option<&'static Encoding> {
+        self.entry.charset
+    }
+
+    #[must_use]
+    pub const fn relevance(&self) -> u32 {
+        self.entry.relevance
+    }
+
+    #[must_use]
+    pub fn origin(&self) -> &'a Arc<BangOrigin> {
+        &self.store.origins[self.entry.origin as usize]
+    }
+
+    #[must_use]
+    pub fn shadowed(&self) -> &'a [ShadowedBang] {
+        u32::try_from(self.idx)
+            .ok()
+            .and_then(|idx| self.store.shadowed.get(&idx))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The parameters declared by the bang, in the order they are asked for.
+    #[must_use]
+    pub fn params(&self) -> &'a [BangParamConfig] {
+        u32::try_from(self.idx)
+            .ok()
+            .and_then(|idx| self.store.params.get(&idx))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// How the url is requested.
+    #[must_use]
+    pub const fn method(&self) -> HttpMethod {
+        self.entry.method
+    }
+
+    /// The form fields submitted to the url by `"post"` bangs, with their compiled templates.
+    #[must_use]
+    pub fn fields(&self) -> &'a [(String, Template)] {
+        u32::try_from(self.idx)
+            .ok()
+            .and_then(|idx| self.store.fields.get(&idx))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The webhook asked for the url, if the url template is an endpoint.
+    #[must_use]
+    pub fn webhook(&self) -> Option<&'a Webhook> {
+        u32::try_from(self.idx)
+            .ok()
+            .and_then(|idx| self.store.webhooks.get(&idx))
+    }
+
+    /// The mirrors chosen between alongside the url template, if any.
+    #[must_use]
+    pub fn mirrors(&self) -> Option<&'a MirrorPool> {
+        u32::try_from(self.idx)
+            .ok()
+            .and_then(|idx| self.store.mirrors.get(&idx))
+    }
+
+    /// When the bang expires, in seconds since the Unix epoch, if it is temporary.
+    #[must_use]
+    pub fn expires_at(&self) -> Option<u64> {
+        u32::try_from(self.idx)
+            .ok()
+            .and_then(|idx| self.store.expiries.get(&idx))
+            .copied()
+    }
+
+    /// Whether the bang has expired by `now`, in seconds since the Unix epoch.
+    #[must_use]
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at()
+            .is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// The url template, split into the parts surrounding each query placeholder.
+    pub fn segments(&self) -> impl ExactSizeIterator<Item = TemplatePart<'a>> + 'a {
+        let (first, count) = self.entry.segments;
+        let text = self.store.text.as_str();
+        self.store.segments[first as usize..(first + count) as usize]
+            .iter()
+            .map(move |segment| TemplatePart::from_segment(*segment, text))
+    }
+
+    /// Copies the bang out of the store.
+    #[must_use]
+    pub fn to_redirect(&self) -> Redirect {
+        Redirect {
+            short_name: self.short_name().to_string(),
+            trigger: self.trigger().to_string(),
+            url_template: self.url_template().to_string(),
+            escape: self.escape(),
+            encoding: self.charset().map(|charset| charset.name().to_string()),
+            params: self.params().to_vec(),
+            method: self.method(),
+            fields: self
+                .fields()
+                .iter()
+                .map(|(name, template)| (name.clone(), template.source().to_string()))
+                .collect(),
+            webhook: self.webhook().map(|webhook| webhook.config.clone()),
+            mirrors: self.mirrors().map(|mirrors| mirrors.config.clone()),
+            expires_at: self.expires_at(),
+            relevance: self.relevance(),
+            origin: Arc::clone(self.origin()),
+            shadowed: self.shadowed().to_vec(),
+        }
+    }
+}
+
+impl Serialize for BangRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let shadowed = self.shadowed();
+        let mut s = serializer.serialize_struct("Redirect", 14)?;
+        s.serialize_field("s", self.short_name())?;
+        s.serialize_field("t", self.trigger())?;
+        s.serialize_field("u", self.url_template())?;
+        if let Some(escape) = self.escape() {
+            s.serialize_field("escape", &escape)?;
+        } else {
+            s.skip_field("escape")?;
+        }
+        if let Some(charset) = self.charset() {
+            s.serialize_field("encoding", charset.name())?;
+        } else {
+            s.skip_field("encoding")?;
+        }
+        let params = self.params();
+        if params.is_empty() {
+            s.skip_field("params")?;
+        } else {
+            s.serialize_field("params", params)?;
+        }
+        if self.method().is_get() {
+            s.skip_field("method")?;
+        } else {
+            s.serialize_field("method", &self.method())?;
+        }
+        let fields = self.fields();
+        if fields.is_empty() {
+            s.skip_field("fields")?;
+        } else {
+            s.serialize_field(
+                "fields",
+                &fields
+                    .iter()
+                    .map(|(name, template)| (name, template.source()))
+                    .collect::<BTreeMap<_, _>>(),
+            )?;
+        }
+        if let Some(webhook) = self.webhook() {
+            s.serialize_field("webhook", &webhook.config)?;
+        } else {
+            s.skip_field("webhook")?;
+        }
+        if let Some(mirrors) = self.mirrors() {
+            s.serialize_field("mirrors", &mirrors.config)?;
+        } else {
+            s.skip_field("mirrors")?;
+        }
+        if let Some(expires_at) = self.expires_at() {
+            s.serialize_field("expires_at", &expires_at)?;
+        } else {
+            s.skip_field("expires_at")?;
+        }
+        s.serialize_field("r", &self.relevance())?;
+        s.serialize_field("origin", self.origin().as_ref())?;
+        if shadowed.is_empty() {
+            s.skip_field("shadowed")?;
+        } else {
+            s.serialize_field("shadowed", shadowed)?;
+        }
+        s.end()
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use std::sync::Arc;
+
+    #[allow(unused_imports)]
+    use crate::{BangOrigin, Redirect};
+
+    #[allow(unused_imports)]
+    use super::{BangStore, HttpMethod, TemplatePart, UrlComponent};
+
+    #[allow(dead_code)]
+    fn redirect(trigger: &str, template: &str) -> Redirect {
+        Redirect {
+            short_name: trigger.to_uppercase(),
+            trigger: trigger.to_string(),
+            url_template: template.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        let store: BangStore = [
+            redirect("gh", "https://github.com/{{{s}}}"),
+            redirect("yt", "https://youtube.com/results?search_query={{{s}}}"),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(store.len(), 2);
+        let gh = store.get(0).unwrap();
+        assert_eq!(gh.trigger(), "gh");
+        assert_eq!(gh.short_name(), "GH");
+        assert_eq!(gh.url_template(), "https://github.com/{{{s}}}");
+        assert_eq!(store.get(1).unwrap().trigger(), "yt");
+        assert!(store.get(2).is_none());
+    }
+
+    #[test]
+    fn test_store_segments() {
+        let store: BangStore = [
+            redirect("a", "https://a.com/{{{s}}}/search?q={{{s}}}&x=1"),
+            redirect("invalid", "https://invalid.com/?q={{{q}}}"),
+            redirect("b", "https://b.com"),
+            redirect("c", "{{{s}}}"),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(store.len(), 3);
+        assert_eq!(
+            store.get(0).unwrap().segments().collect::<Vec<_>>(),
+            vec![
+                TemplatePart::Literal("https://a.com/"),
+                TemplatePart::Query(UrlComponent::Path),
+                TemplatePart::Literal("/search?q="),
+                TemplatePart::Query(UrlComponent::Query),
+                TemplatePart::Literal("&x=1"),
+            ]
+        );
+        assert_eq!(
+            store.get(1).unwrap().segments().collect::<Vec<_>>(),
+            vec![TemplatePart::Literal("https://b.com")]
+        );
+        assert_eq!(
+            store.get(2).unwrap().segments().collect::<Vec<_>>(),
+            vec![TemplatePart::Query(UrlComponent::Path)]
+        );
+    }
+
+    #[test]
+    fn test_store_escape_override() {
+        let mut wiki = redirect("w", "https://w.org/?q={{{s}}}");
+        wiki.escape = Some(UrlComponent::Path);
+        let store: BangStore = [wiki].into_iter().collect();
+
+        let w = store.get(0).unwrap();
+        assert_eq!(w.escape(), Some(UrlComponent::Path));
+        assert_eq!(w.to_redirect().escape, Some(UrlComponent::Path));
+        assert_eq!(
+            w.segments().nth(1),
+            Some(TemplatePart::Query(UrlComponent::Path))
+        );
+    }
+
+    #[test]
+    fn test_store_fields() {
+        let mut intranet = redirect("intranet", "https://intranet.example.com/search");
+        intranet.method = HttpMethod::Post;
+        intranet
+            .fields
+            .insert("query".to_string(), "{{{s}}}".to_string());
+        let mut invalid = redirect("invalid", "https://invalid.com/search");
+        invalid
+            .fields
+            .insert("query".to_string(), "{{{q}}}".to_string());
+        let store: BangStore = [intranet, invalid].into_iter().collect();
+
+        assert_eq!(store.len(), 1);
+        let intranet = store.get(0).unwrap();
+        assert_eq!(intranet.method(), HttpMethod::Post);
+        assert_eq!(intranet.fields()[0].0, "query");
+        assert_eq!(
+            intranet
+                .to_redirect()
+                .fields
+                .get("query")
+                .map(String::as_str),
+            Some("{{{s}}}")
+        );
+    }
+
+    #[test]
+    fn test_store_replace_keeps_shadowed() {
+        let mut store: BangStore = [redirect("gh", "https://github.com/search?q={{{s}}}")]
+            .into_iter()
+            .collect();
+
+        let mut replacement = redirect("gh", "https://github.com/{{{s}}}");
+        replacement.origin = Arc::new(BangOrigin::Api);
+        replacement.shadow(store.get(0).unwrap().to_redirect());
+        store.replace(0, replacement).unwrap();
+
+        let gh = store.get(0).unwrap();
+        assert_eq!(gh.url_template(), "https://github.com/{{{s}}}");
+        assert_eq!(**gh.origin(), BangOrigin::Api);
+        assert_eq!(gh.shadowed().len(), 1);
+    }
+}
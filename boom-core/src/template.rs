@@ -0,0 +1,571 @@
+//! Url templates, compiled ahead of time.
+//!
+//! A template is split into [`Segment`]s once, when its source is loaded or the config is
+//! reloaded, so that resolving a query only has to concatenate the segments with the encoded
+//! query.
+//!
+//! Every placeholder records the [`UrlComponent`] it sits within, which decides how the query is
+//! encoded (see [`crate::encode`]). Placeholders before any `?` are within the path, those after a
+//! `?` are within the query, and those after a `#` are within the fragment.
+//!
+//! Besides `{{{s}}}`, templates may reference details of the request through [`Variable`]s, which
+//! are filled in from a [`RequestContext`]. Custom bangs may also reference the parameters they
+//! declare (see [`BangParamConfig`]), which take precedence over variables of the same name.
+
+use std::{error::Error, fmt::Display, ops::Range};
+
+use boom_config::{BangParamConfig, UrlComponent};
+use encoding_rs::Encoding;
+
+use crate::{
+    context::RequestContext,
+    encode::{encode_charset_into, encode_into},
+};
+
+/// The placeholder replaced by the search query within a url template.
+pub const QUERY_PLACEHOLDER: &str = "{{{s}}}";
+
+const PLACEHOLDER_OPEN: &str = "{{{";
+const PLACEHOLDER_CLOSE: &str = "}}}";
+
+/// A range of bytes within the text a template was compiled from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    start: u32,
+    len: u32,
+}
+
+impl Span {
+    /// The span from `start` up to (but excluding) `end`, if it can be addressed using `u32`s.
+    #[must_use]
+    pub fn new(start: usize, end: usize) -> Option<Self> {
+        Some(Self {
+            start: u32::try_from(start).ok()?,
+            len: u32::try_from(end.checked_sub(start)?).ok()?,
+        })
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn range(self) -> Range<usize> {
+        self.start as usize..(self.start + self.len) as usize
+    }
+}
+
+/// A placeholder, other than `{{{s}}}`, which is filled in from the [`RequestContext`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variable {
+    /// `{{{lang}}}`, the preferred language of the user, such as `en-GB`.
+    Lang,
+    /// `{{{si}}}`, the source identifier the query was made with.
+    SourceIdentifier,
+    /// `{{{host}}}`, the host `boom` is being served from.
+    Host,
+    /// `{{{date}}}` (or `{{{now}}}`), today's date as `YYYY-MM-DD`, whilst `{{{now-7d}}}` is the
+    /// date 7 days ago.
+    Date { days_ago: u16 },
+}
+
+impl Variable {
+    /// Every variable a template may use, as listed within errors.
+    pub const NAMES: &str = "s, lang, si, host, date, now, now-<days>d";
+
+    /// Parses the name between `{{{` and `}}}`.
+    ///
+    /// # Example
+    /// ```
+    /// use boom_core::template::Variable;
+    ///
+    /// assert_eq!(Variable::parse("now-7d"), Some(Variable::Date { days_ago: 7 }));
+    /// assert_eq!(Variable::parse("query"), None);
+    /// ```
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "lang" => Some(Self::Lang),
+            "si" => Some(Self::SourceIdentifier),
+            "host" => Some(Self::Host),
+            "date" | "now" => Some(Self::Date { days_ago: 0 }),
+            _ => name
+                .strip_prefix("now-")?
+                .strip_suffix('d')
+                .filter(|days| days.bytes().all(|b| b.is_ascii_digit()))?
+                .parse()
+                .ok()
+                .map(|days_ago| Self::Date { days_ago }),
+        }
+    }
+}
+
+/// Part of a compiled url template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    /// Text which is copied into the url as-is.
+    Literal(Span),
+    /// Where the search query is inserted, encoded for the given component.
+    Query(UrlComponent),
+    /// Where a variable is inserted, encoded for the given component.
+    Variable(Variable, UrlComponent),
+    /// Where the parameter named by the span is inserted, encoded for the given component.
+    Param(Span, UrlComponent),
+}
+
+/// A [`Segment`], borrowed alongside the text it was compiled from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplatePart<'a> {
+    Literal(&'a str),
+    Query(UrlComponent),
+    Variable(Variable, UrlComponent),
+    Param(&'a str, UrlComponent),
+}
+
+impl<'a> TemplatePart<'a> {
+    #[inline]
+    #[must_use]
+    pub fn from_segment(segment: Segment, text: &'a str) -> Self {
+        match segment {
+            Segment::Literal(span) => Self::Literal(&text[span.range()]),
+            Segment::Query(component) => Self::Query(component),
+            Segment::Variable(variable, component) => Self::Variable(variable, component),
+            Segment::Param(name, component) => Self::Param(&text[name.range()], component),
+        }
+    }
+}
+
+/// Why a url template could not be compiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// The template is empty.
+    Empty,
+    /// A `{{{` without a matching `}}}`, at the given byte offset.
+    Unterminated(usize),
+    /// A placeholder which is neither a [`Variable`] nor a declared parameter, at the given byte
+    /// offset.
+    UnknownPlaceholder(String, usize),
+    /// The template is too large to be addressed with a [`Span`].
+    TooLarge,
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => f.write_str("template is empty"),
+            Self::Unterminated(at) => write!(f, "unterminated placeholder at byte {at}"),
+            Self::UnknownPlaceholder(name, at) => {
+                write!(
+                    f,
+                    "unknown placeholder `{{{{{{{name}}}}}}}` at byte {at}, expected a declared parameter or one of: {}",
+                    Variable::NAMES
+                )
+            }
+            Self::TooLarge => f.write_str("template is too large"),
+        }
+    }
+}
+
+impl Error for TemplateError {}
+
+/// Splits `template` into `segments`, offsetting every [`Span`] by `offset`.
+///
+/// Placeholders are encoded for the component they sit within, unless `escape` overrides it.
+/// Besides variables, placeholders may name any of `params`.
+///
+/// Nothing is pushed onto `segments` when the template is invalid.
+///
+/// # Errors
+/// See [`TemplateError`].
+pub fn compile_segments(
+    template: &str,
+    offset: usize,
+    escape: Option<UrlComponent>,
+    params: &[BangParamConfig],
+    segments: &mut Vec<Segment>,
+) -> Result<(), TemplateError> {
+    if template.is_empty() {
+        return Err(TemplateError::Empty);
+    }
+
+    let span = |start: usize, end: usize| {
+        Span::new(offset + start, offset + end).ok_or(TemplateError::TooLarge)
+    };
+
+    let first = segments.len();
+    let result = (|| {
+        let mut literal_start = 0;
+        let mut component = UrlComponent::Path;
+        while let Some(found) = template[literal_start..].find(PLACEHOLDER_OPEN) {
+            let open = literal_start + found;
+            let name_start = open + PLACEHOLDER_OPEN.len();
+            let Some(name_len) = template[name_start..].find(PLACEHOLDER_CLOSE) else {
+                return Err(TemplateError::Unterminated(open));
+            };
+            let name_end = name_start + name_len;
+            let name = &template[name_start..name_end];
+            let placeholder = match name {
+                "s" => Placeholder::Query,
+                _ if params.iter().any(|param| param.name == name) => {
+                    Placeholder::Param(span(name_start, name_end)?)
+                }
+                _ => Placeholder::Variable(
+                    Variable::parse(name)
+                        .ok_or_else(|| TemplateError::UnknownPlaceholder(name.to_string(), open))?,
+                ),
+            };
+
+            if open > literal_start {
+                component = component_after(&template[literal_start..open], component);
+                segments.push(Segment::Literal(span(literal_start, open)?));
+            }
+            let component = escape.unwrap_or(component);
+            segments.push(match placeholder {
+                Placeholder::Query => Segment::Query(component),
+                Placeholder::Variable(variable) => Segment::Variable(variable, component),
+                Placeholder::Param(name) => Segment::Param(name, component),
+            });
+            literal_start = name_end + PLACEHOLDER_CLOSE.len();
+        }
+        if literal_start < template.len() {
+            segments.push(Segment::Literal(span(literal_start, template.len())?));
+        }
+        Ok(())
+    })();
+
+    if result.is_err() {
+        segments.truncate(first);
+    }
+    result
+}
+
+/// What a placeholder within a template is replaced by.
+enum Placeholder {
+    Query,
+    Variable(Variable),
+    Param(Span),
+}
+
+/// The component of a url which follows `literal`, given that `literal` starts in `component`.
+fn component_after(literal: &str, component: UrlComponent) -> UrlComponent {
+    if component == UrlComponent::Fragment || literal.contains('#') {
+        UrlComponent::Fragment
+    } else if literal.contains('?') {
+        UrlComponent::Query
+    } else {
+        component
+    }
+}
+
+/// Concatenates `parts` into `out`, inserting `terms` at every query placeholder, one after the
+/// other, and filling in variables and parameters from `context`. Everything inserted is encoded
+/// for the placeholder's component.
+///
+/// Terms are transcoded into `charset` first, if given (see [`encode_charset_into`]).
+#[inline]
+pub fn render_parts<'a>(
+    parts: impl IntoIterator<Item = TemplatePart<'a>>,
+    terms: &[&str],
+    charset: Option<&'static Encoding>,
+    context: &RequestContext,
+    out: &mut String,
+) {
+    let encode = |value: &str, component, out: &mut String| match charset {
+        Some(charset) => encode_charset_into(value, charset, component, out),
+        None => encode_into(value, component, out),
+    };
+    for part in parts {
+        match part {
+            TemplatePart::Literal(literal) => out.push_str(literal),
+            TemplatePart::Query(component) => {
+                terms.iter().for_each(|term| encode(term, component, out));
+            }
+            TemplatePart::Variable(Variable::Date { days_ago }, _) => context
+                .write_date(days_ago, out)
+                .expect("Writing into a String should not fail"),
+            // The host is commonly used as the authority of a url, so its port is kept
+            TemplatePart::Variable(Variable::Host, _)
+                if context.host.is_some_and(is_valid_host) =>
+            {
+                out.push_str(context.host.unwrap_or_default());
+            }
+            TemplatePart::Variable(variable, component) => {
+                let (value, component) = match variable {
+                    Variable::Lang => (context.lang, component),
+                    Variable::SourceIdentifier => (context.source_identifier, component),
+                    // Escape anything which could break out of the authority
+                    Variable::Host => (context.host, UrlComponent::Query),
+                    Variable::Date { .. } => (None, component),
+                };
+                encode(value.unwrap_or_default(), component, out);
+            }
+            TemplatePart::Param(name, component) => {
+                encode(context.param(name).unwrap_or_default(), component, out);
+            }
+        }
+    }
+}
+
+/// Concatenates `parts` into `out` as plain text rather than as a url, such that `terms`,
+/// variables and parameters are inserted without being encoded. This is how macros are expanded (see
+/// [`macros`](crate::boom::macros)).
+pub fn render_parts_raw<'a>(
+    parts: impl IntoIterator<Item = TemplatePart<'a>>,
+    terms: &[&str],
+    context: &RequestContext,
+    out: &mut String,
+) {
+    for part in parts {
+        match part {
+            TemplatePart::Literal(literal) => out.push_str(literal),
+            TemplatePart::Query(_) => terms.iter().for_each(|term| out.push_str(term)),
+            TemplatePart::Variable(Variable::Date { days_ago }, _) => context
+                .write_date(days_ago, out)
+                .expect("Writing into a String should not fail"),
+            TemplatePart::Variable(variable, _) => out.push_str(
+                match variable {
+                    Variable::Lang => context.lang,
+                    Variable::SourceIdentifier => context.source_identifier,
+                    Variable::Host => context.host,
+                    Variable::Date { .. } => None,
+                }
+                .unwrap_or_default(),
+            ),
+            TemplatePart::Param(name, _) => out.push_str(context.param(name).unwrap_or_default()),
+        }
+    }
+}
+
+/// Whether `host` is a plain `host[:port]`, which can be inserted into a url as-is.
+///
+/// # Example
+/// ```
+/// use boom_core::template::is_valid_host;
+///
+/// assert!(is_valid_host("127.0.0.1:3000"));
+/// assert!(!is_valid_host(r#"evil";alert(1);""#));
+/// ```
+#[must_use]
+pub fn is_valid_host(host: &str) -> bool {
+    host.bytes()
+        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b':' | b'[' | b']'))
+}
+
+/// An owned, compiled url template, such as the `default_search_template`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    /// Compiles `source` into its segments.
+    ///
+    /// # Errors
+    /// See [`TemplateError`].
+    ///
+    /// # Example
+    /// ```
+    /// use boom_core::template::Template;
+    ///
+    /// let template = Template::compile("https://google.com/search?q={{{s}}}").unwrap();
+    /// assert_eq!(template.render("boom"), "https://google.com/search?q=boom");
+    ///
+    /// assert!(Template::compile("https://google.com/search?q={{{q}}}").is_err());
+    /// ```
+    pub fn compile<S: Into<String>>(source: S) -> Result<Self, TemplateError> {
+        Self::compile_with_params(source, &[])
+    }
+
+    /// Compiles `source` into its segments, allowing it to reference any of `params`.
+    ///
+    /// # Errors
+    /// See [`TemplateError`].
+    ///
+    /// # Example
+    /// ```
+    /// use boom_config::BangParamConfig;
+    /// use boom_core::template::Template;
+    ///
+    /// let params = [BangParamConfig {
+    ///     name: "project".to_string(),
+    ///     ..Default::default()
+    /// }];
+    /// assert!(Template::compile_with_params("https://jira.example.com/{{{project}}}", &params).is_ok());
+    /// assert!(Template::compile("https://jira.example.com/{{{project}}}").is_err());
+    /// ```
+    pub fn compile_with_params<S: Into<String>>(
+        source: S,
+        params: &[BangParamConfig],
+    ) -> Result<Self, TemplateError> {
+        Self::compile_escaped(source, None, params)
+    }
+
+    /// Compiles `source` into its segments, as [`compile_with_params`](Self::compile_with_params)
+    /// does, encoding every placeholder as part of `escape` (if given) rather than the component
+    /// it sits within.
+    ///
+    /// # Errors
+    /// See [`TemplateError`].
+    pub fn compile_escaped<S: Into<String>>(
+        source: S,
+        escape: Option<UrlComponent>,
+        params: &[BangParamConfig],
+    ) -> Result<Self, TemplateError> {
+        let source = source.into();
+        let mut segments = Vec::with_capacity(3);
+        compile_segments(&source, 0, escape, params, &mut segments)?;
+        segments.shrink_to_fit();
+        Ok(Self { source, segments })
+    }
+
+    /// The text the template was compiled from.
+    #[must_use]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn parts(&self) -> impl ExactSizeIterator<Item = TemplatePart<'_>> {
+        self.segments
+            .iter()
+            .map(|segment| TemplatePart::from_segment(*segment, &self.source))
+    }
+
+    /// Appends the template to `out`, with `query` encoded at every placeholder, and variables
+    /// filled in from `context`.
+    #[inline]
+    pub fn render_into(&self, query: &str, context: &RequestContext, out: &mut String) {
+        render_parts(self.parts(), &[query], None, context, out);
+    }
+
+    #[must_use]
+    pub fn render(&self, query: &str) -> String {
+        let mut out = String::with_capacity(self.source.len() + query.len());
+        self.render_into(query, &RequestContext::default(), &mut out);
+        out
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[allow(unused_imports)]
+    use super::{
+        BangParamConfig, RequestContext, Template, TemplateError, TemplatePart, UrlComponent,
+        Variable,
+    };
+
+    #[test]
+    fn test_compile_template() {
+        let template = Template::compile("https://a.com/{{{s}}}/search?q={{{s}}}&x=1").unwrap();
+        assert_eq!(
+            template.parts().collect::<Vec<_>>(),
+            vec![
+                TemplatePart::Literal("https://a.com/"),
+                TemplatePart::Query(UrlComponent::Path),
+                TemplatePart::Literal("/search?q="),
+                TemplatePart::Query(UrlComponent::Query),
+                TemplatePart::Literal("&x=1"),
+            ]
+        );
+        assert_eq!(template.render("q"), "https://a.com/q/search?q=q&x=1");
+
+        let shortened = Template::compile("https://b.com").unwrap();
+        assert_eq!(shortened.render("ignored"), "https://b.com");
+    }
+
+    #[test]
+    fn test_template_components() {
+        let template = Template::compile("https://a.com/{{{s}}}?q={{{s}}}#/{{{s}}}").unwrap();
+        assert_eq!(
+            template.render("a/b?c"),
+            "https://a.com/a/b%3Fc?q=a%2Fb%3Fc#/a/b?c"
+        );
+
+        let mut segments = Vec::new();
+        super::compile_segments(
+            "https://a.com/{{{s}}}",
+            0,
+            Some(UrlComponent::Query),
+            &[],
+            &mut segments,
+        )
+        .unwrap();
+        assert_eq!(segments[1], super::Segment::Query(UrlComponent::Query));
+    }
+
+    #[test]
+    fn test_template_variables() {
+        let template = Template::compile(
+            "https://a.com/{{{lang}}}/?q={{{s}}}&from={{{now-7d}}}&to={{{date}}}&si={{{si}}}&h={{{host}}}",
+        )
+        .unwrap();
+        assert_eq!(
+            template.parts().nth(1),
+            Some(TemplatePart::Variable(Variable::Lang, UrlComponent::Path))
+        );
+
+        let context = RequestContext {
+            lang: Some("en-GB"),
+            source_identifier: Some("my phone"),
+            host: Some("localhost:3000"),
+            // 2024-03-05T12:00:00Z
+            now: UNIX_EPOCH + Duration::from_secs(1_709_640_000),
+            params: &[],
+        };
+        let mut url = String::new();
+        template.render_into("a b", &context, &mut url);
+        assert_eq!(
+            url,
+            "https://a.com/en-GB/?q=a%20b&from=2024-02-27&to=2024-03-05&si=my%20phone&h=localhost:3000"
+        );
+
+        assert_eq!(
+            Template::compile("https://a.com/?q={{{now-7w}}}"),
+            Err(TemplateError::UnknownPlaceholder("now-7w".to_string(), 17))
+        );
+    }
+
+    #[test]
+    fn test_template_params() {
+        let params = [
+            BangParamConfig {
+                name: "project".to_string(),
+                ..Default::default()
+            },
+            BangParamConfig {
+                name: "lang".to_string(),
+                ..Default::default()
+            },
+        ];
+        let template = Template::compile_with_params(
+            "https://a.com/{{{project}}}/?q={{{s}}}&hl={{{lang}}}",
+            &params,
+        )
+        .unwrap();
+        assert_eq!(
+            template.parts().nth(1),
+            Some(TemplatePart::Param("project", UrlComponent::Path))
+        );
+
+        let context = RequestContext {
+            lang: Some("en-GB"),
+            params: &[("project", "a/b c"), ("lang", "fr")],
+            ..RequestContext::default()
+        };
+        let mut url = String::new();
+        template.render_into("q", &context, &mut url);
+        assert_eq!(url, "https://a.com/a/b%20c/?q=q&hl=fr");
+    }
+
+    #[test]
+    fn test_compile_template_invalid() {
+        assert_eq!(Template::compile(""), Err(TemplateError::Empty));
+        assert_eq!(
+            Template::compile("https://a.com/?q={{{s"),
+            Err(TemplateError::Unterminated(17))
+        );
+        assert_eq!(
+            Template::compile("https://a.com/?q={{{query}}}"),
+            Err(TemplateError::UnknownPlaceholder("query".to_string(), 17))
+        );
+    }
+}
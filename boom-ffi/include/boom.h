@@ -0,0 +1,81 @@
+#ifndef BOOM_H
+#define BOOM_H
+
+/* Generated by cbindgen from boom-ffi/src/lib.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// A loaded config, along with its bangs.
+typedef struct BoomRegistry BoomRegistry;
+
+// A list of triggers, without their leading `!`.
+typedef struct BoomTriggers {
+  char **triggers;
+  size_t len;
+} BoomTriggers;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Reads the config at `config_path`, and loads its bangs. Bangs from sources with a url are
+// requested, as they are when serving.
+//
+// Returns null if the config does not exist or cannot be read, or if a required source cannot be
+// fetched or read.
+//
+// # Safety
+// `config_path` must be null, or point to a nul-terminated string.
+struct BoomRegistry *boom_registry_new(const char *config_path);
+
+// Frees a registry created by [`boom_registry_new`]. The bangs remain loaded.
+//
+// # Safety
+// `registry` must be null, or have been returned by [`boom_registry_new`] and not yet freed.
+void boom_registry_free(struct BoomRegistry *registry);
+
+// Resolves `query` into `buf`, which holds `len` bytes, as a nul-terminated url.
+//
+// Returns the length of the url, excluding the terminator. Should it be `len` or more, the url
+// did not fit and nothing is written, so the query can be resolved again into a larger buffer.
+// Returns `-1` if any argument is null, or if `query` is blank or not UTF-8.
+//
+// # Safety
+// `registry` must be null or a live registry, `query` must be null or point to a nul-terminated
+// string, and `buf` must be null or valid for writes of `len` bytes.
+ptrdiff_t boom_resolve(const struct BoomRegistry *registry,
+                       const char *query,
+                       char *buf,
+                       size_t len);
+
+// Lists the trigger of every loaded bang which has not expired.
+//
+// # Safety
+// `registry` must be null or a live registry, for which nothing is listed.
+struct BoomTriggers boom_list(const struct BoomRegistry *registry);
+
+// Lists the triggers of up to `limit` bangs whose trigger or short name starts with `prefix`,
+// most relevant first (see [`complete`]).
+//
+// # Safety
+// `registry` must be null or a live registry, and `prefix` must be null or point to a
+// nul-terminated string. Nothing is listed for either being null.
+struct BoomTriggers boom_complete(const struct BoomRegistry *registry,
+                                  const char *prefix,
+                                  size_t limit);
+
+// Frees triggers listed by [`boom_list`] or [`boom_complete`].
+//
+// # Safety
+// `triggers` must have been returned by [`boom_list`] or [`boom_complete`], and not yet freed.
+void boom_triggers_free(struct BoomTriggers triggers);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BOOM_H */
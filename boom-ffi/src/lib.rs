@@ -0,0 +1,247 @@
+//! # About
+//! `boom-ffi` exposes the resolver of [`boom_core`] through a C ABI, so that `boom` can be embedded
+//! within programs written in other languages. The header, `include/boom.h`, is generated by
+//! cbindgen, and checked against this file by `tests/header.rs`.
+//!
+//! Strings are UTF-8 and nul-terminated. Anything returned by a `boom_*` function is owned by the
+//! caller, and released through the matching `boom_*_free`. A `boom_*` function which panics
+//! returns as it would for invalid arguments, rather than unwinding into the caller.
+//!
+//! Bangs are held globally by [`boom_core`], so every registry shares the bangs of whichever was
+//! created last, whilst resolving queries according to its own config.
+
+use std::{
+    ffi::{CStr, CString, c_char},
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::PathBuf,
+    ptr,
+    sync::{Arc, RwLock},
+};
+
+use boom_config::{Config, ConfigSource, unix_timestamp};
+use boom_core::{
+    boom::{
+        resolver::resolve_into, trigger_index::complete,
+        update_bangs_from_config::try_update_bangs_from_config,
+    },
+    cache::get_redirects,
+};
+
+/// A loaded config, along with its bangs.
+pub struct BoomRegistry {
+    config: Config,
+}
+
+/// A list of triggers, without their leading `!`.
+#[repr(C)]
+pub struct BoomTriggers {
+    pub triggers: *mut *mut c_char,
+    pub len: usize,
+}
+
+impl BoomTriggers {
+    const EMPTY: Self = Self {
+        triggers: ptr::null_mut(),
+        len: 0,
+    };
+
+    fn new<'a>(triggers: impl IntoIterator<Item = &'a str>) -> Self {
+        let triggers: Box<[*mut c_char]> = triggers
+            .into_iter()
+            .filter_map(|trigger| CString::new(trigger).ok())
+            .map(CString::into_raw)
+            .collect();
+        let len = triggers.len();
+        Self {
+            triggers: Box::into_raw(triggers).cast(),
+            len,
+        }
+    }
+}
+
+/// Runs `f`, returning `fallback` should it panic, as unwinding into C is undefined behaviour.
+fn or_on_panic<T>(fallback: T, f: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(fallback)
+}
+
+/// Borrows `s` as a `&str`, unless it is null or not UTF-8.
+///
+/// # Safety
+/// `s` must be null, or point to a nul-terminated string which outlives `'a`.
+unsafe fn to_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    unsafe { CStr::from_ptr(s) }.to_str().ok()
+}
+
+/// Reads the config at `config_path`, and loads its bangs. Bangs from sources with a url are
+/// requested, as they are when serving.
+///
+/// Returns null if the config does not exist or cannot be read, or if a required source cannot be
+/// fetched or read.
+///
+/// # Safety
+/// `config_path` must be null, or point to a nul-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn boom_registry_new(config_path: *const c_char) -> *mut BoomRegistry {
+    or_on_panic(ptr::null_mut(), || {
+        let Some(config_path) = (unsafe { to_str(config_path) }) else {
+            return ptr::null_mut();
+        };
+        let config_path = PathBuf::from(config_path);
+        // Reading a missing config would write the default one in its place
+        if !config_path.is_file() {
+            return ptr::null_mut();
+        }
+        let Ok(mut builder) = config_path.read_into_builder() else {
+            return ptr::null_mut();
+        };
+        let config = builder.set_config_source(&config_path).to_owned().build();
+
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        else {
+            return ptr::null_mut();
+        };
+        let loaded = runtime.block_on(try_update_bangs_from_config(
+            Arc::new(config.bangs.clone()),
+            config.config_source.clone(),
+            Arc::new(RwLock::new(vec![])),
+            false,
+            false,
+        ));
+        if loaded.is_err() {
+            return ptr::null_mut();
+        }
+
+        Box::into_raw(Box::new(BoomRegistry { config }))
+    })
+}
+
+/// Frees a registry created by [`boom_registry_new`]. The bangs remain loaded.
+///
+/// # Safety
+/// `registry` must be null, or have been returned by [`boom_registry_new`] and not yet freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn boom_registry_free(registry: *mut BoomRegistry) {
+    or_on_panic((), || {
+        if !registry.is_null() {
+            drop(unsafe { Box::from_raw(registry) });
+        }
+    });
+}
+
+/// Resolves `query` into `buf`, which holds `len` bytes, as a nul-terminated url.
+///
+/// Returns the length of the url, excluding the terminator. Should it be `len` or more, the url
+/// did not fit and nothing is written, so the query can be resolved again into a larger buffer.
+/// Returns `-1` if any argument is null, or if `query` is blank or not UTF-8.
+///
+/// # Safety
+/// `registry` must be null or a live registry, `query` must be null or point to a nul-terminated
+/// string, and `buf` must be null or valid for writes of `len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn boom_resolve(
+    registry: *const BoomRegistry,
+    query: *const c_char,
+    buf: *mut c_char,
+    len: usize,
+) -> isize {
+    or_on_panic(-1, || {
+        let (Some(registry), Some(query)) =
+            (unsafe { registry.as_ref() }, unsafe { to_str(query) })
+        else {
+            return -1;
+        };
+        if buf.is_null() || query.trim().is_empty() {
+            return -1;
+        }
+
+        let mut url = String::new();
+        resolve_into(query, &registry.config, &mut url);
+        if url.len() < len {
+            unsafe {
+                ptr::copy_nonoverlapping(url.as_ptr(), buf.cast(), url.len());
+                *buf.add(url.len()) = 0;
+            }
+        }
+        isize::try_from(url.len()).unwrap_or(isize::MAX)
+    })
+}
+
+/// Lists the trigger of every loaded bang which has not expired.
+///
+/// # Safety
+/// `registry` must be null or a live registry, for which nothing is listed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn boom_list(registry: *const BoomRegistry) -> BoomTriggers {
+    or_on_panic(BoomTriggers::EMPTY, || {
+        if registry.is_null() {
+            return BoomTriggers::EMPTY;
+        }
+        let Ok(redirects) = get_redirects() else {
+            return BoomTriggers::EMPTY;
+        };
+
+        let now = unix_timestamp();
+        BoomTriggers::new(
+            redirects
+                .iter()
+                .filter(|bang| !bang.is_expired(now))
+                .map(|bang| bang.trigger()),
+        )
+    })
+}
+
+/// Lists the triggers of up to `limit` bangs whose trigger or short name starts with `prefix`,
+/// most relevant first (see [`complete`]).
+///
+/// # Safety
+/// `registry` must be null or a live registry, and `prefix` must be null or point to a
+/// nul-terminated string. Nothing is listed for either being null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn boom_complete(
+    registry: *const BoomRegistry,
+    prefix: *const c_char,
+    limit: usize,
+) -> BoomTriggers {
+    or_on_panic(BoomTriggers::EMPTY, || {
+        let Some(prefix) = (unsafe { to_str(prefix) }) else {
+            return BoomTriggers::EMPTY;
+        };
+        if registry.is_null() {
+            return BoomTriggers::EMPTY;
+        }
+
+        let completions = complete(prefix, limit);
+        BoomTriggers::new(
+            completions
+                .iter()
+                .map(|completion| completion.trigger.as_str()),
+        )
+    })
+}
+
+/// Frees triggers listed by [`boom_list`] or [`boom_complete`].
+///
+/// # Safety
+/// `triggers` must have been returned by [`boom_list`] or [`boom_complete`], and not yet freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn boom_triggers_free(triggers: BoomTriggers) {
+    or_on_panic((), || {
+        if triggers.triggers.is_null() {
+            return;
+        }
+        let triggers: Box<[*mut c_char]> = unsafe {
+            Box::from_raw(ptr::slice_from_raw_parts_mut(
+                triggers.triggers,
+                triggers.len,
+            ))
+        };
+        for trigger in triggers {
+            drop(unsafe { CString::from_raw(trigger) });
+        }
+    });
+}
@@ -0,0 +1,130 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Instant,
+};
+
+const CONFIG: &str = r#"
+[bangs]
+default_search_template = "https://google.com/search?q={{{s}}}"
+
+[[bangs.source]]
+required = false
+filepath = "/nonexistent/bangs.json"
+
+[bangs.custom.jira]
+short_name = "Jira"
+template = "https://jira.example.com/browse/{{{s}}}"
+
+[bangs.custom.jiradocs]
+short_name = "Jira Docs"
+template = "https://docs.example.com/jira?q={{{s}}}"
+"#;
+
+const UNREADABLE_CONFIG: &str = r#"
+[[bangs.source]]
+required = true
+filepath = "/nonexistent/bangs.json"
+
+[bangs.custom.jira]
+short_name = "Jira"
+template = "https://jira.example.com/browse/{{{s}}}"
+"#;
+
+/// The libraries the static library depends on, as given by
+/// `cargo rustc -p boom-ffi --lib --crate-type staticlib -- --print native-static-libs`.
+#[cfg(target_os = "linux")]
+const NATIVE_LIBS: &[&str] = &[
+    "-lssl",
+    "-lcrypto",
+    "-lgcc_s",
+    "-lutil",
+    "-lrt",
+    "-lpthread",
+    "-lm",
+    "-ldl",
+    "-lc",
+];
+#[cfg(target_os = "macos")]
+const NATIVE_LIBS: &[&str] = &[
+    "-framework",
+    "Security",
+    "-framework",
+    "CoreFoundation",
+    "-framework",
+    "SystemConfiguration",
+    "-liconv",
+    "-lSystem",
+    "-lc",
+    "-lm",
+];
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+const NATIVE_LIBS: &[&str] = &[];
+
+/// The directory the static library is built into, alongside the `deps` directory of this test.
+fn target_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    exe.parent().and_then(Path::parent).unwrap().to_path_buf()
+}
+
+/// Builds the static library, which `cargo test` only builds as an rlib.
+fn build_static_lib(target_dir: &Path) {
+    let mut cargo = Command::new(env!("CARGO"));
+    cargo
+        .args(["build", "-p", "boom-ffi", "--lib"])
+        .env("CARGO_TARGET_DIR", target_dir.parent().unwrap());
+    if target_dir.ends_with("release") {
+        cargo.arg("--release");
+    }
+    assert!(cargo.status().unwrap().success(), "boom-ffi should build");
+}
+
+/// Compiles `tests/c/<name>.c` against the static library and the generated header.
+fn compile(name: &str) -> PathBuf {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = target_dir();
+    build_static_lib(&target_dir);
+    let out = target_dir.join(format!("c-{name}"));
+
+    let status = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .arg(crate_dir.join(format!("tests/c/{name}.c")))
+        .arg("-I")
+        .arg(crate_dir.join("include"))
+        .arg("-o")
+        .arg(&out)
+        .arg(target_dir.join("libboom_ffi.a"))
+        .args(NATIVE_LIBS)
+        .status()
+        .expect("A C compiler should be installed");
+    assert!(status.success(), "{name}.c should compile");
+    out
+}
+
+#[test]
+#[cfg_attr(not(any(target_os = "linux", target_os = "macos")), ignore)]
+fn test_c_api() {
+    let dir = env::temp_dir().join(format!("boom-ffi-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("config.toml");
+    std::fs::write(&config, CONFIG).unwrap();
+    let unreadable = dir.join("unreadable.toml");
+    std::fs::write(&unreadable, UNREADABLE_CONFIG).unwrap();
+
+    let start = Instant::now();
+    let test = compile("test_boom");
+    eprintln!("Building the C tests took {:?}", start.elapsed());
+
+    let output = Command::new(test)
+        .args([&config, &unreadable, &dir.join("missing.toml")])
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
@@ -0,0 +1,95 @@
+/* Resolves, lists and completes the bangs of the config given as the first argument, which is
+ * expected to define the custom bangs `jira` and `jiradocs` (see `tests/c.rs`). The second is a
+ * config requiring a source which does not exist, and the third a path without any config. */
+
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "boom.h"
+
+static int contains(struct BoomTriggers triggers, const char *trigger) {
+  for (size_t i = 0; i < triggers.len; i++) {
+    if (strcmp(triggers.triggers[i], trigger) == 0) {
+      return 1;
+    }
+  }
+  return 0;
+}
+
+static void test_resolve(const struct BoomRegistry *registry) {
+  const char *expected = "https://jira.example.com/browse/PROJ-1";
+  char url[64];
+
+  ptrdiff_t len = boom_resolve(registry, "PROJ-1 !jira", url, sizeof url);
+  assert(len == (ptrdiff_t)strlen(expected));
+  assert(strcmp(url, expected) == 0);
+
+  /* Too small a buffer is left untouched, and the length of the url is still returned */
+  char small[8] = "unused";
+  assert(boom_resolve(registry, "PROJ-1 !jira", small, sizeof small) == len);
+  assert(strcmp(small, "unused") == 0);
+
+  /* Without a bang, the default search template is used */
+  assert(boom_resolve(registry, "rust", url, sizeof url) > 0);
+  assert(strcmp(url, "https://google.com/search?q=rust") == 0);
+
+  assert(boom_resolve(registry, "   ", url, sizeof url) == -1);
+  assert(boom_resolve(registry, NULL, url, sizeof url) == -1);
+  assert(boom_resolve(NULL, "rust", url, sizeof url) == -1);
+}
+
+static void test_list(const struct BoomRegistry *registry) {
+  struct BoomTriggers triggers = boom_list(registry);
+  assert(triggers.len == 2);
+  assert(contains(triggers, "jira"));
+  assert(contains(triggers, "jiradocs"));
+  boom_triggers_free(triggers);
+}
+
+static void test_complete(const struct BoomRegistry *registry) {
+  struct BoomTriggers triggers = boom_complete(registry, "!jira", 10);
+  assert(triggers.len == 2);
+  /* An exact match comes first */
+  assert(strcmp(triggers.triggers[0], "jira") == 0);
+  boom_triggers_free(triggers);
+
+  triggers = boom_complete(registry, "!jira", 1);
+  assert(triggers.len == 1);
+  boom_triggers_free(triggers);
+
+  triggers = boom_complete(registry, "!nothing", 10);
+  assert(triggers.len == 0);
+  boom_triggers_free(triggers);
+
+  triggers = boom_complete(registry, NULL, 10);
+  assert(triggers.triggers == NULL && triggers.len == 0);
+  boom_triggers_free(triggers);
+}
+
+static void test_registry_errors(const char *unreadable, const char *missing) {
+  assert(boom_registry_new(NULL) == NULL);
+  assert(boom_registry_new(unreadable) == NULL);
+
+  /* No default config is written in place of a missing one */
+  assert(boom_registry_new(missing) == NULL);
+  assert(fopen(missing, "r") == NULL);
+}
+
+int main(int argc, char **argv) {
+  assert(argc == 4);
+  test_registry_errors(argv[2], argv[3]);
+
+  struct BoomRegistry *registry = boom_registry_new(argv[1]);
+  assert(registry != NULL);
+
+  test_resolve(registry);
+  test_list(registry);
+  test_complete(registry);
+
+  boom_registry_free(registry);
+  boom_registry_free(NULL);
+
+  puts("ok");
+  return 0;
+}
@@ -0,0 +1,28 @@
+use std::{env, path::Path, time::Instant};
+
+/// The committed header should match the one generated from `src/lib.rs`. Running this test with
+/// `BOOM_UPDATE_HEADER=1` rewrites it instead.
+#[test]
+fn test_header_up_to_date() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let path = crate_dir.join("include/boom.h");
+
+    let start = Instant::now();
+    let mut generated = vec![];
+    cbindgen::Builder::new()
+        .with_crate(crate_dir)
+        .with_config(cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap())
+        .generate()
+        .expect("Should be able to generate the C header")
+        .write(&mut generated);
+    eprintln!("Generating the header took {:?}", start.elapsed());
+
+    if env::var_os("BOOM_UPDATE_HEADER").is_some() {
+        std::fs::write(&path, &generated).unwrap();
+        return;
+    }
+    assert!(
+        std::fs::read(&path).unwrap() == generated,
+        "include/boom.h is stale, so run `BOOM_UPDATE_HEADER=1 cargo test -p boom-ffi --test header`"
+    );
+}
@@ -5,6 +5,21 @@
  * @import { CachedFaviconRequest, CachedFaviconResponse } from "./index.d.js"
  */
 
+import { attachBangCompletion } from "../components/complete.js";
+
+/**
+ * Where a bang was defined, mirroring `boom_core::BangOrigin`
+ * @typedef {{kind: "source", filepath: string, remote: string | null}
+ *   | {kind: "config", path: string}
+ *   | {kind: "api"}
+ *   | {kind: "unknown"}} BangOrigin
+ */
+
+/**
+ * A definition which lost out to another bang with the same trigger
+ * @typedef {{s: string, u: string, origin: BangOrigin}} ShadowedBang
+ */
+
 /**
  * Bang wrapper class
  * @class
@@ -14,8 +29,10 @@ class Bang {
    * @param {string} s - The short
    * @param {string} t - The trigger
    * @param {string} u - The url template
+   * @param {BangOrigin} origin - Where the bang was defined
+   * @param {ShadowedBang[]} shadowed - Definitions this bang took precedence over
    */
-  constructor(s, t, u) {
+  constructor(s, t, u, origin, shadowed) {
     /** @type {string} */
     this.short = s;
 
@@ -24,6 +41,12 @@ class Bang {
 
     /** @type {string} */
     this.url_template = u;
+
+    /** @type {BangOrigin} */
+    this.origin = origin;
+
+    /** @type {ShadowedBang[]} */
+    this.shadowed = shadowed;
   }
 
   /**
@@ -38,7 +61,7 @@ class Bang {
 
 if ("serviceWorker" in navigator) {
   navigator.serviceWorker
-    .register("/sw.js")
+    .register("sw.js")
     .then((registration) => {
       console.log("Service Worker registered.");
 
@@ -98,8 +121,9 @@ let raw = document.getElementById("bang-data")?.textContent ?? "{}";
  * @type {Bang[]}
  */
 const bangs = JSON.parse(raw).map(
-  (/** @type {{s: string, t: string, u: string }} */ b) =>
-    new Bang(b.s, b.t, b.u),
+  (
+    /** @type {{s: string, t: string, u: string, origin: BangOrigin, shadowed?: ShadowedBang[] }} */ b,
+  ) => new Bang(b.s, b.t, b.u, b.origin, b.shadowed ?? []),
 );
 
 const bang_len = bangs.length;
@@ -144,6 +168,7 @@ window.onload = () => {
   );
 
   _urlQuery != null && input && (input.value = _urlQuery);
+  input && attachBangCompletion(input, { requireBang: false });
 
   input?.addEventListener("input", (e) => {
     const target = /** @type {HTMLInputElement} */ (e.currentTarget);
@@ -293,6 +318,9 @@ function loadBangs(filter) {
  *    <!-- Bang URL Template -->
  *    </a>
  *  </td>
+ *  <td title="<shadowed definitions>">
+ *  <!-- Bang Origin -->
+ *  </td>
  * </tr>
  *
  * @param {Bang} bang
@@ -305,7 +333,7 @@ function buildBangElement(bang) {
   imageContainer.className = "image-container";
 
   const faviconCell = document.createElement("img");
-  faviconCell.src = "/assets/bangs/fallback-icon.svg";
+  faviconCell.src = "assets/bangs/fallback-icon.svg";
   faviconCell.dataset["fallback"] = "1";
   faviconCell.decoding = "async";
 
@@ -334,7 +362,7 @@ function buildBangElement(bang) {
     url = new URL(bang.url_template);
     link.href = url.origin;
   } catch (_) {
-    url = new URL(location.href + "/assets/bangs/fallback-icon.svg");
+    url = new URL("assets/bangs/fallback-icon.svg", document.baseURI);
     link.href = "#";
   }
 
@@ -342,14 +370,41 @@ function buildBangElement(bang) {
   link.target = "_blank";
   templateCell.appendChild(link);
 
+  const originCell = document.createElement("td");
+  originCell.textContent = describeOrigin(bang.origin);
+  if (bang.shadowed.length !== 0) {
+    originCell.textContent += ` (shadows ${bang.shadowed.length})`;
+    originCell.title = bang.shadowed
+      .map((s) => `${s.s}: ${s.u} from ${describeOrigin(s.origin)}`)
+      .join("\n");
+  }
+
   row.appendChild(shortCell);
   row.appendChild(triggerCell);
   row.appendChild(templateCell);
+  row.appendChild(originCell);
 
   setFavicon(shortCell, url);
   return row;
 }
 
+/**
+ * @param {BangOrigin} origin
+ * @returns {string}
+ */
+function describeOrigin(origin) {
+  switch (origin.kind) {
+    case "source":
+      return origin.remote ?? origin.filepath;
+    case "config":
+      return `config (${origin.path})`;
+    case "api":
+      return "api";
+    default:
+      return "unknown";
+  }
+}
+
 /**
  * @param {string} url
  * @returns {Promise<boolean>}
@@ -484,7 +539,7 @@ function toggleAddBang() {
       },
       {},
     );
-    fetch("/api/add-bang", {
+    fetch("api/add-bang", {
       body: JSON.stringify(parsed),
       method: "POST",
       headers: {
@@ -118,10 +118,10 @@ selfTyped.addEventListener("install", (event) => {
   event.waitUntil(
     (async () => {
       addResourcesToCache([
-        "/assets/bangs/index.js",
-        "/assets/bangs/index.html",
-        "/assets/bangs/style.css",
-        "/assets/bangs/fallback-icon.svg",
+        "assets/bangs/index.js",
+        "assets/bangs/index.html",
+        "assets/bangs/style.css",
+        "assets/bangs/fallback-icon.svg",
       ]).then((_) => console.log("Added fallbacks to cache"));
       selfTyped.skipWaiting();
     })(),
@@ -151,7 +151,7 @@ selfTyped.addEventListener("fetch", (event) => {
         request: event.request,
         preloadResponsePromise: event.preloadResponse,
         fallbackUrl: event.request.url.endsWith(".ico")
-          ? "/assets/bangs/fallback-icon.svg"
+          ? "assets/bangs/fallback-icon.svg"
           : undefined,
       });
     })(),
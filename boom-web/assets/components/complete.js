@@ -0,0 +1,67 @@
+// @ts-check
+/// <reference lib="dom" />
+
+/**
+ * A completion returned by `/api/bangs/complete`
+ * @typedef {{s: string, t: string, u: string}} Completion
+ */
+
+/**
+ * Autocompletes the bang being typed into `input` using `/api/bangs/complete`.
+ * Completions are offered through a <datalist> attached to the input.
+ *
+ * @param {HTMLInputElement} input
+ * @param {{limit?: number, requireBang?: boolean}} options - When `requireBang` is set, only the
+ * last word of the input is completed, and only once it starts with `!`
+ */
+export function attachBangCompletion(
+  input,
+  { limit = 8, requireBang = true } = {},
+) {
+  const list = document.createElement("datalist");
+  list.id = `${input.name}-bang-completions`;
+  input.after(list);
+  input.setAttribute("list", list.id);
+  input.autocomplete = "off";
+
+  /** @type {AbortController | null} */
+  let pending = null;
+
+  input.addEventListener("input", () => {
+    pending?.abort();
+
+    const words = input.value.split(" ");
+    const partial = requireBang ? words[words.length - 1] : input.value.trim();
+    if (
+      (requireBang && !partial.startsWith("!")) ||
+      partial.replace(/^!/, "").length === 0
+    ) {
+      list.replaceChildren();
+      return;
+    }
+
+    const head = requireBang
+      ? input.value.slice(0, input.value.length - partial.length)
+      : "";
+
+    pending = new AbortController();
+    fetch(
+      `api/bangs/complete?q=${encodeURIComponent(partial)}&limit=${limit}`,
+      { signal: pending.signal },
+    )
+      .then((r) => r.json())
+      .then((/** @type {Completion[]} */ completions) => {
+        list.replaceChildren(
+          ...completions.map((completion) => {
+            const option = document.createElement("option");
+            option.value = requireBang
+              ? `${head}!${completion.t}`
+              : completion.t;
+            option.label = completion.s;
+            return option;
+          }),
+        );
+      })
+      .catch(() => {});
+  });
+}
@@ -0,0 +1,11 @@
+// @ts-check
+/// <reference lib="dom" />
+
+import { attachBangCompletion } from "./components/complete.js";
+
+window.addEventListener("DOMContentLoaded", () => {
+  const searchInput = /** @type {HTMLInputElement | null} */ (
+    document.querySelector("form[role='search'] input[name='q']")
+  );
+  searchInput && attachBangCompletion(searchInput);
+});
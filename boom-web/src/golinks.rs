@@ -0,0 +1,120 @@
+//! Go-links style resolution of paths, such as `/jira/PROJ-1` for `!jira PROJ-1`, or `/docs` for
+//! `!docs`.
+//!
+//! Built-in routes always take precedence, so bangs named after one of [`RESERVED_ROUTES`] can only
+//! be reached through their path beneath a [`path_prefix`](boom_config::ServerConfig::path_prefix),
+//! or through the [`pseudo_host`](boom_config::ServerConfig::pseudo_host) (see [`proxy_script`]).
+
+use boom_config::Config;
+
+/// The first segments of the paths served by `boom` itself, whether or not they are enabled.
+pub const RESERVED_ROUTES: &[&str] = &[
+    "api",
+    "assets",
+    "bangs",
+    "history",
+    "opensearch.xml",
+    "proxy.pac",
+    "suggest",
+    "sw.js",
+];
+
+/// The query which the (percent-decoded) `path` resolves like, unless it is reserved or lies
+/// outside of `prefix`.
+///
+/// # Example
+/// ```
+/// use boom_web::golinks::path_query;
+///
+/// assert_eq!(path_query("/jira/PROJ-1", None).as_deref(), Some("!jira PROJ-1"));
+/// assert_eq!(path_query("/go/docs", Some("go")).as_deref(), Some("!docs"));
+/// assert_eq!(path_query("/docs", Some("go")), None);
+/// assert_eq!(path_query("/bangs", None), None);
+/// ```
+#[must_use]
+pub fn path_query(path: &str, prefix: Option<&str>) -> Option<String> {
+    let path = path.trim_start_matches('/');
+    let path = match prefix {
+        Some(prefix) => path
+            .strip_prefix(prefix.trim_matches('/'))?
+            .strip_prefix('/')?,
+        None => path,
+    };
+
+    let name = path.split('/').next()?;
+    if prefix.is_none() && RESERVED_ROUTES.contains(&name) {
+        return None;
+    }
+    link_query(path)
+}
+
+/// The query which the (percent-decoded) `path` resolves like, whether or not it is reserved.
+///
+/// # Example
+/// ```
+/// use boom_web::golinks::link_query;
+///
+/// assert_eq!(link_query("/bangs/rust").as_deref(), Some("!bangs rust"));
+/// assert_eq!(link_query("/"), None);
+/// ```
+#[must_use]
+pub fn link_query(path: &str) -> Option<String> {
+    let path = path.trim_start_matches('/');
+    let (name, rest) = path.split_once('/').unwrap_or((path, ""));
+    if name.is_empty() {
+        return None;
+    }
+    let rest = rest.trim_end_matches('/');
+    Some(if rest.is_empty() {
+        format!("!{name}")
+    } else {
+        format!("!{name} {rest}")
+    })
+}
+
+/// Whether `host` (the `Host` header of a request, which may include a port) is `pseudo_host`.
+#[must_use]
+pub fn is_pseudo_host(host: &str, pseudo_host: &str) -> bool {
+    let host = host.rsplit_once(':').map_or(host, |(host, _)| host);
+    host.trim_end_matches('.').eq_ignore_ascii_case(pseudo_host)
+}
+
+/// A proxy auto-config (PAC) script, which sends every request for `pseudo_host` to `proxy` (the
+/// address of `boom`), and everything else directly.
+///
+/// # Example
+/// ```
+/// use boom_web::golinks::proxy_script;
+///
+/// let script = proxy_script("go", "127.0.0.1:3000");
+/// assert!(script.contains(r#"return "PROXY 127.0.0.1:3000";"#));
+/// ```
+#[must_use]
+pub fn proxy_script(pseudo_host: &str, proxy: &str) -> String {
+    // Both are written as string literals, so that neither can break out of them
+    let literal = |s: &str| serde_json::to_string(s).expect("Strings should serialize");
+    format!(
+        r#"function FindProxyForURL(url, host) {{
+  if (host.toLowerCase() === {pseudo_host}) {{
+    return {proxy};
+  }}
+  return "DIRECT";
+}}
+"#,
+        pseudo_host = literal(&pseudo_host.to_ascii_lowercase()),
+        proxy = literal(&format!("PROXY {proxy}")),
+    )
+}
+
+/// The custom bangs of `config` which cannot be reached through their path, as a built-in route
+/// takes precedence over them.
+pub fn path_conflicts(config: &Config) -> impl Iterator<Item = &str> {
+    config
+        .bangs
+        .custom
+        .keys()
+        .filter(|trigger| {
+            config.server.path_prefix.is_none() && RESERVED_ROUTES.contains(&trigger.as_str())
+        })
+        .map(String::as_str)
+}
@@ -23,6 +23,8 @@ const SCHEMA_STR: &str = "message schema {
         REQUIRED INT64 timestamp;
     }";
 
+/// Writes the search history into `hist_file.parquet`, alongside the default config, unless it is
+/// empty.
 pub async fn save_history() {
     info!("Updating search history save");
 
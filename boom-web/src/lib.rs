@@ -3,37 +3,49 @@
 //! Whilst [`boom_core`] provides the tools to crunch data, `boom-web` provides the user-facing
 //! functions to display awesome web pages.
 
-#[cfg(feature = "history")]
-use std::time::Duration;
 use std::{
+    convert::Infallible,
     net::{IpAddr, SocketAddr},
     sync::{Arc, RwLock},
+    time::Duration,
 };
 
 use axum::{Router, routing::get};
 use axum::{
-    extract::Path,
-    http::StatusCode,
+    extract::{FromRequestParts, NestedPath, Path},
+    http::{Extensions, StatusCode, request::Parts},
     response::{IntoResponse, Response},
 };
 use axum_template::engine::Engine;
-use boom_config::{Config, ConfigBuilder, get_default_config_path};
-use boom_core::boom::update_bangs_from_config::update_bangs_from_config;
+use boom_config::{Config, ConfigBuilder, get_default_config_path, unix_timestamp};
+use boom_core::{
+    boom::update_bangs_from_config::update_bangs_from_config, cache::remove_expired_bangs,
+};
 use handlebars::{Context, Handlebars, Helper, HelperResult, Output, RenderContext};
 use notify::{RecommendedWatcher, Watcher};
-use routes::{bangs::list_bangs, index::redirector, opensearch::opensearch};
+use routes::{
+    bangs::list_bangs,
+    index::{complete_params, go_link, pseudo_host, redirector},
+    opensearch::opensearch,
+    pac::proxy_pac,
+};
 use rust_embed::RustEmbed;
-use tokio::net::TcpListener;
+use serde::Serialize;
+use tokio::{net::TcpListener, task::JoinHandle};
 use tower::util::Either;
 use tracing::{error, info};
 
 #[cfg(feature = "history")]
 use crate::routes::history::list_history;
-use crate::routes::suggest::suggest;
+use crate::routes::{api::complete::complete_bangs, suggest::suggest};
 
 #[cfg(feature = "history")]
 mod history;
+#[cfg(feature = "history")]
+pub use history::save_history;
 
+pub mod golinks;
+pub mod mirrors;
 mod routes;
 
 type AppEngine = Engine<Handlebars<'static>>;
@@ -53,12 +65,74 @@ where
     }
 }
 
+/// The state shared by every route of [`router`].
 #[derive(Clone)]
 pub struct AppState {
     engine: AppEngine,
     shared_config: Arc<RwLock<Config>>,
 }
 
+impl AppState {
+    /// The config being served, which is replaced whenever it is reloaded (see [`watch_config`]).
+    #[must_use]
+    pub const fn shared_config(&self) -> &Arc<RwLock<Config>> {
+        &self.shared_config
+    }
+}
+
+/// Where [`router`] is mounted, such as `/boom` once nested within another app (see
+/// [`NestedPath`]). Links within the pages of `boom` are relative to it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MountPath(String);
+
+impl MountPath {
+    fn from_extensions(extensions: &Extensions) -> Self {
+        Self(
+            extensions
+                .get::<NestedPath>()
+                .map(|path| path.as_str().trim_end_matches('/').to_string())
+                .unwrap_or_default(),
+        )
+    }
+
+    /// The path of the homepage, which queries are resolved at.
+    pub(crate) fn root(&self) -> &str {
+        if self.0.is_empty() { "/" } else { &self.0 }
+    }
+
+    /// The path everything else is relative to, ending with a `/`.
+    pub(crate) fn base(&self) -> String {
+        format!("{}/", self.0)
+    }
+
+    /// Wraps the data of a template, so that its links can be resolved against the mount path.
+    pub(crate) fn page<T>(&self, data: T) -> Page<T> {
+        Page {
+            base: self.base(),
+            root: self.root().to_string(),
+            data,
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for MountPath {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_extensions(&parts.extensions))
+    }
+}
+
+/// The data of a template, along with `base` (the `href` of its `<base>`) and `root` (see
+/// [`MountPath::root`]).
+#[derive(Serialize)]
+pub(crate) struct Page<T> {
+    base: String,
+    root: String,
+    #[serde(flatten)]
+    data: T,
+}
+
 #[derive(RustEmbed)]
 #[folder = "assets/"]
 /// Assets bundled directly into the binary.
@@ -87,77 +161,98 @@ fn register_templates(hbs: &mut Handlebars) {
 
     hbs.register_template_string("/bangs", include_str!("../assets/bangs/index.html"))
         .expect("Template should be syntactically correct");
+
+    hbs.register_template_string("/params", include_str!("../assets/params/index.html"))
+        .expect("Template should be syntactically correct");
+
+    hbs.register_template_string("/post", include_str!("../assets/post/index.html"))
+        .expect("Template should be syntactically correct");
 }
 
 #[cfg(feature = "history")]
-/// Periodically calls `boom_web::history::save_history` and watches for UNIX signals.
+/// Periodically calls [`save_history`], every `period` and whenever `SIGUSR1` is received.
 ///
-/// This function does **not** handle history persistence itself; it merely acts
-/// as a periodic caller to [`boom_web::history::save_history`], invoking it
-/// every `period` and on specific signals:
-/// - `SIGINT` / `SIGTERM`: Saves history before exiting.
-/// - `SIGUSR1`: Forces an immediate history save without exiting.
+/// This function does **not** handle history persistence itself; it merely acts as a periodic
+/// caller to [`save_history`]. The task runs until the returned handle is aborted.
 ///
 /// # Panics
-/// If signal listeners cannot be created.
+/// If the signal listener cannot be created.
 ///
 /// # Example
-/// ```rust.no_run
+/// ```no_run
 /// use std::time::Duration;
 /// use boom_web::watch_history;
 ///
+/// # #[tokio::main] async fn main() {
 /// // Save history every 30 seconds
-/// watch_history(Duration::from_secs(30));
+/// let saver = watch_history(Duration::from_secs(30));
+/// # }
 /// ```
-pub(crate) fn watch_history(period: Duration) {
-    use tokio::signal::unix::{SignalKind, signal};
+pub fn watch_history(period: Duration) -> JoinHandle<()> {
+    use tokio::{
+        signal::unix::{SignalKind, signal},
+        time::{Instant, interval_at},
+    };
 
     info!("Saving histfile with period: {period:?}");
 
-    let mut sigterm =
-        signal(SignalKind::terminate()).expect("Process should be able to listen to signals");
-    let mut sigint =
-        signal(SignalKind::interrupt()).expect("Process should be able to listen to signals");
     let mut sigusr1 =
         signal(SignalKind::user_defined1()).expect("Process should be able to listen to signals");
 
     tokio::spawn(async move {
-        use tokio::time::{Instant, interval_at};
-
-        use crate::history::save_history;
         let mut history_save_interval = interval_at(Instant::now() + period, period);
         loop {
-            history_save_interval.tick().await;
+            tokio::select! {
+                _ = history_save_interval.tick() => {}
+                _ = sigusr1.recv() => info!("Force saving history"),
+            }
             save_history().await;
         }
-    });
+    })
+}
 
-    tokio::spawn(async move {
-        loop {
-            use std::process::exit;
+#[cfg(feature = "history")]
+/// Saves the history (see [`save_history`]) and then exits upon `SIGINT` or `SIGTERM`.
+///
+/// # Panics
+/// If the signal listeners cannot be created.
+fn save_history_on_exit() -> JoinHandle<()> {
+    use tokio::signal::unix::{SignalKind, signal};
 
-            use tokio::select;
+    let mut sigterm =
+        signal(SignalKind::terminate()).expect("Process should be able to listen to signals");
+    let mut sigint =
+        signal(SignalKind::interrupt()).expect("Process should be able to listen to signals");
 
-            use crate::history::save_history;
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = sigint.recv() => {}
+            _ = sigterm.recv() => {}
+        }
+        info!("Attempting to save history before quitting");
+        save_history().await;
+        std::process::exit(1);
+    })
+}
 
-            select! {
-                _ = sigint.recv() => {
-                    info!("Attempting to save history before quitting");
-                    save_history().await;
-                    exit(1);
-                }
-                _ = sigterm.recv() => {
-                    info!("Attempting to save history before quitting");
-                    save_history().await;
-                    exit(1);
-                }
-                _ = sigusr1.recv() => {
-                    info!("Force saving history");
-                    save_history().await;
+/// Removes every bang which has expired from the list every `period` (see
+/// [`remove_expired_bangs`]). They are pruned from the config file whenever it is next written.
+///
+/// The task runs until the returned handle is aborted.
+pub fn watch_expired_bangs(period: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            match remove_expired_bangs(unix_timestamp()) {
+                Ok(expired) if !expired.is_empty() => {
+                    info!("Removed expired bangs: !{}", expired.join(", !"));
                 }
+                Ok(_) => {}
+                Err(e) => error!("Could not remove expired bangs: {e}"),
             }
         }
-    });
+    })
 }
 
 /// Watches the config file for changes and hot-reloads the in-memory configuration.
@@ -172,28 +267,29 @@ pub(crate) fn watch_history(period: Duration) {
 /// redirects are applied at runtime.
 ///
 /// # Notes
-/// - Hot-reloading only works when using the default config path.
 /// - Only file content modifications trigger a reload; other filesystem
 ///   events are ignored.
-/// - Runs in a background task and does not block the caller.
+/// - Runs in a background task, until the returned handle is aborted, and does not block the
+///   caller or the runtime.
 ///
-/// # Panics
-/// If the file watcher cannot be created or the path cannot be watched.
+/// Should the file watcher not be created, or the path not be watched, the task panics, which
+/// surfaces only through the returned handle.
 ///
 /// # Example
-/// ```rust.no_run
+/// ```no_run
 /// use std::sync::{Arc, RwLock};
 /// use boom_web::watch_config;
 /// use boom_config::Config;
 ///
+/// # #[tokio::main] async fn main() {
 /// let cfg = Config::default();
 /// let shared_config = Arc::new(RwLock::new(cfg));
 ///
-/// watch_config(shared_config.clone());
+/// let watcher = watch_config(shared_config.clone());
 /// // Continue running the rest of the application...
-pub fn watch_config(shared_config: Arc<RwLock<Config>>) {
-    // NOTE: Hot-reloading only works using the default config path!
-
+/// # }
+/// ```
+pub fn watch_config(shared_config: Arc<RwLock<Config>>) -> JoinHandle<()> {
     tokio::spawn(async move {
         let config_path = shared_config.read().map_or_else(
             |_| get_default_config_path(),
@@ -202,14 +298,18 @@ pub fn watch_config(shared_config: Arc<RwLock<Config>>) {
 
         info!("Awaiting changes on {}", config_path.display());
 
-        let (tx, rx) = std::sync::mpsc::channel();
-        let mut watcher =
-            RecommendedWatcher::new(tx, notify::Config::default().with_compare_contents(true))
-                .unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut watcher = RecommendedWatcher::new(
+            move |res| {
+                let _ = tx.send(res);
+            },
+            notify::Config::default().with_compare_contents(true),
+        )
+        .unwrap();
         watcher
             .watch(&config_path, notify::RecursiveMode::NonRecursive)
             .unwrap();
-        for res in rx {
+        while let Some(res) = rx.recv().await {
             match res {
                 Ok(event) => {
                     if !matches!(
@@ -227,9 +327,15 @@ pub fn watch_config(shared_config: Arc<RwLock<Config>>) {
                         *write_lock = config;
                     }
 
+                    #[cfg(feature = "plugins")]
+                    boom_core::boom::plugins::update_plugins_from_config(
+                        &shared_config.read().unwrap(),
+                    );
+
                     let config_bangs = Arc::new(shared_config.read().unwrap().bangs.clone());
                     update_bangs_from_config(
                         config_bangs,
+                        config_path.clone(),
                         Arc::new(RwLock::new(vec![])),
                         true,
                         true,
@@ -239,16 +345,71 @@ pub fn watch_config(shared_config: Arc<RwLock<Config>>) {
                 Err(e) => error!("Watch Error: {e:?}"),
             }
         }
-    });
+    })
 }
 
-/// Serve the web server on `address` and `port`
-///
-/// # Panics
-/// Panics if the server could not bind to the desired address/port.
-pub async fn serve(address: IpAddr, port: u16, config: &Config) {
-    info!(name:"Boom", "Starting Web Server on {}:{}", address, port);
+/// The tasks `boom` relies on whilst serving, each of which runs until aborted.
+#[must_use = "Dropping the handles leaves the tasks running, without a way to abort them"]
+pub struct BackgroundTasks {
+    pub config: JoinHandle<()>,
+    pub mirrors: JoinHandle<()>,
+    pub expired_bangs: JoinHandle<()>,
+    #[cfg(feature = "history")]
+    pub history: JoinHandle<()>,
+}
+
+impl BackgroundTasks {
+    /// Starts every task for `state`, with the periods [`serve`] uses.
+    ///
+    /// Should the config file not be watched, the `config` task fails, which surfaces only
+    /// through its handle (see [`watch_config`]).
+    ///
+    /// # Panics
+    /// If the signals for saving the history cannot be listened to (see [`watch_history`]).
+    pub fn spawn(state: &AppState) -> Self {
+        Self {
+            config: watch_config(state.shared_config.clone()),
+            mirrors: mirrors::watch_mirrors(Duration::from_secs(60)),
+            expired_bangs: watch_expired_bangs(Duration::from_secs(60)),
+            #[cfg(feature = "history")]
+            history: watch_history(Duration::from_secs(60)),
+        }
+    }
+
+    /// Stops every task.
+    pub fn abort(&self) {
+        self.config.abort();
+        self.mirrors.abort();
+        self.expired_bangs.abort();
+        #[cfg(feature = "history")]
+        self.history.abort();
+    }
+}
 
+/// Builds the routes of `boom` for `config`, along with their state, for serving or mounting
+/// within another app (e.g through [`Router::nest`]).
+///
+/// No background tasks are started, see [`BackgroundTasks`].
+///
+/// # Example
+/// ```no_run
+/// use axum::{Router, routing::get};
+/// use boom_config::Config;
+/// use boom_web::{BackgroundTasks, router};
+///
+/// # #[tokio::main] async fn main() {
+/// let (boom, state) = router(&Config::default());
+/// let tasks = BackgroundTasks::spawn(&state);
+///
+/// let app: Router = Router::new()
+///     .route("/health", get(|| async { "OK" }))
+///     .nest("/boom", boom);
+/// # }
+/// ```
+pub fn router<S>(config: &Config) -> (Router<S>, AppState)
+where
+    S: Clone + Send + Sync + 'static,
+{
     let mut hbs = Handlebars::new();
     hbs.register_helper("json", Box::new(json_helper));
     register_templates(&mut hbs);
@@ -260,23 +421,19 @@ pub async fn serve(address: IpAddr, port: u16, config: &Config) {
         shared_config: Arc::new(shared_config),
     };
 
-    #[cfg(feature = "history")]
-    {
-        watch_history(Duration::from_secs(60));
-    }
-
-    watch_config(state.shared_config.clone());
-
     let mut router = Router::new()
-        .route("/", get(redirector))
+        .route("/", get(redirector).post(complete_params))
         .route("/bangs", get(list_bangs))
         .route("/suggest", get(suggest))
+        .route("/api/bangs/complete", get(complete_bangs))
         .route("/opensearch.xml", get(opensearch))
+        .route("/proxy.pac", get(proxy_pac))
         .route("/assets/{*path}", get(asset_handler)) // serve embedded files
         .route(
             "/sw.js",
             get(|| async { asset_handler(Path("bangs/sw.js".to_string())).await }),
-        );
+        )
+        .route("/{*path}", get(go_link));
 
     #[cfg(feature = "history")]
     {
@@ -292,6 +449,27 @@ pub async fn serve(address: IpAddr, port: u16, config: &Config) {
             .route("/api/add-bang", post(add_bang));
     }
 
+    let router = router
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            pseudo_host,
+        ))
+        .with_state(state.clone());
+    (router, state)
+}
+
+/// Serve the web server on `address` and `port`
+///
+/// # Panics
+/// Panics if the server could not bind to the desired address/port.
+pub async fn serve(address: IpAddr, port: u16, config: &Config) {
+    info!(name:"Boom", "Starting Web Server on {}:{}", address, port);
+
+    let (router, state) = router(config);
+    let _tasks = BackgroundTasks::spawn(&state);
+    #[cfg(feature = "history")]
+    save_history_on_exit();
+
     let addr = SocketAddr::new(address, port);
     let listener = match TcpListener::bind(addr).await {
         Ok(listener) => listener,
@@ -300,7 +478,7 @@ pub async fn serve(address: IpAddr, port: u16, config: &Config) {
         }
     };
     info!(name:"Boom", "Server running on {addr}");
-    axum::serve(listener, router.with_state(state).into_make_service())
+    axum::serve(listener, router.into_make_service())
         .await
         .unwrap();
 }
@@ -0,0 +1,74 @@
+//! Health checks for the mirrors of bangs (see [`boom_core::boom::mirrors`]).
+
+use std::{collections::HashSet, sync::LazyLock, time::Duration};
+
+use boom_core::{
+    boom::mirrors::mirror_origin,
+    cache::{get_redirects, set_mirror_health},
+};
+use reqwest::Client;
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{error, info, warn};
+
+static CLIENT: LazyLock<Client> = LazyLock::new(Client::new);
+
+/// How long a mirror has to respond to a health check.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The origins of every template within a mirror pool, including the bangs' own.
+fn mirror_origins() -> HashSet<String> {
+    let Ok(redirects) = get_redirects() else {
+        return HashSet::new();
+    };
+    redirects
+        .iter()
+        .filter_map(|bang| bang.mirrors().map(|mirrors| (bang, mirrors)))
+        .flat_map(|(bang, mirrors)| {
+            std::iter::once(bang.url_template())
+                .chain(mirrors.templates().iter().map(|template| template.source()))
+        })
+        .filter_map(mirror_origin)
+        .map(str::to_string)
+        .collect()
+}
+
+/// Requests the origin of every mirror at once, recording those which fail to respond within
+/// `timeout`, or respond with a server error, as unhealthy.
+pub async fn check_mirrors(timeout: Duration) {
+    let mut checks = JoinSet::new();
+    for origin in mirror_origins() {
+        checks.spawn(async move {
+            let healthy = match CLIENT.get(&origin).timeout(timeout).send().await {
+                Ok(response) => !response.status().is_server_error(),
+                Err(e) => {
+                    warn!("Mirror {origin} failed its health check: {e}");
+                    false
+                }
+            };
+            (origin, healthy)
+        });
+    }
+
+    while let Some(check) = checks.join_next().await {
+        let Ok((origin, healthy)) = check else {
+            continue;
+        };
+        if let Err(e) = set_mirror_health(&origin, healthy) {
+            error!("Could not record the health of {origin}: {e}");
+        }
+    }
+}
+
+/// Checks the health of every mirror (see [`check_mirrors`]) every `period`, starting straight
+/// away, until the returned handle is aborted.
+pub fn watch_mirrors(period: Duration) -> JoinHandle<()> {
+    info!("Checking mirrors with period: {period:?}");
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            check_mirrors(CHECK_TIMEOUT).await;
+        }
+    })
+}
@@ -1,8 +1,8 @@
-#[cfg(feature = "api")]
 pub mod api;
 pub mod bangs;
 #[cfg(feature = "history")]
 pub mod history;
 pub mod index;
 pub mod opensearch;
+pub mod pac;
 pub mod suggest;
@@ -1 +1,3 @@
+#[cfg(feature = "api")]
 pub mod add_bang;
+pub mod complete;
@@ -1,8 +1,12 @@
+use std::sync::Arc;
+
 use axum::{Json, extract::State, http::Response, response::IntoResponse};
-use boom_config::{BangCustomConfig, ConfigBuilder};
+use boom_config::{BangCustomConfig, ConfigBuilder, unix_timestamp};
 use boom_core::{
-    Redirect,
+    BangOrigin, Redirect,
+    boom::macros::{format_cycle, is_macro, macro_cycles},
     cache::{get_bang, get_redirects, update_redirect},
+    template::Template,
 };
 use reqwest::StatusCode;
 use serde::Deserialize;
@@ -16,13 +20,87 @@ pub struct RedirectExtras {
     base: Redirect,
     #[serde(rename = "overwrite")]
     allow_overwrite: Option<String>,
+    /// How many seconds the bang lasts for, as an alternative to `expires_at`.
+    ttl: Option<u64>,
 }
 
 pub async fn add_bang(
     State(state): State<AppState>,
     Json(req): Json<RedirectExtras>,
 ) -> impl IntoResponse {
-    let new_bang = req.base;
+    let mut new_bang = req.base;
+    new_bang.origin = Arc::new(BangOrigin::Api);
+    new_bang.shadowed.clear();
+
+    let now = unix_timestamp();
+    if let Some(ttl) = req.ttl {
+        new_bang.expires_at = Some(now.saturating_add(ttl));
+    }
+    if new_bang
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= now)
+    {
+        return Response::builder()
+            .status(StatusCode::UNPROCESSABLE_ENTITY)
+            .body("The bang would already have expired".to_string())
+            .unwrap();
+    }
+
+    if let Err(e) = Template::compile_with_params(new_bang.url_template.as_str(), &new_bang.params)
+    {
+        return Response::builder()
+            .status(StatusCode::UNPROCESSABLE_ENTITY)
+            .body(format!("Invalid url template: {e}"))
+            .unwrap();
+    }
+
+    if let Some((name, e)) = new_bang.fields.iter().find_map(|(name, value)| {
+        Template::compile_with_params(value.as_str(), &new_bang.params)
+            .err()
+            .map(|e| (name, e))
+    }) {
+        return Response::builder()
+            .status(StatusCode::UNPROCESSABLE_ENTITY)
+            .body(format!("Invalid template for the field `{name}`: {e}"))
+            .unwrap();
+    }
+
+    if let Some(Err(e)) = new_bang
+        .webhook
+        .as_ref()
+        .and_then(|webhook| webhook.fallback.as_deref())
+        .map(|fallback| Template::compile_with_params(fallback, &new_bang.params))
+    {
+        return Response::builder()
+            .status(StatusCode::UNPROCESSABLE_ENTITY)
+            .body(format!("Invalid webhook fallback: {e}"))
+            .unwrap();
+    }
+
+    if let Some((template, e)) = new_bang
+        .mirrors
+        .iter()
+        .flat_map(|mirrors| &mirrors.templates)
+        .find_map(|template| {
+            Template::compile_with_params(template.as_str(), &new_bang.params)
+                .err()
+                .map(|e| (template, e))
+        })
+    {
+        return Response::builder()
+            .status(StatusCode::UNPROCESSABLE_ENTITY)
+            .body(format!("Invalid template for the mirror {template}: {e}"))
+            .unwrap();
+    }
+
+    if is_macro(&new_bang.url_template)
+        && let Some(cycle) = find_macro_cycle(&new_bang)
+    {
+        return Response::builder()
+            .status(StatusCode::UNPROCESSABLE_ENTITY)
+            .body(format!("Macro would form a cycle: {cycle}"))
+            .unwrap();
+    }
 
     let handle_update_redirect = |b| match update_redirect(b) {
         Ok(()) => {
@@ -32,6 +110,14 @@ pub async fn add_bang(
                 BangCustomConfig {
                     template: b.url_template.clone(),
                     short_name: b.short_name.clone(),
+                    escape: b.escape,
+                    encoding: b.encoding.clone(),
+                    params: b.params.clone(),
+                    method: b.method,
+                    fields: b.fields.clone(),
+                    webhook: b.webhook.clone(),
+                    mirrors: b.mirrors.clone(),
+                    expires_at: b.expires_at,
                 },
             );
             cfg_builder.serialize();
@@ -62,14 +148,17 @@ pub async fn add_bang(
         None => get_bang(&new_bang.trigger).unwrap_or_default().map_or_else(
             || handle_update_redirect(&new_bang),
             |idx| {
-                let bang = get_redirects().expect("Read Lock on Redirects");
+                let bangs = get_redirects().expect("Read Lock on Redirects");
+                let bang = bangs
+                    .get(idx)
+                    .expect("Cached bang should be within the list");
                 Response::builder()
                     .status(StatusCode::CONFLICT)
                     .body(
                         json!({
-                            "s": bang[idx].short_name,
-                            "t": bang[idx].trigger,
-                            "u": bang[idx].url_template,
+                            "s": bang.short_name(),
+                            "t": bang.trigger(),
+                            "u": bang.url_template(),
                         })
                         .to_string(),
                     )
@@ -78,3 +167,17 @@ pub async fn add_bang(
         ),
     }
 }
+
+/// The cycle `new_bang` would form with the loaded macros, if any.
+fn find_macro_cycle(new_bang: &Redirect) -> Option<String> {
+    let bangs = get_redirects().expect("Read Lock on Redirects");
+    macro_cycles(
+        bangs
+            .iter()
+            .map(|bang| (bang.trigger(), bang.url_template()))
+            .chain([(new_bang.trigger.as_str(), new_bang.url_template.as_str())]),
+    )
+    .iter()
+    .find(|cycle| cycle.contains(&new_bang.trigger.as_str()))
+    .map(|cycle| format_cycle(cycle))
+}
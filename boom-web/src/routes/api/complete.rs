@@ -0,0 +1,26 @@
+use axum::{Json, extract::Query, response::IntoResponse};
+use boom_core::boom::trigger_index::{Completion, complete};
+use serde::Deserialize;
+
+/// The number of completions returned when `limit` is not given.
+pub(crate) const DEFAULT_COMPLETION_LIMIT: usize = 10;
+
+#[derive(Deserialize, Debug)]
+pub struct CompleteParams {
+    /// The partially typed trigger, with or without its leading `!`
+    #[serde(rename = "q")]
+    query: Option<String>,
+    limit: Option<usize>,
+}
+
+/// [`complete_bangs`] lists the bangs whose trigger or short name starts with `q`, best match
+/// first.
+pub async fn complete_bangs(Query(params): Query<CompleteParams>) -> impl IntoResponse {
+    let completions: Vec<Completion> = params.query.map_or_else(Vec::new, |query| {
+        complete(
+            query.trim(),
+            params.limit.unwrap_or(DEFAULT_COMPLETION_LIMIT),
+        )
+    });
+    Json(completions)
+}
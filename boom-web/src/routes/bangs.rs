@@ -3,7 +3,7 @@ use axum_template::RenderHtml;
 use boom_core::{Redirect, cache::get_redirects};
 use serde::Serialize;
 
-use crate::AppState;
+use crate::{AppState, MountPath};
 
 #[derive(Serialize, Debug)]
 struct TemplateData {
@@ -24,13 +24,17 @@ impl TemplateData {
 impl Default for TemplateData {
     fn default() -> Self {
         Self {
-            bangs: get_redirects().unwrap().clone(),
+            bangs: get_redirects()
+                .unwrap()
+                .iter()
+                .map(|bang| bang.to_redirect())
+                .collect(),
             api_enabled: cfg!(feature = "api"),
         }
     }
 }
 
 /// [`list_bangs`] - a quite self-explanatory name.
-pub async fn list_bangs(State(state): State<AppState>) -> impl IntoResponse {
-    RenderHtml("/bangs", state.engine, TemplateData::default())
+pub async fn list_bangs(State(state): State<AppState>, mount: MountPath) -> impl IntoResponse {
+    RenderHtml("/bangs", state.engine, mount.page(TemplateData::default()))
 }
@@ -1,16 +1,35 @@
-use std::time::Instant;
+use std::{cell::RefCell, time::Instant};
 
 use axum::{
-    extract::{Query, State},
-    response::{IntoResponse, Redirect},
+    Form,
+    extract::{Path, Query, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
 };
 use axum_template::RenderHtml;
-use boom_core::{SourceIdentifier, boom::resolver::resolve};
+use boom_core::{
+    SourceIdentifier,
+    boom::{
+        form::PostForm,
+        params::MissingParams,
+        pipeline::Resolving,
+        resolver::{Resolution, find_redirect},
+    },
+    cache::get_pipeline,
+    context::RequestContext,
+};
 use serde::{Deserialize, Serialize};
 use tower::util::Either;
 use tracing::info;
 
-use crate::{AppState, EitherResponse};
+use crate::{
+    AppEngine, AppState, EitherResponse, MountPath, Page,
+    golinks::{is_pseudo_host, link_query, path_query},
+};
+
+/// Prefixes the names of the parameters submitted by the form rendered for [`MissingParams`].
+const PARAM_FIELD_PREFIX: &str = "param.";
 
 #[derive(Deserialize, Debug)]
 pub struct SearchParams {
@@ -21,39 +40,266 @@ pub struct SearchParams {
     pub(crate) source_identifier: Option<SourceIdentifier>,
 }
 
+thread_local! {
+    /// Reused by every query resolved on this thread, so that resolving does not allocate.
+    static URL_BUFFER: RefCell<String> = RefCell::new(String::with_capacity(1024));
+}
+
 #[derive(Serialize)]
 struct TemplateData {
     source_identifier: String,
 }
 
+/// The pages shown in place of a redirect, each rendered by its own template.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum PageData {
+    /// `/params`, which asks for the parameters of a bang.
+    Params {
+        #[serde(flatten)]
+        missing: MissingParams,
+        source_identifier: String,
+    },
+    /// `/post`, which submits a form to the url a query resolved to.
+    Post {
+        action: String,
+        #[serde(flatten)]
+        form: PostForm,
+    },
+}
+
 /// [`redirector`] handles directing the user to the location of their parsed query, or, if no
 /// query is provided, showing them to the `boom` homepage.
+///
+/// Template variables are filled in from the `Accept-Language` and `Host` headers of the request.
+/// Should the bang be missing any of its parameters, a form asking for them is shown instead,
+/// which is submitted to [`complete_params`]. Bangs which only accept form submissions are given
+/// a form which submits itself, rather than a redirect.
 pub async fn redirector(
     State(state): State<AppState>,
+    mount: MountPath,
     Query(params): Query<SearchParams>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
+    let source_identifier = params.source_identifier.unwrap_or_default();
     let res = if let Some(query) = params.query {
-        let timer = Instant::now();
-        let resolved = resolve(
-            query.as_str(),
-            &state
-                .shared_config
-                .read()
-                .expect("Shared Config should not be poisoned")
-                .clone(),
-            &params.source_identifier.unwrap_or_default(),
-        );
-        info!("Redirecting to {resolved} took {:?}", timer.elapsed());
-        Either::Left(Redirect::to(resolved.as_str()))
+        Either::Left(
+            resolve_or_ask(&state, &mount, &query, &source_identifier, &headers, &[]).await,
+        )
     } else {
         Either::Right(RenderHtml(
             "/",
             state.engine,
-            TemplateData {
-                source_identifier: params.source_identifier.unwrap_or_default().into(),
-            },
+            mount.page(TemplateData {
+                source_identifier: source_identifier.into(),
+            }),
         ))
     };
 
     EitherResponse(res)
 }
+
+/// [`go_link`] resolves paths such as `/jira/PROJ-1` as [`redirector`] would resolve
+/// `!jira PROJ-1` (see [`golinks`](crate::golinks)). Paths which do not name a known bang are not
+/// found.
+pub async fn go_link(
+    State(state): State<AppState>,
+    mount: MountPath,
+    Path(path): Path<String>,
+    Query(params): Query<SearchParams>,
+    headers: HeaderMap,
+) -> Response {
+    let query = path_query(
+        &path,
+        state
+            .shared_config
+            .read()
+            .expect("Shared Config should not be poisoned")
+            .server
+            .path_prefix
+            .as_deref(),
+    );
+    resolve_link(&state, &mount, query, params, &headers).await
+}
+
+/// [`pseudo_host`] resolves every path requested of the
+/// [`pseudo_host`](boom_config::ServerConfig::pseudo_host), such as `http://go/jira/PROJ-1`, as a
+/// go-link (see [`go_link`]). Unlike other hosts, the paths of built-in routes are resolved too,
+/// whilst paths which do not name a known bang (such as the assets of the homepage) are served as
+/// they would be for any other host.
+pub async fn pseudo_host(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .or_else(|| request.uri().host());
+    let is_pseudo_host = host.is_some_and(|host| {
+        is_pseudo_host(
+            host,
+            &state
+                .shared_config
+                .read()
+                .expect("Shared Config should not be poisoned")
+                .server
+                .pseudo_host,
+        )
+    });
+    let query = is_pseudo_host
+        .then(|| urlencoding::decode(request.uri().path()).ok())
+        .flatten()
+        .and_then(|path| link_query(&path))
+        .filter(|query| find_redirect(query).is_some());
+    let Some(query) = query else {
+        return next.run(request).await;
+    };
+
+    let params = Query::<SearchParams>::try_from_uri(request.uri()).map_or(
+        SearchParams {
+            query: None,
+            source_identifier: None,
+        },
+        |Query(params)| params,
+    );
+    let mount = MountPath::from_extensions(request.extensions());
+    let source_identifier = params.source_identifier.unwrap_or_default();
+    resolve_or_ask(
+        &state,
+        &mount,
+        &query,
+        &source_identifier,
+        request.headers(),
+        &[],
+    )
+    .await
+    .into_response()
+}
+
+/// Resolves the query of a go-link, unless it does not name a known bang.
+async fn resolve_link(
+    state: &AppState,
+    mount: &MountPath,
+    query: Option<String>,
+    params: SearchParams,
+    headers: &HeaderMap,
+) -> Response {
+    let Some(query) = query.filter(|query| find_redirect(query).is_some()) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let source_identifier = params.source_identifier.unwrap_or_default();
+    resolve_or_ask(state, mount, &query, &source_identifier, headers, &[])
+        .await
+        .into_response()
+}
+
+/// [`complete_params`] completes the resolution of a query once the form shown by [`redirector`]
+/// is submitted, using the parameters given within it.
+pub async fn complete_params(
+    State(state): State<AppState>,
+    mount: MountPath,
+    headers: HeaderMap,
+    Form(fields): Form<Vec<(String, String)>>,
+) -> impl IntoResponse {
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find_map(|(field, value)| (field == name).then_some(value.as_str()))
+    };
+    let Some(query) = field("q").filter(|query| !query.is_empty()) else {
+        return EitherResponse(Either::Left(Redirect::to(mount.root())));
+    };
+    let source_identifier = field("si")
+        .map(|si| SourceIdentifier::from(si.to_string()))
+        .unwrap_or_default();
+    let params: Vec<(&str, &str)> = fields
+        .iter()
+        .filter_map(|(field, value)| {
+            Some((field.strip_prefix(PARAM_FIELD_PREFIX)?, value.as_str()))
+        })
+        .collect();
+
+    EitherResponse(Either::Right(
+        resolve_or_ask(&state, &mount, query, &source_identifier, &headers, &params).await,
+    ))
+}
+
+/// Resolves `query` into a redirect (or a form submitting itself), given `params` for the bang, or
+/// renders a form asking for those of its parameters which are missing.
+async fn resolve_or_ask(
+    state: &AppState,
+    mount: &MountPath,
+    query: &str,
+    source_identifier: &SourceIdentifier,
+    headers: &HeaderMap,
+    params: &[(&str, &str)],
+) -> EitherResponse<Redirect, RenderHtml<&'static str, AppEngine, Page<PageData>>> {
+    let timer = Instant::now();
+
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let mut context = RequestContext::new()
+        .with_source_identifier(source_identifier)
+        .with_params(params);
+    if let Some(accept_language) = header(header::ACCEPT_LANGUAGE) {
+        context = context.with_accept_language(accept_language);
+    }
+    if let Some(host) = header(header::HOST) {
+        context = context.with_host(host);
+    }
+
+    // Queries are recorded within the history by the `record` stage of the pipeline, once resolved
+    let resolved: Result<Resolution, MissingParams> = URL_BUFFER.with_borrow_mut(|url| {
+        url.clear();
+        let config = state
+            .shared_config
+            .read()
+            .expect("Shared Config should not be poisoned");
+        let resolution = get_pipeline().run(
+            &mut Resolving::new(query, &config, &context, url)
+                .required()
+                .recorded_for(source_identifier),
+        )?;
+        info!("Resolving {url} took {:?}", timer.elapsed());
+        Ok(resolution)
+    });
+
+    let resolved = match resolved {
+        Ok(resolution) => Ok(match resolution {
+            Resolution::Redirect => Either::Left(URL_BUFFER.with_borrow(|url| Redirect::to(url))),
+            Resolution::Form(form) => Either::Right(PageData::Post {
+                action: URL_BUFFER.with_borrow(Clone::clone),
+                form,
+            }),
+            Resolution::Webhook(call) => {
+                let fallback = URL_BUFFER.with_borrow(Clone::clone);
+                let target = call.fetch().await.unwrap_or(fallback);
+                info!("Redirecting to {target} took {:?}", timer.elapsed());
+                Either::Left(Redirect::to(&target))
+            }
+        }),
+        Err(missing) => Err(missing),
+    };
+
+    let (name, page) = match resolved {
+        Ok(Either::Left(redirect)) => return EitherResponse(Either::Left(redirect)),
+        Ok(Either::Right(page)) => ("/post", page),
+        Err(missing) => {
+            info!(
+                "Asking for the parameters of !{} took {:?}",
+                missing.trigger,
+                timer.elapsed()
+            );
+            (
+                "/params",
+                PageData::Params {
+                    missing,
+                    source_identifier: source_identifier.clone().into(),
+                },
+            )
+        }
+    };
+    EitherResponse(Either::Right(RenderHtml(
+        name,
+        state.engine.clone(),
+        mount.page(page),
+    )))
+}
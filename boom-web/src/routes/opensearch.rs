@@ -7,12 +7,13 @@ use mime_guess::mime::APPLICATION_JSON;
 use reqwest::header::HOST;
 use serde_json::json;
 
-use crate::{AppState, routes::index::SearchParams};
+use crate::{AppState, MountPath, routes::index::SearchParams};
 
 /// [`opensearch`] provides an XML response allowing browsers to add `boom` as a search-engine.
 /// This is done via the `OpenSearchDescription` tag, from the [OpenSearch 1.1 Namespace](http://a9.com/-/spec/opensearch/1.1/)
 pub async fn opensearch(
     State(state): State<AppState>,
+    mount: MountPath,
     Query(params): Query<SearchParams>,
     headers: HeaderMap,
 ) -> Response<String> {
@@ -53,13 +54,13 @@ r#"
   <InputEncoding>UTF-8</InputEncoding>
   <Image width="16" height="16" type="image/x-icon">{ICON_ICO}</Image>
   <Image width="32" height="32" type="image/png">{ICON_32}</Image>
-  <Url type="text/html" template="{scheme}://{host}/?q={{searchTerms}}&amp;si={source_identifier}" />
-  <Url type="application/x-suggestions+json" method="GET" template="{scheme}://{host}/suggest?q={{searchTerms}}&amp;si={source_identifier}" />
+  <Url type="text/html" template="{scheme}://{host}{root}?q={{searchTerms}}&amp;si={source_identifier}" />
+  <Url type="application/x-suggestions+json" method="GET" template="{scheme}://{host}{base}suggest?q={{searchTerms}}&amp;si={source_identifier}" />
 <Url
   type="application/opensearchdescription+xml"
   rel="self"
-  template="{scheme}://{host}/opensearch.xml&amp;si={source_identifier}" />
-</OpenSearchDescription>"#, host = host, source_identifier = Into::<String>::into(params.source_identifier.unwrap_or_default())
+  template="{scheme}://{host}{base}opensearch.xml&amp;si={source_identifier}" />
+</OpenSearchDescription>"#, host = host, root = mount.root(), base = mount.base(), source_identifier = Into::<String>::into(params.source_identifier.unwrap_or_default())
     ))
         .unwrap()
 }
@@ -0,0 +1,44 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::State,
+    http::{
+        HeaderMap,
+        header::{CONTENT_TYPE, HOST},
+    },
+    response::{IntoResponse, Response},
+};
+
+use boom_core::template::is_valid_host;
+
+use crate::{AppState, golinks::proxy_script};
+
+/// [`proxy_pac`] provides a proxy auto-config (PAC) file, which sends every request for the
+/// [`pseudo_host`](boom_config::ServerConfig::pseudo_host) to `boom`, and everything else directly.
+///
+/// `boom` is reached through its configured address, unless it listens on every address, in which
+/// case it is reached through the host the PAC file was requested from, provided it is a plain
+/// `host[:port]`.
+pub async fn proxy_pac(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let cfg = state
+        .shared_config
+        .read()
+        .expect("Shared Config should not be poisoned");
+
+    let listener = SocketAddr::new(cfg.server.address, cfg.server.port).to_string();
+    let proxy = if cfg.server.address.is_unspecified() {
+        headers
+            .get(HOST)
+            .and_then(|host| host.to_str().ok())
+            .filter(|host| is_valid_host(host))
+            .unwrap_or(&listener)
+    } else {
+        &listener
+    };
+
+    (
+        [(CONTENT_TYPE, "application/x-ns-proxy-autoconfig")],
+        proxy_script(&cfg.server.pseudo_host, proxy),
+    )
+        .into_response()
+}
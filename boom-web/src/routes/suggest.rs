@@ -4,6 +4,7 @@ use axum::{
     http::{HeaderMap, HeaderValue, StatusCode},
     response::IntoResponse,
 };
+use boom_core::boom::trigger_index::complete;
 use reqwest::Client;
 
 #[cfg(feature = "history-suggestions")]
@@ -12,7 +13,10 @@ use serde_json::Value;
 
 use tracing::error;
 
-use crate::{AppState, routes::index::SearchParams};
+use crate::{
+    AppState,
+    routes::{api::complete::DEFAULT_COMPLETION_LIMIT, index::SearchParams},
+};
 
 #[cfg(feature = "history-suggestions")]
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,6 +32,9 @@ struct Suggestions {
 /// > LibreWolf, may disable search suggestions by default.
 /// > On Firefox-based browsers, it should be possible to enable the feature via the `#about:preferences#search` settings page.
 /// > For those using Chromium-based browsers, the equivalent would be `chrome://settings/syncSetup`
+///
+/// Whilst the last word of the query is a partially typed bang (e.g `rust !gi`), the
+/// suggestions are instead completions of that bang, taken from the loaded bangs.
 pub async fn suggest(
     State(state): State<AppState>,
     Query(params): Query<SearchParams>,
@@ -52,6 +59,24 @@ pub async fn suggest(
         return bad_request();
     };
 
+    // Complete a partially typed bang locally instead of asking the suggestions provider
+    if let Some(partial) = query
+        .rsplit(' ')
+        .next()
+        .filter(|word| word.len() > 1 && word.starts_with('!'))
+    {
+        let head = &query[..query.len() - partial.len()];
+        let completions: Vec<String> = complete(partial, DEFAULT_COMPLETION_LIMIT)
+            .into_iter()
+            .map(|completion| format!("{head}!{}", completion.trigger))
+            .collect();
+        return (
+            StatusCode::OK,
+            headers.clone(),
+            Json(serde_json::json!([query, completions])),
+        );
+    }
+
     // Build URL from config
     let url = {
         let cfg = state
@@ -0,0 +1,15 @@
+#![no_main]
+
+use boom_core::boom::{
+    parse_bangs::{parse_bang_indexes_avx2, parse_bang_indexes_scalar},
+    simd_available,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|query: &str| {
+    if simd_available() {
+        // SAFETY: AVX2 is supported.
+        let simd = unsafe { parse_bang_indexes_avx2(query) };
+        assert_eq!(simd, parse_bang_indexes_scalar(query), "{query:?}");
+    }
+});
@@ -0,0 +1,15 @@
+#![no_main]
+
+use boom_core::boom::{
+    parse_templates::{parse_template_indexes_avx2, parse_template_indexes_scalar},
+    simd_available,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|template: &str| {
+    if simd_available() {
+        // SAFETY: AVX2 is supported.
+        let simd = unsafe { parse_template_indexes_avx2(template) };
+        assert_eq!(simd, parse_template_indexes_scalar(template), "{template:?}");
+    }
+});
@@ -1,7 +1,7 @@
 use std::{net::IpAddr, path::PathBuf};
 
 use boom_config::{ConfigBuilder, ConfigSource, get_default_config_path};
-use clap::{Parser, Subcommand, command};
+use clap::{Parser, Subcommand, ValueEnum};
 use serde::Serialize;
 
 #[derive(Subcommand, Clone, Debug, Serialize)]
@@ -39,6 +39,62 @@ pub enum LaunchType {
         /// Redownloads any required bangs instead of relying on the cache
         #[arg(long, default_value_t = false)]
         no_cache: bool,
+
+        /// The preferred languages for `{{{lang}}}`, as an `Accept-Language` header.
+        /// E.g, en-GB,en;q=0.9
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// The source identifier for `{{{si}}}`
+        #[arg(long)]
+        si: Option<String>,
+
+        /// The host for `{{{host}}}`.
+        /// Defaults to the address and port of the server within the config
+        #[arg(long)]
+        host: Option<String>,
+    },
+
+    /// List the bangs whose trigger or short name starts with a prefix
+    Complete {
+        /// The partially typed trigger.
+        /// E.g, !gi
+        #[arg(required = true)]
+        prefix: String,
+
+        /// The maximum number of bangs to list
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// List bangs which took precedence over another definition of the same trigger,
+    /// along with where each definition came from
+    Conflicts,
+
+    /// Answer the requests of a browser extension over native messaging
+    /// ===============================================================
+    /// Requests and responses are JSON, each prefixed by its length,
+    /// over stdin and stdout.
+    /// With `--manifest`, the host manifest to install for the browser
+    /// is printed instead.
+    #[command(verbatim_doc_comment)]
+    NativeHost {
+        /// The browser to print the host manifest for
+        #[arg(long, value_enum, requires = "path")]
+        manifest: Option<Browser>,
+
+        /// The executable the browser launches, which must run `boom native-host`.
+        /// Browsers pass their own arguments to it, so this is usually a script
+        #[arg(long)]
+        path: Option<PathBuf>,
+
+        /// The ID of an extension allowed to connect to the host
+        #[arg(long = "extension", value_name = "ID")]
+        extensions: Vec<String>,
+
+        /// The arguments passed by the browser, such as the ID of the extension
+        #[arg(hide = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        browser_args: Vec<String>,
     },
 
     /// Validate the configuration
@@ -48,6 +104,14 @@ pub enum LaunchType {
     },
 }
 
+/// The browsers whose host manifests differ.
+#[derive(ValueEnum, Copy, Clone, Debug, Serialize)]
+pub enum Browser {
+    Firefox,
+    /// Chrome, Chromium and their derivatives.
+    Chromium,
+}
+
 #[derive(PartialEq, Eq, Copy, Clone)]
 pub(crate) enum SetupMode {
     /// Setup, ignoring any existing caches. If they are present, the cached resources will be
@@ -73,7 +137,10 @@ impl LaunchType {
                     SetupMode::Caches
                 }
             }
-            Self::Validate { .. } => SetupMode::NoSetup,
+            Self::Complete { .. } | Self::Conflicts | Self::NativeHost { manifest: None, .. } => {
+                SetupMode::Caches
+            }
+            Self::Validate { .. } | Self::NativeHost { .. } => SetupMode::NoSetup,
         }
     }
 }
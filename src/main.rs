@@ -26,16 +26,28 @@ use std::{
     sync::{Arc, RwLock},
 };
 
-use boom_config::{ConfigBuilder, ConfigSource};
+use boom_config::{BangConfig, ConfigBuilder, ConfigSource};
 use boom_core::{
-    SourceIdentifier,
-    boom::{resolver::resolve, update_bangs_from_config::update_bangs_from_config},
+    Redirect, SourceIdentifier,
+    boom::{
+        macros::{format_cycle, macro_cycles},
+        normalize::normalize_query,
+        pipeline::Resolving,
+        resolver::{Resolution, find_redirect, resolve_with_context},
+        trigger_index::complete,
+        update_bangs_from_config::{update_bangs_for_query, update_bangs_from_config},
+    },
+    cache::{get_conflicts, get_pipeline},
+    context::RequestContext,
+    encode::lookup_charset,
+    template::Template,
 };
-use boom_web::serve;
+use boom_web::{golinks::path_conflicts, serve};
 use clap::Parser;
 use cli::{LaunchType, SetupMode};
-use tracing::{Level, error, info};
+use tracing::{Level, error, info, warn};
 pub mod cli;
+mod native_host;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
@@ -57,8 +69,22 @@ async fn main() -> std::io::Result<()> {
 
         match &args.config.read_into_builder() {
             Ok(cfg) => {
-                dbg!(cfg.clone().build());
-                info!("Parsed config with no errors.");
+                let config = dbg!(cfg.clone().build());
+                for trigger in path_conflicts(&config) {
+                    warn!(
+                        "!{trigger} cannot be reached through /{trigger}, which is a built-in route. Set `server.path_prefix` to reach it."
+                    );
+                }
+                match (
+                    validate_templates(&config.bangs),
+                    validate_encodings(&config.bangs),
+                    validate_macros(&config.bangs),
+                ) {
+                    (0, 0, 0) => info!("Parsed config with no errors."),
+                    (templates, encodings, cycles) => error!(
+                        "Parsed config with {templates} invalid template(s), {encodings} unknown encoding(s) and {cycles} macro cycle(s)."
+                    ),
+                }
             }
             Err(e) => error!("{}", e),
         }
@@ -70,6 +96,21 @@ async fn main() -> std::io::Result<()> {
         exit(1);
     }
 
+    if let LaunchType::NativeHost {
+        manifest: Some(browser),
+        path: Some(path),
+        extensions,
+        ..
+    } = &args.launch
+    {
+        let manifest = native_host::manifest(*browser, path, extensions);
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&manifest).expect("Manifest should serialize")
+        );
+        return Ok(());
+    }
+
     let config = ConfigBuilder::new()
         .add_source(args.as_ref())
         .add_source(&args.config)
@@ -78,13 +119,23 @@ async fn main() -> std::io::Result<()> {
 
     let setup = args.launch.setup_type();
 
-    update_bangs_from_config(
-        Arc::new(config.bangs.clone()),
-        Arc::new(RwLock::new(vec![])),
-        matches!(setup, SetupMode::Caches),
-        false,
-    )
-    .await;
+    let use_cache = matches!(setup, SetupMode::Caches);
+    // A single query only needs the bangs it could resolve through
+    if let LaunchType::Resolve { search_query, .. } = &args.launch {
+        update_bangs_for_query(&config, search_query, use_cache).await;
+    } else {
+        update_bangs_from_config(
+            Arc::new(config.bangs.clone()),
+            config.config_source.clone(),
+            Arc::new(RwLock::new(vec![])),
+            use_cache,
+            false,
+        )
+        .await;
+    }
+
+    #[cfg(feature = "plugins")]
+    boom_core::boom::plugins::update_plugins_from_config(&config);
 
     #[cfg(feature = "history")]
     if let Err(e) = import_history_data() {
@@ -104,11 +155,85 @@ async fn main() -> std::io::Result<()> {
 
             serve(*addr, *port, &config).await;
         }
-        LaunchType::Resolve { search_query, .. } => {
-            println!(
-                "Resolved: {:?}",
-                resolve(search_query.as_str(), &config, &SourceIdentifier::default())
-            );
+        LaunchType::Resolve {
+            search_query,
+            lang,
+            si,
+            host,
+            ..
+        } => {
+            let source_identifier = si
+                .clone()
+                .map_or_else(SourceIdentifier::default, SourceIdentifier::from);
+            let host = host
+                .clone()
+                .unwrap_or_else(|| format!("{}:{}", config.server.address, config.server.port));
+            let mut context = RequestContext::new()
+                .with_source_identifier(&source_identifier)
+                .with_host(&host);
+            if let Some(lang) = lang {
+                context = context.with_accept_language(lang);
+            }
+
+            let mut url = String::new();
+            let mut state = Resolving::new(search_query, &config, &context, &mut url)
+                .required()
+                .recorded_for(&source_identifier);
+            let resolved = get_pipeline().run(&mut state);
+            let expanded = state.query.into_owned();
+            if expanded != normalize_query(search_query) {
+                println!("Expanded: {expanded:?}");
+            }
+
+            let resolution = match resolved {
+                Ok(resolution) => resolution,
+                Err(missing) => {
+                    println!(
+                        "Missing parameters for !{}: {}",
+                        missing.trigger,
+                        missing.missing().collect::<Vec<_>>().join(", ")
+                    );
+                    resolve_with_context(search_query, &config, &context, &mut url);
+                    Resolution::Redirect
+                }
+            };
+            match resolution {
+                Resolution::Redirect => println!("Resolved: {url:?}"),
+                Resolution::Form(form) => {
+                    println!("Resolved: {url:?}");
+                    println!("Submitted as a form ({}):", form.charset.unwrap_or("UTF-8"));
+                    for field in &form.fields {
+                        println!("  {}: {:?}", field.name, field.value);
+                    }
+                }
+                Resolution::Webhook(call) => {
+                    println!("Webhook: {:?}", call.endpoint);
+                    if let Some(target) = call.fetch().await {
+                        println!("Resolved: {target:?}");
+                    } else {
+                        println!("Resolved (fallback): {url:?}");
+                    }
+                }
+            }
+            if let Some(redirect) = find_redirect(search_query) {
+                print_provenance(&redirect);
+            }
+        }
+        LaunchType::Complete { prefix, limit } => {
+            for completion in complete(prefix, *limit) {
+                println!(
+                    "!{} ({}) {:?}",
+                    completion.trigger, completion.short_name, completion.url_template
+                );
+            }
+        }
+        LaunchType::Conflicts => match get_conflicts() {
+            Ok(conflicts) if conflicts.is_empty() => println!("No conflicting bangs."),
+            Ok(conflicts) => conflicts.iter().for_each(print_provenance),
+            Err(e) => error!("Could not read bangs. Reason: {e}"),
+        },
+        LaunchType::NativeHost { .. } => {
+            native_host::run(&config, io::stdin().lock(), io::stdout().lock()).await?;
         }
         _ => {}
     }
@@ -116,6 +241,107 @@ async fn main() -> std::io::Result<()> {
     Ok(())
 }
 
+/// Prints where `redirect` was defined, along with every definition it shadowed.
+fn print_provenance(redirect: &Redirect) {
+    println!(
+        "Bang: !{} ({}) from {}",
+        redirect.trigger, redirect.short_name, redirect.origin
+    );
+    for shadowed in &redirect.shadowed {
+        println!(
+            "  shadows: {} {:?} from {}",
+            shadowed.short_name, shadowed.url_template, shadowed.origin
+        );
+    }
+}
+
+/// Compiles the default search template and every custom bang (along with its form fields, webhook
+/// fallback and mirrors), logging those which are invalid. Returns how many were invalid.
+fn validate_templates(bangs: &BangConfig) -> usize {
+    let default = std::iter::once((
+        "default_search_template".to_string(),
+        &bangs.default_search_template,
+        [].as_slice(),
+    ));
+    let custom = bangs.custom.iter().flat_map(|(trigger, custom)| {
+        let fields = custom.fields.iter().map(move |(name, value)| {
+            (
+                format!("field `{name}` of custom bang !{trigger}"),
+                value,
+                custom.params.as_slice(),
+            )
+        });
+        std::iter::once((
+            format!("custom bang !{trigger}"),
+            &custom.template,
+            custom.params.as_slice(),
+        ))
+        .chain(fields)
+        .chain(custom.webhook.iter().filter_map(move |webhook| {
+            webhook.fallback.as_ref().map(|fallback| {
+                (
+                    format!("webhook fallback of custom bang !{trigger}"),
+                    fallback,
+                    custom.params.as_slice(),
+                )
+            })
+        }))
+        .chain(custom.mirrors.iter().flat_map(move |mirrors| {
+            mirrors.templates.iter().map(move |template| {
+                (
+                    format!("mirror {template} of custom bang !{trigger}"),
+                    template,
+                    custom.params.as_slice(),
+                )
+            })
+        }))
+    });
+
+    default
+        .chain(custom)
+        .filter_map(|(name, template, params)| {
+            Template::compile_with_params(template.as_str(), params)
+                .err()
+                .map(|e| (name, e))
+        })
+        .inspect(|(name, e)| error!("Invalid template for {name}: {e}"))
+        .count()
+}
+
+/// Looks up the charset of every custom bang and override, logging those which are unknown.
+/// Returns how many were unknown.
+fn validate_encodings(bangs: &BangConfig) -> usize {
+    let custom = bangs.custom.iter().filter_map(|(trigger, custom)| {
+        custom
+            .encoding
+            .as_ref()
+            .map(|encoding| (format!("custom bang !{trigger}"), encoding))
+    });
+    let overrides = bangs
+        .encodings
+        .iter()
+        .map(|(trigger, encoding)| (format!("encoding override for !{trigger}"), encoding));
+
+    custom
+        .chain(overrides)
+        .filter(|(_, encoding)| lookup_charset(encoding).is_none())
+        .inspect(|(name, encoding)| error!("Unknown encoding for {name}: {encoding}"))
+        .count()
+}
+
+/// Finds the cycles formed by custom macros, logging each of them. Returns how many there were.
+fn validate_macros(bangs: &BangConfig) -> usize {
+    macro_cycles(
+        bangs
+            .custom
+            .iter()
+            .map(|(trigger, custom)| (trigger.as_str(), custom.template.as_str())),
+    )
+    .iter()
+    .inspect(|cycle| error!("Macros form a cycle: {}", format_cycle(cycle)))
+    .count()
+}
+
 #[cfg(feature = "history")]
 fn import_history_data() -> Result<(), Box<dyn std::error::Error>> {
     use std::fs::File;
@@ -0,0 +1,305 @@
+//! A [native-messaging](https://developer.mozilla.org/en-US/docs/Mozilla/Add-ons/WebExtensions/Native_messaging)
+//! host, through which browser extensions resolve queries without the server running.
+//!
+//! Every message, in either direction, is a JSON object prefixed by its length in bytes as a
+//! 32-bit integer in native byte order. Each request is answered by exactly one response, carrying
+//! the `id` of the request, if it has one:
+//! - `{"type": "resolve", "query": "!gh boom"}` (optionally with `lang`, `si` and `params`) is
+//!   answered by `{"type": "resolved", "url": …}`, along with the `form` to submit to it for bangs
+//!   which are posted, or by `{"type": "missing_params", "missing": …}`.
+//! - `{"type": "complete", "prefix": "!gi"}` (optionally with `limit`) is answered by
+//!   `{"type": "completions", "completions": […]}`.
+//! - `{"type": "list"}` (optionally with `offset` and `limit`) is answered by
+//!   `{"type": "bangs", "bangs": […], "total": …}`.
+//!
+//! Anything else is answered by `{"type": "error", "message": …}`.
+
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind, Read, Write},
+    path::Path,
+};
+
+use boom_config::{Config, unix_timestamp};
+use boom_core::{
+    SourceIdentifier,
+    boom::{
+        form::PostForm,
+        params::MissingParams,
+        pipeline::Resolving,
+        resolver::Resolution,
+        trigger_index::{Completion, complete},
+    },
+    cache::{get_pipeline, get_redirects},
+    context::RequestContext,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::cli::Browser;
+
+/// The name of the host, which extensions connect to.
+pub(crate) const HOST_NAME: &str = "boom";
+
+/// The largest message a browser accepts from a host.
+const MAX_RESPONSE_LEN: usize = 1024 * 1024;
+
+/// The largest message read from a browser, well beyond any request.
+const MAX_REQUEST_LEN: u64 = 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    Resolve {
+        query: String,
+        /// The preferred languages, as an `Accept-Language` header.
+        lang: Option<String>,
+        si: Option<String>,
+        #[serde(default)]
+        params: HashMap<String, String>,
+    },
+    Complete {
+        prefix: String,
+        #[serde(default = "default_completions")]
+        limit: usize,
+    },
+    List {
+        #[serde(default)]
+        offset: usize,
+        #[serde(default = "default_bangs")]
+        limit: usize,
+    },
+}
+
+const fn default_completions() -> usize {
+    10
+}
+
+const fn default_bangs() -> usize {
+    1000
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Response {
+    Resolved {
+        url: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        form: Option<PostForm>,
+    },
+    MissingParams {
+        missing: MissingParams,
+    },
+    Completions {
+        completions: Vec<Completion>,
+    },
+    Bangs {
+        bangs: Vec<Completion>,
+        /// How many bangs there are, regardless of `offset` and `limit`.
+        total: usize,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Answers every request read from `reader` until it is closed, writing the responses to `writer`.
+///
+/// # Errors
+/// If either `reader` or `writer` fails.
+pub(crate) async fn run(
+    config: &Config,
+    mut reader: impl Read,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    while let Some(message) = read_message(&mut reader)? {
+        let Some(message) = message else {
+            let response = Response::Error {
+                message: format!("The request is larger than {MAX_REQUEST_LEN} bytes."),
+            };
+            write_response(&mut writer, None, &response)?;
+            continue;
+        };
+        let (id, response) = match serde_json::from_slice::<Value>(&message) {
+            Ok(message) => {
+                let id = message.get("id").cloned();
+                let response = match serde_json::from_value::<Request>(message) {
+                    Ok(request) => answer(config, request).await,
+                    Err(e) => Response::Error {
+                        message: format!("Invalid request: {e}"),
+                    },
+                };
+                (id, response)
+            }
+            Err(e) => (
+                None,
+                Response::Error {
+                    message: format!("Invalid JSON: {e}"),
+                },
+            ),
+        };
+        write_response(&mut writer, id, &response)?;
+    }
+    Ok(())
+}
+
+async fn answer(config: &Config, request: Request) -> Response {
+    match request {
+        Request::Resolve {
+            query,
+            lang,
+            si,
+            params,
+        } => resolve(config, &query, lang.as_deref(), si, &params).await,
+        Request::Complete { prefix, limit } => Response::Completions {
+            completions: complete(&prefix, limit),
+        },
+        Request::List { offset, limit } => list(offset, limit),
+    }
+}
+
+async fn resolve(
+    config: &Config,
+    query: &str,
+    lang: Option<&str>,
+    si: Option<String>,
+    params: &HashMap<String, String>,
+) -> Response {
+    let source_identifier = si.map_or_else(SourceIdentifier::default, SourceIdentifier::from);
+    let host = format!("{}:{}", config.server.address, config.server.port);
+    let params: Vec<(&str, &str)> = params
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    let mut context = RequestContext::new()
+        .with_source_identifier(&source_identifier)
+        .with_host(&host)
+        .with_params(&params);
+    if let Some(lang) = lang {
+        context = context.with_accept_language(lang);
+    }
+
+    let mut url = String::new();
+    let resolution = get_pipeline().run(
+        &mut Resolving::new(query, config, &context, &mut url)
+            .required()
+            .recorded_for(&source_identifier),
+    );
+    match resolution {
+        Ok(Resolution::Redirect) => Response::Resolved { url, form: None },
+        Ok(Resolution::Form(form)) => Response::Resolved {
+            url,
+            form: Some(form),
+        },
+        Ok(Resolution::Webhook(call)) => Response::Resolved {
+            url: call.fetch().await.unwrap_or(url),
+            form: None,
+        },
+        Err(missing) => Response::MissingParams { missing },
+    }
+}
+
+fn list(offset: usize, limit: usize) -> Response {
+    let Ok(redirects) = get_redirects() else {
+        return Response::Error {
+            message: "Could not read bangs.".to_string(),
+        };
+    };
+
+    let now = unix_timestamp();
+    let live = || redirects.iter().filter(move |bang| !bang.is_expired(now));
+    Response::Bangs {
+        bangs: live()
+            .skip(offset)
+            .take(limit)
+            .map(|bang| Completion {
+                short_name: bang.short_name().to_string(),
+                trigger: bang.trigger().to_string(),
+                url_template: bang.url_template().to_string(),
+            })
+            .collect(),
+        total: live().count(),
+    }
+}
+
+/// Reads a single message, unless `reader` has been closed. Messages larger than
+/// [`MAX_REQUEST_LEN`] are skipped, leaving `None` in their place.
+fn read_message(reader: &mut impl Read) -> io::Result<Option<Option<Vec<u8>>>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+
+    let len = u64::from(u32::from_ne_bytes(len));
+    if len > MAX_REQUEST_LEN {
+        if io::copy(&mut reader.take(len), &mut io::sink())? < len {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        return Ok(Some(None));
+    }
+
+    let mut message = Vec::new();
+    reader.take(len).read_to_end(&mut message)?;
+    if (message.len() as u64) < len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(Some(Some(message)))
+}
+
+/// Writes `response` as a single message, or an error should it be too large for the browser.
+fn write_response(
+    writer: &mut impl Write,
+    id: Option<Value>,
+    response: &Response,
+) -> io::Result<()> {
+    let mut message = serde_json::to_value(response)?;
+    if let (Some(id), Value::Object(fields)) = (id.clone(), &mut message) {
+        fields.insert("id".to_string(), id);
+    }
+
+    let mut bytes = serde_json::to_vec(&message)?;
+    if bytes.len() > MAX_RESPONSE_LEN {
+        let mut error = json!({
+            "type": "error",
+            "message": format!("The response is larger than {MAX_RESPONSE_LEN} bytes."),
+        });
+        if let Some(id) = id {
+            error["id"] = id;
+        }
+        bytes = serde_json::to_vec(&error)?;
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    writer.write_all(&(bytes.len() as u32).to_ne_bytes())?;
+    writer.write_all(&bytes)?;
+    writer.flush()
+}
+
+/// The manifest to install for `browser`, allowing `extensions` to launch `path` as the host.
+///
+/// Chromium expects the origins of the extensions, which are formed from their IDs when needed.
+pub(crate) fn manifest(browser: Browser, path: &Path, extensions: &[String]) -> Value {
+    let mut manifest = json!({
+        "name": HOST_NAME,
+        "description": env!("CARGO_PKG_DESCRIPTION"),
+        "path": path,
+        "type": "stdio",
+    });
+    match browser {
+        Browser::Firefox => manifest["allowed_extensions"] = json!(extensions),
+        Browser::Chromium => {
+            manifest["allowed_origins"] = extensions
+                .iter()
+                .map(|id| {
+                    if id.starts_with("chrome-extension://") {
+                        id.clone()
+                    } else {
+                        format!("chrome-extension://{id}/")
+                    }
+                })
+                .collect();
+        }
+    }
+    manifest
+}
@@ -1,6 +1,17 @@
-use std::time::Instant;
+use std::{sync::Once, time::Instant};
 
-use boom_core::boom::{Match, parse_bangs::parse_bang_indexes};
+use boom_config::{BangParamConfig, Config, HttpMethod, UrlComponent};
+use boom_core::{
+    Redirect, SourceIdentifier,
+    boom::{
+        Match,
+        normalize::{normalize_query, trigger_candidates},
+        parse_bangs::parse_bang_indexes,
+        resolver::{Resolution, resolve, try_resolve_with_context},
+    },
+    cache::{init_list, insert_bang},
+    context::RequestContext,
+};
 
 #[test]
 fn test_bang_retrieval_none() {
@@ -122,6 +133,318 @@ fn test_bang_invalid_single_char() {
     assert_eq!(indices, Some(Match::new(7, infix.len())))
 }
 
+#[test]
+fn test_bang_later_in_chunk() {
+    // Every `!` shares the first 32 byte chunk, though only the last begins a bang
+    let infix = "x!a y!b z!c w!d this !gh is within the same chunk";
+    let timer = Instant::now();
+    let indices = parse_bang_indexes(infix);
+    eprintln!(
+        "Took {:?} to retrieve the bang indices. (LATER IN CHUNK)",
+        timer.elapsed()
+    );
+    assert_eq!(indices, Some(Match::new(21, 24)))
+}
+
+// Regressions for query normalization and tokenization
+
+static LOAD_BANGS: Once = Once::new();
+
+fn load_bangs() {
+    LOAD_BANGS.call_once(|| {
+        let mut bangs = [
+            ("GitHub", "gh", "https://github.com/{{{s}}}"),
+            ("C++", "c++", "https://cppreference.com/?q={{{s}}}"),
+            ("Wiki", "w", "https://w.org/wiki/{{{s}}}"),
+            (
+                "Kakaku",
+                "kakaku",
+                "https://kakaku.com/search_results/{{{s}}}",
+            ),
+            ("My repo", "myrepo", "!gh tobybridle/{{{s}}}"),
+            ("My repo issues", "myissues", "!myrepo {{{s}}}/issues"),
+            ("Loop", "loop", "!loop {{{s}}}"),
+            (
+                "Jira",
+                "jira",
+                "https://jira.example.com/issues/?project={{{project}}}&type={{{type}}}&text={{{s}}}",
+            ),
+            ("Boom issues", "boomissues", "!jira project=BOOM {{{s}}}"),
+            ("Intranet", "intranet", "https://intranet.example.com/search"),
+        ]
+        .map(|(short_name, trigger, template)| Redirect {
+            short_name: short_name.to_string(),
+            trigger: trigger.to_string(),
+            url_template: template.to_string(),
+            ..Default::default()
+        });
+        bangs[2].escape = Some(UrlComponent::Query);
+        bangs[3].encoding = Some("shift_jis".to_string());
+        bangs[7].params = vec![
+            BangParamConfig {
+                name: "project".to_string(),
+                label: "Project".to_string(),
+                ..Default::default()
+            },
+            BangParamConfig {
+                name: "type".to_string(),
+                default: Some("bug".to_string()),
+                choices: vec!["bug".to_string(), "story".to_string()],
+                ..Default::default()
+            },
+        ];
+        bangs[9].method = HttpMethod::Post;
+        bangs[9].fields = [("query", "{{{s}}}"), ("scope", "all")]
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .into();
+        bangs.iter().enumerate().for_each(|(idx, bang)| {
+            insert_bang(bang.trigger.clone(), idx).unwrap();
+        });
+        init_list(bangs.to_vec(), true).unwrap();
+    });
+}
+
+fn resolve_with_bangs(query: &str) -> String {
+    load_bangs();
+    resolve(query, &Config::default(), &SourceIdentifier::default())
+}
+
+#[test]
+fn test_bang_trailing_exclamation() {
+    let timer = Instant::now();
+    let indices = parse_bang_indexes("test !");
+    eprintln!(
+        "Took {:?} to retrieve the bang indices. (TRAILING EXCLAMATION)",
+        timer.elapsed()
+    );
+    assert_eq!(indices, None);
+    assert_eq!(parse_bang_indexes("!"), None);
+}
+
+#[test]
+fn test_bang_ascii_whitespace_separators() {
+    let timer = Instant::now();
+    let indices = parse_bang_indexes("rust\t!gh\ntraits");
+    eprintln!(
+        "Took {:?} to retrieve the bang indices. (ASCII WHITESPACE)",
+        timer.elapsed()
+    );
+    assert_eq!(indices, Some(Match::new(5, 8)));
+}
+
+#[test]
+fn test_bang_opening_punctuation() {
+    assert_eq!(
+        parse_bang_indexes("rust (!gh) traits"),
+        Some(Match::new(6, 10))
+    );
+    assert_eq!(parse_bang_indexes("\"!gh"), Some(Match::new(1, 4)));
+    assert_eq!(parse_bang_indexes("rust!gh"), None);
+}
+
+#[test]
+fn test_normalize_unicode_whitespace() {
+    assert_eq!(normalize_query("rust\u{a0}!gh"), "rust !gh");
+    assert_eq!(normalize_query("rust\u{2003}\u{3000}!gh"), "rust !gh");
+    assert_eq!(
+        normalize_query("  rust \t\n traits   !gh  "),
+        "rust traits !gh"
+    );
+    assert_eq!(normalize_query(" \t "), "");
+    assert_eq!(normalize_query("already normal !gh"), "already normal !gh");
+}
+
+#[test]
+fn test_trigger_closing_punctuation() {
+    assert_eq!(
+        trigger_candidates("gh,").collect::<Vec<_>>(),
+        vec!["gh,", "gh"]
+    );
+    assert_eq!(trigger_candidates("c++").collect::<Vec<_>>(), vec!["c++"]);
+    assert_eq!(trigger_candidates("!").collect::<Vec<_>>(), vec!["!"]);
+}
+
+#[test]
+fn test_resolve_normalized_query() {
+    for query in [
+        "rust traits !gh",
+        "rust\ttraits\t!gh",
+        "rust\u{a0}traits\u{a0}!gh",
+        "  rust   traits   !gh  ",
+        "rust traits !gh,",
+        "rust traits (!gh)",
+        "rust !gh traits",
+        "rust !gh, traits",
+        "!gh rust traits",
+        // Only trimmed
+        "rust traits !gh ",
+        " !gh rust traits",
+    ] {
+        assert_eq!(
+            resolve_with_bangs(query),
+            "https://github.com/rust%20traits",
+            "{query:?}"
+        );
+    }
+    assert_eq!(resolve_with_bangs("  "), "https://google.com/search?q=");
+    assert_eq!(
+        resolve_with_bangs("foo "),
+        "https://google.com/search?q=foo"
+    );
+}
+
+#[test]
+fn test_resolve_punctuated_trigger() {
+    assert_eq!(
+        resolve_with_bangs("vectors !c++"),
+        "https://cppreference.com/?q=vectors"
+    );
+    assert_eq!(
+        resolve_with_bangs("vectors !c++."),
+        "https://cppreference.com/?q=vectors"
+    );
+}
+
+#[test]
+fn test_resolve_trailing_exclamation() {
+    assert_eq!(
+        resolve_with_bangs("wow !"),
+        "https://google.com/search?q=wow%20%21"
+    );
+}
+
+#[test]
+fn test_resolve_component_encoding() {
+    assert_eq!(
+        resolve_with_bangs("tobybridle/boom !gh"),
+        "https://github.com/tobybridle/boom"
+    );
+    assert_eq!(
+        resolve_with_bangs("a/b?c !c++"),
+        "https://cppreference.com/?q=a%2Fb%3Fc"
+    );
+    assert_eq!(resolve_with_bangs("AC/DC !w"), "https://w.org/wiki/AC%2FDC");
+}
+
+#[test]
+fn test_resolve_legacy_charset() {
+    assert_eq!(
+        resolve_with_bangs("日本 カメラ !kakaku"),
+        "https://kakaku.com/search_results/%93%FA%96%7B%20%83J%83%81%83%89"
+    );
+    assert_eq!(
+        resolve_with_bangs("日本 !gh"),
+        "https://github.com/%E6%97%A5%E6%9C%AC"
+    );
+}
+
+#[test]
+fn test_resolve_macro() {
+    assert_eq!(
+        resolve_with_bangs("boom !myrepo"),
+        "https://github.com/tobybridle/boom"
+    );
+    assert_eq!(
+        resolve_with_bangs("!myissues boom"),
+        "https://github.com/tobybridle/boom/issues"
+    );
+}
+
+#[test]
+fn test_resolve_macro_depth_limit() {
+    // Cycles are rejected at load time, but were not here, so the depth limit kicks in
+    let timer = Instant::now();
+    let url = resolve_with_bangs("forever !loop");
+    eprintln!("Took {:?} to give up on a cyclic macro.", timer.elapsed());
+    assert_eq!(url, "https://google.com/search?q=forever");
+}
+
+#[test]
+fn test_resolve_params() {
+    assert_eq!(
+        resolve_with_bangs("!jira project=BOOM type=story login page"),
+        "https://jira.example.com/issues/?project=BOOM&type=story&text=login%20page"
+    );
+    assert_eq!(
+        resolve_with_bangs("login !boomissues"),
+        "https://jira.example.com/issues/?project=BOOM&type=bug&text=login"
+    );
+    // Missing parameters are left empty
+    assert_eq!(
+        resolve_with_bangs("!jira login"),
+        "https://jira.example.com/issues/?project=&type=bug&text=login"
+    );
+}
+
+#[test]
+fn test_try_resolve_missing_params() {
+    load_bangs();
+    let config = Config::default();
+    let mut url = String::new();
+
+    let timer = Instant::now();
+    let missing = try_resolve_with_context(
+        "!jira type=task login",
+        &config,
+        &RequestContext::new(),
+        &mut url,
+    )
+    .unwrap_err();
+    eprintln!("Took {:?} to find the missing parameters.", timer.elapsed());
+    assert!(url.is_empty());
+    assert_eq!(missing.trigger, "jira");
+    assert_eq!(missing.query, "!jira type=task login");
+    assert_eq!(
+        missing.missing().collect::<Vec<_>>(),
+        vec!["project", "type"]
+    );
+    assert_eq!(missing.params[1].value, "task");
+
+    // Resubmitting the query alongside the parameters completes the resolution
+    let given = [("project", "BOOM"), ("type", "story")];
+    try_resolve_with_context(
+        &missing.query,
+        &config,
+        &RequestContext::new().with_params(&given),
+        &mut url,
+    )
+    .unwrap();
+    assert_eq!(
+        url,
+        "https://jira.example.com/issues/?project=BOOM&type=story&text=login"
+    );
+}
+
+#[test]
+fn test_try_resolve_post_form() {
+    load_bangs();
+    let config = Config::default();
+    let mut url = String::new();
+
+    let Ok(Resolution::Form(form)) = try_resolve_with_context(
+        "a/b & c !intranet",
+        &config,
+        &RequestContext::new(),
+        &mut url,
+    ) else {
+        panic!("Bang should be submitted as a form");
+    };
+    assert_eq!(url, "https://intranet.example.com/search");
+    assert_eq!(form.charset, None);
+    assert_eq!(
+        form.fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.value.as_str()))
+            .collect::<Vec<_>>(),
+        vec![("query", "a/b & c"), ("scope", "all")]
+    );
+
+    url.clear();
+    let resolution =
+        try_resolve_with_context("rust !gh", &config, &RequestContext::new(), &mut url);
+    assert_eq!(resolution, Ok(Resolution::Redirect));
+}
+
 #[cfg(feature = "measure-allocs")]
 mod tests {
     use super::*;
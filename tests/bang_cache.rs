@@ -0,0 +1,119 @@
+use std::{collections::HashSet, env, fs, path::PathBuf, time::Instant};
+
+use boom_config::{BangCustomConfig, BangSourceConfig, Config};
+use boom_core::{
+    boom::{
+        bang_cache::{BangCache, cache_path, find_bangs, load_bangs},
+        parse_bangs::parse_bang_file,
+        update_bangs_from_config::update_bangs_for_query,
+    },
+    cache::get_redirects,
+};
+use common::resolve;
+
+mod common;
+
+/// Copies `bangs.json` somewhere scratch, so that its cache is not written into the repository.
+fn scratch_bangs(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("boom-bang-cache-tests-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let source = dir.join(name);
+    fs::copy(env::current_dir().unwrap().join("bangs.json"), &source)
+        .expect("bangs.json should be present in the CWD!");
+    let _ = fs::remove_file(cache_path(&source));
+    source
+}
+
+#[test]
+fn test_cold_start_from_cache() {
+    let source = scratch_bangs("cold_start.json");
+
+    let timer = Instant::now();
+    let parsed = parse_bang_file(&source).unwrap();
+    eprintln!(
+        "Took {:?} to parse {} bangs from JSON.",
+        timer.elapsed(),
+        parsed.len()
+    );
+
+    // Parses the JSON and writes the cache
+    assert_eq!(load_bangs(&source).unwrap().len(), parsed.len());
+
+    let timer = Instant::now();
+    let cache = BangCache::open(&cache_path(&source), &source)
+        .unwrap()
+        .expect("The cache should be up to date");
+    eprintln!(
+        "Took {:?} to map {} cached bangs.",
+        timer.elapsed(),
+        cache.len()
+    );
+
+    let timer = Instant::now();
+    let bang = cache.find("gh").next().expect("!gh should be cached");
+    eprintln!("Took {:?} to find !gh within the cache.", timer.elapsed());
+    assert_eq!(bang.short_name, "GitHub");
+
+    let timer = Instant::now();
+    let triggers: HashSet<String> = ["gh", "w"].map(str::to_string).into();
+    let found = find_bangs(&source, &triggers).unwrap();
+    eprintln!(
+        "Took {:?} to load {} bangs through the trigger index.",
+        timer.elapsed(),
+        found.len()
+    );
+    assert_eq!(found.len(), 2);
+
+    let timer = Instant::now();
+    let cached = load_bangs(&source).unwrap();
+    eprintln!(
+        "Took {:?} to load {} bangs from the cache.",
+        timer.elapsed(),
+        cached.len()
+    );
+    assert_eq!(cached.len(), parsed.len());
+    for (cached, parsed) in cached.iter().zip(&parsed) {
+        assert_eq!(cached.short_name, parsed.short_name);
+        assert_eq!(cached.trigger, parsed.trigger);
+        assert_eq!(cached.url_template, parsed.url_template);
+        assert_eq!(cached.relevance, parsed.relevance);
+    }
+}
+
+/// A single query only loads the bangs it could resolve through, including those of its macros.
+#[tokio::test]
+async fn test_update_bangs_for_query() {
+    let mut config = Config::default();
+    config.bangs.sources = vec![BangSourceConfig {
+        required: true,
+        filepath: scratch_bangs("single_query.json"),
+        remote: None,
+    }];
+    config.bangs.custom.insert(
+        "myrepo".to_string(),
+        BangCustomConfig {
+            short_name: "My repo".to_string(),
+            template: "!gh tobybridle/{{{s}}}".to_string(),
+            ..Default::default()
+        },
+    );
+
+    let timer = Instant::now();
+    update_bangs_for_query(&config, "boom (!myrepo)", true).await;
+    eprintln!(
+        "Took {:?} to load the bangs for a single query.",
+        timer.elapsed()
+    );
+
+    let mut triggers: Vec<_> = get_redirects()
+        .unwrap()
+        .iter()
+        .map(|bang| bang.trigger().to_string())
+        .collect();
+    triggers.sort();
+    assert_eq!(triggers, vec!["gh", "myrepo"]);
+    assert_eq!(
+        resolve("boom (!myrepo)"),
+        "https://github.com/search?utf8=%E2%9C%93&q=tobybridle%2Fboom"
+    );
+}
@@ -0,0 +1,74 @@
+//! Helpers shared between the integration tests, each of which uses only some of them.
+#![allow(dead_code)]
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use boom_config::Config;
+use boom_core::{Redirect, boom::resolver::resolve_into};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpSocket},
+};
+
+/// Responds to every request with `status` and `body` (as JSON) after `delay`, counting the
+/// requests made.
+pub async fn serve_stub(
+    status: &'static str,
+    body: &'static str,
+    delay: Duration,
+) -> (SocketAddr, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let hits = Arc::new(AtomicUsize::new(0));
+
+    let counter = Arc::clone(&hits);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                let mut buf = [0; 4096];
+                let _ = stream.read(&mut buf).await;
+                tokio::time::sleep(delay).await;
+                let response = format!(
+                    "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    (addr, hits)
+}
+
+/// An address which refuses connections for as long as the socket is held, as it is bound
+/// without listening.
+pub fn closed_addr() -> (SocketAddr, TcpSocket) {
+    let socket = TcpSocket::new_v4().unwrap();
+    socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    (socket.local_addr().unwrap(), socket)
+}
+
+/// A bang for `trigger`, named after it.
+pub fn bang(trigger: &str, url_template: String) -> Redirect {
+    Redirect {
+        short_name: trigger.to_uppercase(),
+        trigger: trigger.to_string(),
+        url_template,
+        ..Default::default()
+    }
+}
+
+/// Resolves `query` with the default config.
+pub fn resolve(query: &str) -> String {
+    let mut url = String::new();
+    resolve_into(query, &Config::default(), &mut url);
+    url
+}
@@ -0,0 +1,138 @@
+use std::time::Instant;
+
+use boom_config::{BangParamConfig, unix_timestamp};
+use boom_core::{
+    BangOrigin, Redirect,
+    boom::resolver::find_redirect,
+    cache::{get_bang, remove_expired_bangs, update_redirect},
+};
+use common::{bang, resolve};
+
+mod common;
+
+fn add_bang(trigger: &str, expires_at: Option<u64>) {
+    update_redirect(&Redirect {
+        expires_at,
+        ..bang(
+            trigger,
+            format!("https://docs.example.com/{trigger}?q={{{{{{s}}}}}}"),
+        )
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_expired_bangs() {
+    let now = unix_timestamp();
+    add_bang("inc", Some(now - 1));
+    add_bang("rel", Some(now + 3600));
+    add_bang("wiki", None);
+
+    // Expired bangs are passed over before they are swept
+    assert_eq!(resolve("outage !inc"), "https://google.com/search?q=outage");
+    assert_eq!(resolve("v2 !rel"), "https://docs.example.com/rel?q=v2");
+    assert_eq!(
+        find_redirect("!rel").and_then(|redirect| redirect.expires_at),
+        Some(now + 3600)
+    );
+
+    let start = Instant::now();
+    let expired = remove_expired_bangs(now).unwrap();
+    eprintln!("Sweeping the expired bangs took {:?}", start.elapsed());
+
+    assert_eq!(expired, vec!["inc".to_string()]);
+    assert_eq!(get_bang("inc").unwrap(), None);
+    assert!(remove_expired_bangs(now).unwrap().is_empty());
+
+    assert_eq!(
+        remove_expired_bangs(now + 3600).unwrap(),
+        vec!["rel".to_string()]
+    );
+    assert_eq!(resolve("v2 !rel"), "https://google.com/search?q=v2");
+    assert_eq!(
+        resolve("rust !wiki"),
+        "https://docs.example.com/wiki?q=rust"
+    );
+}
+
+/// A temporary override gives way to the definition it shadowed once it expires.
+#[test]
+fn test_expired_override() {
+    let now = unix_timestamp();
+    update_redirect(&Redirect {
+        short_name: "Docs".to_string(),
+        trigger: "docs".to_string(),
+        url_template: "https://docs.example.com?q={{{s}}}".to_string(),
+        origin: BangOrigin::Config {
+            path: "config.toml".into(),
+        }
+        .into(),
+        ..Default::default()
+    })
+    .unwrap();
+    update_redirect(&Redirect {
+        short_name: "Status".to_string(),
+        trigger: "docs".to_string(),
+        url_template: "https://status.example.com?q={{{s}}}".to_string(),
+        expires_at: Some(now),
+        origin: BangOrigin::Api.into(),
+        ..Default::default()
+    })
+    .unwrap();
+    // An expired override is passed over in favour of what it shadowed, before it is swept
+    assert_eq!(resolve("!docs outage"), "https://docs.example.com?q=outage");
+
+    let start = Instant::now();
+    remove_expired_bangs(now).unwrap();
+    eprintln!("Restoring the shadowed bang took {:?}", start.elapsed());
+
+    assert_eq!(resolve("!docs rust"), "https://docs.example.com?q=rust");
+    assert_eq!(find_redirect("!docs").unwrap().short_name, "Docs");
+}
+
+/// The definition an expired override gives way to keeps everything beyond its template.
+#[test]
+fn test_expired_override_params() {
+    let now = unix_timestamp();
+    update_redirect(&Redirect {
+        short_name: "Tickets".to_string(),
+        trigger: "tickets".to_string(),
+        url_template: "https://tickets.example.com/{{{project}}}?q={{{s}}}".to_string(),
+        params: vec![BangParamConfig {
+            name: "project".to_string(),
+            default: Some("OPS".to_string()),
+            ..Default::default()
+        }],
+        origin: BangOrigin::Config {
+            path: "config.toml".into(),
+        }
+        .into(),
+        ..Default::default()
+    })
+    .unwrap();
+    update_redirect(&Redirect {
+        short_name: "Incident".to_string(),
+        trigger: "tickets".to_string(),
+        url_template: "https://status.example.com?q={{{s}}}".to_string(),
+        expires_at: Some(now),
+        origin: BangOrigin::Api.into(),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(
+        resolve("!tickets project=WEB login"),
+        "https://tickets.example.com/WEB?q=login"
+    );
+
+    remove_expired_bangs(now).unwrap();
+
+    assert_eq!(
+        resolve("!tickets project=WEB login"),
+        "https://tickets.example.com/WEB?q=login"
+    );
+    assert_eq!(
+        resolve("!tickets outage"),
+        "https://tickets.example.com/OPS?q=outage"
+    );
+    assert_eq!(find_redirect("!tickets").unwrap().params.len(), 1);
+}
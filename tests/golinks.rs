@@ -0,0 +1,67 @@
+use std::time::Instant;
+
+use boom_config::{BangCustomConfig, Config};
+use boom_web::golinks::{is_pseudo_host, link_query, path_conflicts, path_query, proxy_script};
+
+#[test]
+fn test_path_query() {
+    let start = Instant::now();
+    let query = path_query("/jira/PROJ-1", None);
+    eprintln!("Parsing the path took {:?}", start.elapsed());
+    assert_eq!(query.as_deref(), Some("!jira PROJ-1"));
+
+    assert_eq!(path_query("/docs/", None).as_deref(), Some("!docs"));
+    assert_eq!(
+        path_query("/gh/tobybridle/boom", None).as_deref(),
+        Some("!gh tobybridle/boom")
+    );
+    assert_eq!(path_query("/", None), None);
+    assert_eq!(path_query("/api/add-bang", None), None);
+
+    // Beneath a prefix, built-in routes are not reserved
+    assert_eq!(
+        path_query("/go/bangs", Some("go")).as_deref(),
+        Some("!bangs")
+    );
+    assert_eq!(path_query("/gopher/x", Some("go")), None);
+    assert_eq!(path_query("/go", Some("/go/")), None);
+}
+
+#[test]
+fn test_path_conflicts() {
+    let mut config = Config::default();
+    for trigger in ["bangs", "jira"] {
+        config
+            .bangs
+            .custom
+            .insert(trigger.to_string(), BangCustomConfig::default());
+    }
+    assert_eq!(path_conflicts(&config).collect::<Vec<_>>(), vec!["bangs"]);
+
+    config.server.path_prefix = Some("go".to_string());
+    assert_eq!(path_conflicts(&config).count(), 0);
+}
+
+#[test]
+fn test_pseudo_host() {
+    assert!(is_pseudo_host("go", "go"));
+    assert!(is_pseudo_host("GO:80", "go"));
+    assert!(is_pseudo_host("go.", "go"));
+    assert!(!is_pseudo_host("go.example.com", "go"));
+    assert!(!is_pseudo_host("127.0.0.1:3000", "go"));
+
+    // Every path of the pseudo-host is a go-link, including those of built-in routes
+    assert_eq!(link_query("/bangs").as_deref(), Some("!bangs"));
+
+    let start = Instant::now();
+    let script = proxy_script("Go", "127.0.0.1:3000");
+    eprintln!("Generating the PAC file took {:?}", start.elapsed());
+    assert!(script.starts_with("function FindProxyForURL(url, host) {"));
+    assert!(script.contains(r#"host.toLowerCase() === "go""#));
+    assert!(script.contains(r#"return "PROXY 127.0.0.1:3000";"#));
+    assert!(script.contains(r#"return "DIRECT";"#));
+
+    // Neither value can break out of its string literal
+    let script = proxy_script("go", r#"x";alert(1);""#);
+    assert!(script.contains(r#"return "PROXY x\";alert(1);\"";"#));
+}
@@ -0,0 +1,69 @@
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use boom_config::{MirrorConfig, MirrorStrategy};
+use boom_core::{Redirect, cache::update_redirect};
+use boom_web::mirrors::check_mirrors;
+use common::{bang, closed_addr, resolve, serve_stub};
+
+mod common;
+
+/// Adds the bang `trigger`, served by `primary` and then `mirrors`.
+fn add_mirrored_bang(
+    trigger: &str,
+    primary: SocketAddr,
+    mirrors: &[SocketAddr],
+    strategy: MirrorStrategy,
+) {
+    let template = |addr: &SocketAddr| format!("http://{addr}/search?q={{{{{{s}}}}}}");
+    update_redirect(&Redirect {
+        mirrors: Some(MirrorConfig {
+            templates: mirrors.iter().map(template).collect(),
+            strategy,
+        }),
+        ..bang(trigger, template(&primary))
+    })
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_mirrors_round_robin() {
+    let a = serve_stub("200 OK", "", Duration::ZERO).await.0;
+    let b = serve_stub("200 OK", "", Duration::ZERO).await.0;
+    add_mirrored_bang("rr", a, &[b], MirrorStrategy::RoundRobin);
+
+    let start = Instant::now();
+    let urls: Vec<_> = (0..4).map(|_| resolve("rust !rr")).collect();
+    eprintln!("Resolving 4 mirrored queries took {:?}", start.elapsed());
+
+    assert_eq!(urls[0], format!("http://{a}/search?q=rust"));
+    assert_eq!(urls[1], format!("http://{b}/search?q=rust"));
+    assert_eq!(urls[2], urls[0]);
+    assert_eq!(urls[3], urls[1]);
+}
+
+#[tokio::test]
+async fn test_mirrors_skip_unhealthy() {
+    let failing = serve_stub("503 Service Unavailable", "", Duration::ZERO)
+        .await
+        .0;
+    let (closed, _socket) = closed_addr();
+    let healthy = serve_stub("404 Not Found", "", Duration::ZERO).await.0;
+    add_mirrored_bang(
+        "fh",
+        failing,
+        &[closed, healthy],
+        MirrorStrategy::FirstHealthy,
+    );
+    add_mirrored_bang("rnd", failing, &[closed, healthy], MirrorStrategy::Random);
+
+    let start = Instant::now();
+    check_mirrors(Duration::from_secs(1)).await;
+    eprintln!("Checking the mirrors took {:?}", start.elapsed());
+
+    let expected = format!("http://{healthy}/search?q=rust");
+    assert_eq!(resolve("rust !fh"), expected);
+    assert!((0..8).all(|_| resolve("rust !rnd") == expected));
+}
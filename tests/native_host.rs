@@ -0,0 +1,167 @@
+use std::{
+    io::{Read, Write},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use serde_json::{Value, json};
+
+const CONFIG: &str = r#"
+[bangs]
+default_search_template = "https://google.com/search?q={{{s}}}"
+
+[snippets]
+"@a" = "@b"
+"@b" = "x"
+
+[[bangs.source]]
+required = false
+filepath = "/nonexistent/bangs.json"
+
+[bangs.custom.jira]
+short_name = "Jira"
+template = "https://jira.example.com/browse/{{{s}}}"
+
+[bangs.custom.jiradocs]
+short_name = "Jira Docs"
+template = "https://docs.example.com/jira?q={{{s}}}"
+"#;
+
+fn encode(message: &Value) -> Vec<u8> {
+    let bytes = serde_json::to_vec(message).unwrap();
+    let mut encoded = u32::try_from(bytes.len()).unwrap().to_ne_bytes().to_vec();
+    encoded.extend(bytes);
+    encoded
+}
+
+fn decode(mut bytes: &[u8]) -> Vec<Value> {
+    let mut messages = vec![];
+    while let Some((len, rest)) = bytes.split_first_chunk::<4>() {
+        let (message, rest) = rest.split_at(u32::from_ne_bytes(*len) as usize);
+        messages.push(serde_json::from_slice(message).unwrap());
+        bytes = rest;
+    }
+    messages
+}
+
+/// Sends every request to a native host launched as a browser would, returning its responses.
+fn exchange(requests: &[Value]) -> Vec<Value> {
+    exchange_bytes(&requests.iter().flat_map(encode).collect::<Vec<_>>())
+}
+
+/// Sends `input` to a native host launched as a browser would, returning its responses.
+fn exchange_bytes(input: &[u8]) -> Vec<Value> {
+    static EXCHANGES: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "boom-native-host-{}-{}",
+        std::process::id(),
+        EXCHANGES.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("config.toml");
+    std::fs::write(&config, CONFIG).unwrap();
+
+    let mut host = Command::new(env!("CARGO_BIN_EXE_boom"))
+        .arg("--config")
+        .arg(&config)
+        .args(["native-host", "/path/to/boom.json", "boom@example.com"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut stdin = host.stdin.take().unwrap();
+    stdin.write_all(input).unwrap();
+    drop(stdin);
+
+    let mut stdout = vec![];
+    host.stdout
+        .take()
+        .unwrap()
+        .read_to_end(&mut stdout)
+        .unwrap();
+    assert!(host.wait().unwrap().success());
+    std::fs::remove_dir_all(dir).unwrap();
+
+    decode(&stdout)
+}
+
+#[test]
+fn test_native_host() {
+    let start = Instant::now();
+    let responses = exchange(&[
+        json!({"id": 1, "type": "resolve", "query": "PROJ-1 !jira"}),
+        json!({"id": 2, "type": "complete", "prefix": "!jira", "limit": 1}),
+        json!({"id": 3, "type": "list", "offset": 1}),
+        json!({"id": 4, "type": "search"}),
+        json!({"id": 5, "type": "resolve", "query": "@a !jira"}),
+    ]);
+    eprintln!("Exchanging 5 messages took {:?}", start.elapsed());
+
+    assert_eq!(
+        responses[0],
+        json!({"id": 1, "type": "resolved", "url": "https://jira.example.com/browse/PROJ-1"})
+    );
+    assert_eq!(
+        responses[1],
+        json!({"id": 2, "type": "completions", "completions": [
+            {"s": "Jira", "t": "jira", "u": "https://jira.example.com/browse/{{{s}}}"}
+        ]})
+    );
+    assert_eq!(responses[2]["total"], 2);
+    assert_eq!(responses[2]["bangs"].as_array().unwrap().len(), 1);
+    assert_eq!(responses[3]["id"], 4);
+    assert_eq!(responses[3]["type"], "error");
+    // Snippets are expanded once, as they are by the server
+    assert_eq!(responses[4]["url"], "https://jira.example.com/browse/%40b");
+}
+
+/// Oversized messages are answered by an error, without being held in memory.
+#[test]
+fn test_native_host_oversized() {
+    let len = 16 * 1024 * 1024;
+    let mut input = u32::try_from(len).unwrap().to_ne_bytes().to_vec();
+    input.resize(4 + len, b' ');
+    input.extend(encode(&json!({"id": 1, "type": "list"})));
+
+    let start = Instant::now();
+    let responses = exchange_bytes(&input);
+    eprintln!("Skipping a {len} byte message took {:?}", start.elapsed());
+
+    assert_eq!(responses.len(), 2);
+    assert_eq!(responses[0]["type"], "error");
+    assert_eq!(responses[1]["id"], 1);
+    assert_eq!(responses[1]["type"], "bangs");
+}
+
+#[test]
+fn test_native_host_manifest() {
+    let manifest = |browser, extension| {
+        let output = Command::new(env!("CARGO_BIN_EXE_boom"))
+            .args([
+                "native-host",
+                "--manifest",
+                browser,
+                "--path",
+                "/usr/bin/boom-host",
+            ])
+            .args(["--extension", extension])
+            .output()
+            .unwrap();
+        serde_json::from_slice::<Value>(&output.stdout).unwrap()
+    };
+
+    let firefox = manifest("firefox", "boom@example.com");
+    assert_eq!(firefox["name"], "boom");
+    assert_eq!(firefox["path"], "/usr/bin/boom-host");
+    assert_eq!(firefox["type"], "stdio");
+    assert_eq!(firefox["allowed_extensions"], json!(["boom@example.com"]));
+
+    let chromium = manifest("chromium", "knldjmfmopnpolahpmmgbagdohdnhkik");
+    assert_eq!(
+        chromium["allowed_origins"],
+        json!(["chrome-extension://knldjmfmopnpolahpmmgbagdohdnhkik/"])
+    );
+}
@@ -0,0 +1,184 @@
+#![cfg(feature = "plugins")]
+
+use std::{env, fs, sync::Once, time::Instant};
+
+use boom_config::{Config, PluginConfig};
+use boom_core::{
+    boom::{
+        plugins::{Plugin, PluginRequest, load_plugins},
+        resolver::resolve_with_context,
+    },
+    cache::{init_list, set_plugins},
+    context::RequestContext,
+};
+
+/// Resolves queries such as `BOOM-123` to their ticket, passing on everything else.
+const TICKETS: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (data (i32.const 0) "{\"url\":\"https://jira.example.com/browse/")
+  (func (export "boom_alloc") (param i32) (result i32)
+    i32.const 1024)
+  (func (export "boom_resolve") (param $ptr i32) (param $len i32) (result i64)
+    (local $src i32) (local $dst i32) (local $c i32)
+    ;; The query follows `{"query":"`
+    (local.set $src (i32.add (local.get $ptr) (i32.const 10)))
+    (if (i32.ne (i32.load (local.get $src)) (i32.const 0x4D4F4F42)) ;; "BOOM"
+      (then (return (i64.const 0))))
+    (if (i32.ne (i32.load8_u offset=4 (local.get $src)) (i32.const 45)) ;; "-"
+      (then (return (i64.const 0))))
+    (local.set $dst (i32.const 40))
+    (block $done
+      (loop $copy
+        (local.set $c (i32.load8_u (local.get $src)))
+        (br_if $done (i32.eq (local.get $c) (i32.const 34))) ;; '"'
+        (br_if $done (i32.eq (local.get $c) (i32.const 32))) ;; ' '
+        (i32.store8 (local.get $dst) (local.get $c))
+        (local.set $src (i32.add (local.get $src) (i32.const 1)))
+        (local.set $dst (i32.add (local.get $dst) (i32.const 1)))
+        (br $copy)))
+    (i32.store16 (local.get $dst) (i32.const 0x7D22)) ;; "}
+    (i64.extend_i32_u (i32.add (local.get $dst) (i32.const 2)))))
+"#;
+
+/// Never returns, so is only stopped by running out of fuel.
+const SPIN: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "boom_alloc") (param i32) (result i32)
+    i32.const 1024)
+  (func (export "boom_resolve") (param i32 i32) (result i64)
+    (loop $spin (br $spin))
+    unreachable))
+"#;
+
+/// Resolves everything to a url which is not http(s).
+const UNSAFE: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (data (i32.const 0) "{\"url\":\"javascript:alert(1)\"}")
+  (func (export "boom_alloc") (param i32) (result i32)
+    i32.const 1024)
+  (func (export "boom_resolve") (param i32 i32) (result i64)
+    i64.const 29))
+"#;
+
+/// Responds with far more than its memory holds.
+const OVERSIZED: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "boom_alloc") (param i32) (result i32)
+    i32.const 1024)
+  (func (export "boom_resolve") (param i32 i32) (result i64)
+    i64.const 0xFFFFFFFF))
+"#;
+
+fn compile(name: &str, wat: &str) -> Plugin {
+    Plugin::compile(
+        name.to_string(),
+        &wat::parse_str(wat).unwrap(),
+        &PluginConfig {
+            fuel: 100_000,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+static LOAD_PLUGINS: Once = Once::new();
+
+fn load() {
+    LOAD_PLUGINS.call_once(|| {
+        init_list(vec![], true).unwrap();
+        set_plugins(vec![
+            compile("spin", SPIN),
+            compile("unsafe", UNSAFE),
+            compile("tickets", TICKETS),
+        ])
+        .unwrap();
+    });
+}
+
+#[test]
+fn test_plugin_resolves() {
+    let plugin = compile("tickets", TICKETS);
+    let context = RequestContext::new();
+
+    let start = Instant::now();
+    let url = plugin
+        .resolve(&PluginRequest::new("BOOM-123", None, &context))
+        .unwrap();
+    eprintln!("Running the plugin took {:?}", start.elapsed());
+
+    assert_eq!(
+        url.as_deref(),
+        Some("https://jira.example.com/browse/BOOM-123")
+    );
+    assert_eq!(
+        plugin
+            .resolve(&PluginRequest::new("rust", None, &context))
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_plugin_limits() {
+    let context = RequestContext::new();
+    let request = PluginRequest::new("BOOM-123", None, &context);
+
+    let start = Instant::now();
+    assert!(compile("spin", SPIN).resolve(&request).is_err());
+    eprintln!("Running out of fuel took {:?}", start.elapsed());
+
+    assert!(compile("unsafe", UNSAFE).resolve(&request).is_err());
+    assert!(compile("oversized", OVERSIZED).resolve(&request).is_err());
+
+    // Plugins cannot reach outside of their sandbox
+    let imports = r#"(module (import "env" "exit" (func)) (memory (export "memory") 1))"#;
+    assert!(
+        Plugin::compile(
+            "imports".to_string(),
+            &wat::parse_str(imports).unwrap(),
+            &PluginConfig::default()
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_resolve_with_plugins() {
+    load();
+    let config = Config::default();
+    let context = RequestContext::new();
+
+    // Plugins which fail are passed over
+    let mut url = String::new();
+    resolve_with_context("BOOM-123", &config, &context, &mut url);
+    assert_eq!(url, "https://jira.example.com/browse/BOOM-123");
+
+    url.clear();
+    resolve_with_context("rust", &config, &context, &mut url);
+    assert_eq!(url, "https://google.com/search?q=rust");
+
+    // Queries with unknown bangs are given to plugins too
+    url.clear();
+    resolve_with_context("BOOM-7 !unknown", &config, &context, &mut url);
+    assert_eq!(url, "https://jira.example.com/browse/BOOM-7");
+}
+
+#[test]
+fn test_load_plugins() {
+    let dir = env::temp_dir().join(format!("boom-plugins-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("b.wasm"), wat::parse_str(TICKETS).unwrap()).unwrap();
+    fs::write(dir.join("a.wasm"), wat::parse_str(SPIN).unwrap()).unwrap();
+    fs::write(dir.join("invalid.wasm"), b"not a module").unwrap();
+    fs::write(dir.join("notes.txt"), b"ignored").unwrap();
+
+    let plugins = load_plugins(&dir, &PluginConfig::default());
+    let names: Vec<_> = plugins.iter().map(|plugin| plugin.name.as_str()).collect();
+    assert_eq!(names, vec!["a", "b"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
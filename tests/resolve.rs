@@ -0,0 +1,150 @@
+use std::{env, sync::Once, time::Instant};
+
+use boom_config::{Config, SnippetConfig};
+use boom_core::{
+    SourceIdentifier,
+    boom::{
+        parse_bangs::parse_bang_file,
+        resolver::{resolve, resolve_into, resolve_with_context},
+    },
+    cache::{init_list, insert_bang, set_default_template},
+    context::RequestContext,
+    template::Template,
+};
+
+const QUERIES: [&str; 6] = [
+    "!yt this is a test",
+    "please work this is a test pelase work",
+    "i was typing and !d forgot i wanted dictionary",
+    "tobybridle/boom !gh",
+    "ünïcödé & 100% !w",
+    "!notabang at all",
+];
+
+static LOAD_BANGS: Once = Once::new();
+
+fn load_bangs() {
+    LOAD_BANGS.call_once(load_bangs_once);
+}
+
+fn load_bangs_once() {
+    let bangs = parse_bang_file(&{
+        let mut d = env::current_dir().unwrap();
+        d.push("bangs.json");
+        d
+    })
+    .expect("bangs.json should be present in the CWD!");
+    bangs.iter().enumerate().for_each(|(idx, bang)| {
+        insert_bang(bang.trigger.clone(), idx).unwrap();
+    });
+    init_list(bangs, true).unwrap();
+    set_default_template(
+        Template::compile(Config::default().bangs.default_search_template).unwrap(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_resolve_into_matches_resolve() {
+    load_bangs();
+    let config = Config::default();
+    let mut url = String::new();
+
+    for query in QUERIES {
+        url.clear();
+        let timer = Instant::now();
+        resolve_into(query, &config, &mut url);
+        eprintln!(
+            "Took {:?} to resolve {query:?} into a buffer.",
+            timer.elapsed()
+        );
+        assert_eq!(url, resolve(query, &config, &SourceIdentifier::default()));
+    }
+}
+
+#[test]
+fn test_resolve_with_context() {
+    load_bangs();
+    let mut config = Config::default();
+    config.bangs.default_search_template =
+        "https://google.com/search?q={{{s}}}&hl={{{lang}}}&si={{{si}}}".to_string();
+    let si = SourceIdentifier::Identifier("firefox".to_string());
+    let context = RequestContext::new()
+        .with_accept_language("fr-CH, fr;q=0.9, en;q=0.8")
+        .with_source_identifier(&si);
+
+    let mut url = String::new();
+    let timer = Instant::now();
+    resolve_with_context("croissant", &config, &context, &mut url);
+    eprintln!("Took {:?} to resolve with a context.", timer.elapsed());
+    assert_eq!(
+        url,
+        "https://google.com/search?q=croissant&hl=fr-CH&si=firefox"
+    );
+
+    // Anything missing from the context is left empty
+    url.clear();
+    resolve_into("croissant", &config, &mut url);
+    assert_eq!(url, "https://google.com/search?q=croissant&hl=&si=");
+}
+
+#[test]
+fn test_resolve_snippets() {
+    load_bangs();
+    let mut config = Config::default();
+    config.snippets.insert(
+        "@org".to_string(),
+        SnippetConfig::Value("tobybridle".to_string()),
+    );
+    config.snippets.insert(
+        "@repo".to_string(),
+        SnippetConfig::Scoped {
+            value: "tobybridle/boom".to_string(),
+            bangs: vec!["gh".to_string()],
+        },
+    );
+
+    for (query, expanded) in [
+        ("@org/boom !gh", "tobybridle/boom !gh"),
+        ("issues @repo !gh", "issues tobybridle/boom !gh"),
+        ("@repo !yt", "@repo !yt"),
+        ("@org", "tobybridle"),
+    ] {
+        let timer = Instant::now();
+        let url = resolve(query, &config, &SourceIdentifier::default());
+        eprintln!("Took {:?} to resolve {query:?}.", timer.elapsed());
+        assert_eq!(
+            url,
+            resolve(expanded, &Config::default(), &SourceIdentifier::default()),
+            "{query:?}"
+        );
+    }
+}
+
+#[cfg(feature = "measure-allocs")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_into_zero_allocations() {
+        load_bangs();
+        let config = Config::default();
+        let mut url = String::new();
+
+        for query in QUERIES {
+            // Grow the buffer to fit, as a reused buffer would have been
+            url.clear();
+            resolve_into(query, &config, &mut url);
+
+            let alloc = allocation_counter::measure(|| {
+                url.clear();
+                resolve_into(query, &config, &mut url);
+            });
+            eprintln!(
+                "Resolving {query:?} into a buffer made {} allocations",
+                alloc.count_total
+            );
+            assert_eq!(alloc.count_total, 0, "{query:?} allocated");
+        }
+    }
+}
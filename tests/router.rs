@@ -0,0 +1,298 @@
+use std::time::{Duration, Instant};
+
+use axum::{
+    Router,
+    body::{Body, to_bytes},
+    http::{Request, StatusCode, header},
+    routing::{get, post},
+};
+use boom_config::{BangParamConfig, Config, ConfigBuilder};
+use boom_core::{Redirect, cache::update_redirect};
+use boom_web::{router, watch_config};
+use tower::ServiceExt;
+
+const CONFIG: &str = r#"
+[bangs]
+default_search_template = "https://google.com/search?q={{{s}}}"
+
+[[bangs.source]]
+required = false
+filepath = "/nonexistent/bangs.json"
+
+[bangs.custom.docs]
+short_name = "Docs"
+template = "https://docs.example.com?q={{{s}}}"
+"#;
+
+/// Requests `uri` of `app`, returning the status and `Location` of the response.
+async fn get_location(app: &Router, uri: &str) -> (StatusCode, Option<String>) {
+    let response = app
+        .clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let location = response
+        .headers()
+        .get(header::LOCATION)
+        .map(|location| location.to_str().unwrap().to_string());
+    (response.status(), location)
+}
+
+#[tokio::test]
+async fn test_router_nested() {
+    update_redirect(&Redirect {
+        short_name: "Jira".to_string(),
+        trigger: "jira".to_string(),
+        url_template: "https://jira.example.com/browse/{{{s}}}".to_string(),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let start = Instant::now();
+    let (boom, _state) = router(&Config::default());
+    let app = Router::new()
+        .route("/health", get(|| async { "OK" }))
+        .nest("/boom", boom);
+    eprintln!("Building the router took {:?}", start.elapsed());
+
+    let jira = Some("https://jira.example.com/browse/PROJ-1".to_string());
+    assert_eq!(
+        get_location(&app, "/boom?q=PROJ-1%20!jira").await,
+        (StatusCode::SEE_OTHER, jira.clone())
+    );
+    assert_eq!(
+        get_location(&app, "/boom/jira/PROJ-1").await,
+        (StatusCode::SEE_OTHER, jira)
+    );
+    assert_eq!(
+        get_location(&app, "/boom/unknown").await,
+        (StatusCode::NOT_FOUND, None)
+    );
+    assert_eq!(get_location(&app, "/health").await, (StatusCode::OK, None));
+    assert_eq!(
+        get_location(&app, "/jira/PROJ-1").await,
+        (StatusCode::NOT_FOUND, None)
+    );
+}
+
+/// The pages served under a prefix link to it, so the form asking for parameters is submitted back
+/// to `boom` rather than to the app it is mounted within.
+#[tokio::test]
+async fn test_router_nested_params_form() {
+    update_redirect(&Redirect {
+        short_name: "Tickets".to_string(),
+        trigger: "tix".to_string(),
+        url_template: "https://tickets.example.com/{{{project}}}?q={{{s}}}".to_string(),
+        params: vec![BangParamConfig {
+            name: "project".to_string(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    })
+    .unwrap();
+
+    let (boom, _state) = router(&Config::default());
+    let app = Router::new()
+        .route("/", post(|| async { "Host" }))
+        .nest("/boom", boom);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/boom?q=outage%20!tix")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let page = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let page = String::from_utf8_lossy(&page);
+    assert!(page.contains(r#"<base href="/boom/" />"#), "{page}");
+    assert!(page.contains(r#"href="assets/layout.css""#), "{page}");
+    let action = page
+        .split_once(r#"<form method="POST" action=""#)
+        .and_then(|(_, rest)| rest.split_once('"'))
+        .map(|(action, _)| action)
+        .unwrap();
+    assert_eq!(action, "/boom");
+
+    let start = Instant::now();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post(action)
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from("q=outage+%21tix&si=&param.project=OPS"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    eprintln!("Submitting the parameters took {:?}", start.elapsed());
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(
+        response.headers()[header::LOCATION],
+        "https://tickets.example.com/OPS?q=outage"
+    );
+
+    assert_eq!(
+        get_location(&app, "/boom/assets/layout.css").await,
+        (StatusCode::OK, None)
+    );
+
+    // Submitting without a query leads back to the homepage of `boom`
+    let response = app
+        .oneshot(
+            Request::post(action)
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from("q="))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[header::LOCATION], "/boom");
+}
+
+/// Paths of the pseudo-host which name no bang are served as they are for any other host, so that
+/// the homepage loads its assets.
+#[tokio::test]
+async fn test_router_pseudo_host() {
+    update_redirect(&Redirect {
+        short_name: "Wiki".to_string(),
+        trigger: "wiki".to_string(),
+        url_template: "https://wiki.example.com?q={{{s}}}".to_string(),
+        ..Default::default()
+    })
+    .unwrap();
+    let (app, _state) = router(&Config::default());
+    let get_go = |uri: &str| {
+        app.clone().oneshot(
+            Request::get(uri)
+                .header(header::HOST, "go")
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+
+    let start = Instant::now();
+    let response = get_go("/wiki/rust").await.unwrap();
+    eprintln!("Resolving a go-link took {:?}", start.elapsed());
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(
+        response.headers()[header::LOCATION],
+        "https://wiki.example.com?q=rust"
+    );
+
+    assert_eq!(get_go("/").await.unwrap().status(), StatusCode::OK);
+    assert_eq!(
+        get_go("/assets/layout.css").await.unwrap().status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        get_go("/unknown").await.unwrap().status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+/// Listening on every address, the PAC file proxies through the requested host, unless it is not
+/// a plain `host[:port]`.
+#[tokio::test]
+async fn test_router_proxy_pac() {
+    let mut config = Config::default();
+    config.server.address = "0.0.0.0".parse().unwrap();
+    let (app, _state) = router(&config);
+    let pac = |host: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::get("/proxy.pac")
+                        .header(header::HOST, host)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let script = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            String::from_utf8(script.to_vec()).unwrap()
+        }
+    };
+
+    assert!(
+        pac("boom.lan:3000")
+            .await
+            .contains(r#"return "PROXY boom.lan:3000";"#)
+    );
+    let script = pac(r#"x";alert(1);""#).await;
+    assert!(
+        script.contains(r#"return "PROXY 0.0.0.0:3000";"#),
+        "{script}"
+    );
+}
+
+/// The config is reloaded without blocking the (single-threaded) runtime.
+#[tokio::test]
+async fn test_watch_config() {
+    let dir = std::env::temp_dir().join(format!("boom-router-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    std::fs::write(&path, CONFIG).unwrap();
+
+    let config = ConfigBuilder::new().add_source(&path).to_owned().build();
+    let (_, state) = router::<()>(&config);
+    let watcher = watch_config(state.shared_config().clone());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let start = Instant::now();
+    std::fs::write(&path, CONFIG.replace("google.com", "duckduckgo.com")).unwrap();
+    let reloaded = loop {
+        let template = state
+            .shared_config()
+            .read()
+            .unwrap()
+            .bangs
+            .default_search_template
+            .clone();
+        if template.contains("duckduckgo.com") || start.elapsed() > Duration::from_secs(10) {
+            break template;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    };
+    eprintln!("Reloading the config took {:?}", start.elapsed());
+
+    watcher.abort();
+    std::fs::remove_dir_all(dir).unwrap();
+    assert_eq!(reloaded, "https://duckduckgo.com/search?q={{{s}}}");
+}
+
+/// Queries are recorded by the `record` stage of the pipeline, so removing it stops the server
+/// recording them.
+#[cfg(feature = "history")]
+#[tokio::test]
+async fn test_router_records_through_pipeline() {
+    use boom_core::{
+        SourceIdentifier,
+        boom::pipeline::Pipeline,
+        cache::{SEARCH_HISTORY_CACHE, set_pipeline},
+    };
+
+    let recorded = || {
+        SEARCH_HISTORY_CACHE
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|entry| entry.source_identifier == SourceIdentifier::from("router".to_string()))
+            .count()
+    };
+    let (app, _state) = router(&Config::default());
+
+    get_location(&app, "/?q=rust&si=router").await;
+    assert_eq!(recorded(), 1);
+
+    let mut pipeline = Pipeline::default();
+    assert!(pipeline.remove("record").is_some());
+    set_pipeline(pipeline).unwrap();
+    get_location(&app, "/?q=rust&si=router").await;
+    set_pipeline(Pipeline::default()).unwrap();
+    assert_eq!(recorded(), 1);
+}
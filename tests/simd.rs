@@ -0,0 +1,109 @@
+//! Differential tests, checking that the SIMD parsers agree with their scalar counterparts.
+#![cfg(target_arch = "x86_64")]
+
+use std::{env, fs, path::PathBuf, time::Instant};
+
+use boom_core::boom::{
+    parse_bangs::{parse_bang_indexes_avx2, parse_bang_indexes_scalar},
+    parse_templates::{parse_template_indexes_avx2, parse_template_indexes_scalar},
+    simd_available,
+};
+
+/// How many random inputs to compare, per parser.
+const RANDOM_INPUTS: usize = 50_000;
+
+/// Characters which are interesting to the parsers, alongside some filler.
+const ALPHABET: [&str; 12] = [
+    "!", " ", "{", "}", "s", "{{{s}}}", "a", "gh", "é", "日", "\t", "!!",
+];
+
+/// A xorshift generator, so that failures are reproducible without any extra dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn string(&mut self) -> String {
+        let len = self.below(160);
+        (0..len)
+            .map(|_| ALPHABET[self.below(ALPHABET.len())])
+            .collect()
+    }
+}
+
+fn corpus(target: &str) -> Vec<String> {
+    let dir: PathBuf = [
+        env::current_dir().unwrap(),
+        "fuzz/corpus".into(),
+        target.into(),
+    ]
+    .iter()
+    .collect();
+    fs::read_dir(&dir)
+        .expect("The fuzz corpus should be present in the CWD!")
+        .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect()
+}
+
+fn inputs(target: &str, seed: u64) -> Vec<String> {
+    let mut rng = Rng(seed);
+    corpus(target)
+        .into_iter()
+        .chain((0..RANDOM_INPUTS).map(|_| rng.string()))
+        .collect()
+}
+
+#[test]
+fn test_simd_bangs_match_scalar() {
+    if !simd_available() {
+        eprintln!("AVX2 is unavailable, skipping.");
+        return;
+    }
+
+    let inputs = inputs("parse_bangs", 0x0b00_b00b);
+    let timer = Instant::now();
+    for query in &inputs {
+        // SAFETY: AVX2 is supported.
+        let simd = unsafe { parse_bang_indexes_avx2(query) };
+        assert_eq!(simd, parse_bang_indexes_scalar(query), "{query:?}");
+    }
+    eprintln!(
+        "Took {:?} to compare {} queries.",
+        timer.elapsed(),
+        inputs.len()
+    );
+}
+
+#[test]
+fn test_simd_templates_match_scalar() {
+    if !simd_available() {
+        eprintln!("AVX2 is unavailable, skipping.");
+        return;
+    }
+
+    let inputs = inputs("parse_templates", 0x7e3b_1a7e);
+    let timer = Instant::now();
+    for template in &inputs {
+        // SAFETY: AVX2 is supported.
+        let simd = unsafe { parse_template_indexes_avx2(template) };
+        assert_eq!(
+            simd,
+            parse_template_indexes_scalar(template),
+            "{template:?}"
+        );
+    }
+    eprintln!(
+        "Took {:?} to compare {} templates.",
+        timer.elapsed(),
+        inputs.len()
+    );
+}
@@ -0,0 +1,72 @@
+use std::{env, path::PathBuf, time::Instant};
+
+use boom_core::{Redirect, boom::parse_bangs::parse_bang_file, store::BangStore};
+
+fn bangs_path() -> PathBuf {
+    let mut d = env::current_dir().unwrap();
+    d.push("bangs.json");
+    d
+}
+
+fn load_redirects() -> Vec<Redirect> {
+    parse_bang_file(&bangs_path()).expect("bangs.json should be present in the CWD!")
+}
+
+#[test]
+fn test_store_matches_redirects() {
+    let redirects = load_redirects();
+    let timer = Instant::now();
+    let store: BangStore = redirects.clone().into_iter().collect();
+    eprintln!(
+        "Took {:?} to store {} bangs.",
+        timer.elapsed(),
+        redirects.len()
+    );
+
+    assert_eq!(store.len(), redirects.len());
+    for (bang, redirect) in store.iter().zip(&redirects) {
+        assert_eq!(bang.short_name(), redirect.short_name);
+        assert_eq!(bang.trigger(), redirect.trigger);
+        assert_eq!(bang.url_template(), redirect.url_template);
+        assert_eq!(bang.relevance(), redirect.relevance);
+    }
+}
+
+#[cfg(feature = "measure-allocs")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_memory() {
+        let redirects = load_redirects();
+
+        let owned = allocation_counter::measure(|| {
+            let copy = redirects.clone();
+            assert_eq!(copy.len(), redirects.len());
+        });
+        let stored = allocation_counter::measure(|| {
+            let store: BangStore = redirects.clone().into_iter().collect();
+            eprintln!(
+                "`BangStore` reports {} bytes on the heap",
+                store.heap_size()
+            );
+        });
+        let parsed = allocation_counter::measure(|| {
+            let store: BangStore = load_redirects().into_iter().collect();
+            assert!(!store.is_empty());
+        });
+
+        eprintln!(
+            "`Vec<Redirect>` held {} bytes over {} allocations",
+            owned.bytes_max, owned.count_total
+        );
+        eprintln!(
+            "`BangStore` peaked at {} bytes (including the consumed `Vec<Redirect>`), {} bytes over its lifetime",
+            stored.bytes_max, stored.bytes_total
+        );
+        eprintln!(
+            "Loading `bangs.json` into a `BangStore` peaked at {} bytes, {} bytes over its lifetime",
+            parsed.bytes_max, parsed.bytes_total
+        );
+    }
+}
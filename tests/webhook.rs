@@ -0,0 +1,131 @@
+use std::{
+    net::SocketAddr,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use boom_config::{Config, WebhookConfig};
+use boom_core::{
+    Redirect,
+    boom::resolver::{Resolution, try_resolve_with_context},
+    cache::update_redirect,
+    context::RequestContext,
+};
+use common::{bang, serve_stub};
+
+mod common;
+
+/// Adds the bang `trigger`, asking the stub at `addr` for its url.
+fn add_webhook_bang(trigger: &str, addr: SocketAddr, webhook: WebhookConfig) {
+    update_redirect(&Redirect {
+        webhook: Some(webhook),
+        ..bang(trigger, format!("http://{addr}/builds/{{{{{{s}}}}}}"))
+    })
+    .unwrap();
+}
+
+/// Resolves `query`, expecting it to be given by a webhook, along with the fallback url.
+async fn fetch(query: &str) -> (Option<String>, String) {
+    let mut url = String::new();
+    let Ok(Resolution::Webhook(call)) =
+        try_resolve_with_context(query, &Config::default(), &RequestContext::new(), &mut url)
+    else {
+        panic!("{query} should be given by a webhook");
+    };
+    (call.fetch().await, url)
+}
+
+#[tokio::test]
+async fn test_webhook_resolves_url() {
+    let (addr, hits) = serve_stub(
+        "200 OK",
+        r#"{"url":"https://ci.example.com/builds/42"}"#,
+        Duration::ZERO,
+    )
+    .await;
+    add_webhook_bang(
+        "ci",
+        addr,
+        WebhookConfig {
+            fallback: Some("https://ci.example.com/search?q={{{s}}}".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let start = Instant::now();
+    let (target, fallback) = fetch("main !ci").await;
+    eprintln!("Fetching from the webhook took {:?}", start.elapsed());
+
+    assert_eq!(target.as_deref(), Some("https://ci.example.com/builds/42"));
+    assert_eq!(fallback, "https://ci.example.com/search?q=main");
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_webhook_caches_url() {
+    let (addr, hits) = serve_stub(
+        "200 OK",
+        r#"{"url":"https://ci.example.com/builds/42"}"#,
+        Duration::ZERO,
+    )
+    .await;
+    add_webhook_bang(
+        "cached",
+        addr,
+        WebhookConfig {
+            cache_ttl: 60,
+            ..Default::default()
+        },
+    );
+
+    let (first, _) = fetch("main !cached").await;
+    let start = Instant::now();
+    let (second, _) = fetch("main !cached").await;
+    eprintln!("Fetching a cached url took {:?}", start.elapsed());
+
+    assert_eq!(first, second);
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+    // Other queries are not given the cached url
+    fetch("develop !cached").await;
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_webhook_timeout_falls_back() {
+    let (addr, _) = serve_stub(
+        "200 OK",
+        r#"{"url":"https://ci.example.com/builds/42"}"#,
+        Duration::from_secs(5),
+    )
+    .await;
+    add_webhook_bang(
+        "slow",
+        addr,
+        WebhookConfig {
+            timeout_ms: 100,
+            ..Default::default()
+        },
+    );
+
+    let start = Instant::now();
+    let (target, fallback) = fetch("main !slow").await;
+    let elapsed = start.elapsed();
+    eprintln!("Timing out took {elapsed:?}");
+
+    assert_eq!(target, None);
+    assert!(elapsed < Duration::from_secs(5));
+    // Without a fallback, the default search is used
+    assert!(fallback.contains("main"));
+}
+
+#[tokio::test]
+async fn test_webhook_rejects_non_http_url() {
+    let (addr, hits) =
+        serve_stub("200 OK", r#"{"url":"javascript:alert(1)"}"#, Duration::ZERO).await;
+    add_webhook_bang("unsafe", addr, WebhookConfig::default());
+
+    let (target, _) = fetch("main !unsafe").await;
+    assert_eq!(target, None);
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+}